@@ -6,8 +6,9 @@ use std::fmt;
 #[derive(Debug, Serialize)]
 pub struct Empty;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Default)]
 pub enum ApiStatusCode {
+    #[default]
     Ok = 200,
     BadRequest = 400,
     Unauthorized = 401,
@@ -65,12 +66,6 @@ impl fmt::Display for ApiStatusCode {
     }
 }
 
-impl Default for ApiStatusCode {
-    fn default() -> ApiStatusCode {
-        ApiStatusCode::Ok
-    }
-}
-
 #[derive(Serialize)]
 pub struct ApiResponse<T>
 where
@@ -0,0 +1,141 @@
+use std::sync::Arc;
+
+use actix_web::{
+    dev::Payload,
+    error::{ErrorForbidden, ErrorInternalServerError, ErrorUnauthorized},
+    http::header,
+    web, Error, FromRequest, HttpMessage, HttpRequest,
+};
+use futures_util::future::{ready, LocalBoxFuture};
+use futures_util::FutureExt;
+use service::{
+    _entities::sea_orm_active_enums::TeamUserRoles,
+    permission::Permission,
+    sea_orm::{prelude::Uuid, DbErr},
+    utils::jwt::TokenInfo,
+    Service,
+};
+
+use crate::guards::auth::authenticate;
+
+/// The authenticated caller. The auth guard stores it once it validated the
+/// token, so handlers read it back instead of decoding the token again.
+#[derive(Debug, Clone)]
+pub struct CurrentUser {
+    pub user_id: Uuid,
+    /// Platform admin behind an impersonation token.
+    pub actor_id: Option<Uuid>,
+    /// Session the access token belongs to, `None` for API keys and
+    /// impersonation tokens.
+    pub session_id: Option<Uuid>,
+    /// The verified access token, `None` for API keys.
+    pub token: Option<TokenInfo>,
+}
+
+impl CurrentUser {
+    /// Authenticates a request the guard let through without checking its
+    /// token, e.g. a public route called with one.
+    async fn resolve(req: &HttpRequest) -> Result<Self, Error> {
+        let token = req
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .ok_or(ErrorUnauthorized(
+                "Authorization header not found or missing Bearer token",
+            ))?;
+
+        authenticate(req, token).await
+    }
+}
+
+impl FromRequest for CurrentUser {
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        if let Some(user) = req.extensions().get::<CurrentUser>() {
+            return ready(Ok(user.clone())).boxed_local();
+        }
+
+        let req = req.clone();
+        async move {
+            let user = Self::resolve(&req).await?;
+            req.extensions_mut().insert(user.clone());
+            Ok(user)
+        }
+        .boxed_local()
+    }
+}
+
+/// The caller as a member of the team addressed by the `team_id` path
+/// segment, with the permissions they hold there.
+#[derive(Debug, Clone)]
+pub struct TeamMember {
+    pub user_id: Uuid,
+    pub team_id: Uuid,
+    pub role: TeamUserRoles,
+    pub permissions: Vec<Permission>,
+}
+
+impl TeamMember {
+    pub fn can(&self, permission: Permission) -> bool {
+        self.permissions.contains(&permission)
+    }
+
+    /// `Err` is a 403 naming the missing permission, meant for `?`.
+    pub fn require(&self, permission: Permission) -> Result<(), Error> {
+        if self.can(permission) {
+            Ok(())
+        } else {
+            Err(ErrorForbidden(permission.denied()))
+        }
+    }
+}
+
+impl FromRequest for TeamMember {
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let user = CurrentUser::from_request(req, payload);
+        let req = req.clone();
+        async move {
+            let user = user.await?;
+            let team_id = req
+                .match_info()
+                .get("team_id")
+                .and_then(|id| id.parse::<Uuid>().ok())
+                .ok_or(actix_web::error::ErrorBadRequest("Invalid team id"))?;
+
+            let service = service_of(&req)?;
+            let member = service
+                .team_user_service
+                .find_joined_member(team_id, user.user_id)
+                .await
+                .map_err(|e| match e {
+                    DbErr::Custom(_) => ErrorForbidden("You are not a member of this team"),
+                    e => ErrorInternalServerError(e),
+                })?;
+            let permissions = service
+                .permission_service
+                .permissions_of(&member)
+                .await
+                .map_err(ErrorInternalServerError)?;
+
+            Ok(Self {
+                user_id: user.user_id,
+                team_id,
+                role: member.role,
+                permissions,
+            })
+        }
+        .boxed_local()
+    }
+}
+
+fn service_of(req: &HttpRequest) -> Result<Arc<Service>, Error> {
+    req.app_data::<web::Data<Arc<Service>>>()
+        .map(|service| service.get_ref().clone())
+        .ok_or(ErrorInternalServerError("Service is not configured"))
+}
@@ -1,10 +1,163 @@
 use std::sync::Arc;
 
-use actix_web::dev::ServiceRequest;
+use actix_web::{
+    dev::ServiceRequest,
+    error::{ErrorForbidden, ErrorInternalServerError, ErrorUnauthorized},
+    http::Method,
+    rt, web, Error, HttpMessage, HttpRequest,
+};
 use actix_web_httpauth::extractors::bearer::BearerAuth;
-use service::Service;
+use service::{
+    common::api_key_service::{ApiKeyPrincipal, ApiKeyService},
+    Service,
+};
 
-use crate::settings::AppSettings;
+use crate::extractors::CurrentUser;
+
+use super::public_routes::{PublicRoutes, RouteAccess};
+
+/// Routes a token of an account with unfinished setup may still call.
+const LIMITED_ROUTES: [&str; 7] = [
+    "/api/v1/auth/info",
+    "/api/v1/auth/logout",
+    "/api/v1/auth/refresh-token",
+    "/api/v1/auth/verify-email",
+    "/api/v1/auth/verify-email/resend",
+    "/api/v1/auth/mfa/totp/setup",
+    "/api/v1/auth/mfa/totp/confirm",
+];
+
+/// Routes an admin impersonating a user may never call, matched by prefix.
+const IMPERSONATION_REFUSED_ROUTES: [&str; 9] = [
+    "/api/v1/auth/change-password",
+    "/api/v1/auth/deactivate",
+    "/api/v1/auth/account",
+    "/api/v1/auth/update-info",
+    "/api/v1/auth/mfa/",
+    "/api/v1/auth/sessions",
+    "/api/v1/auth/identities",
+    "/api/v1/auth/oidc/link",
+    "/api/v1/api-key/",
+];
+
+/// Routes only platform admins may call.
+const ADMIN_PREFIX: &str = "/api/v1/admin/";
+
+/// Scope an API key needs for the route, `None` when keys may not call it at all.
+/// Reads need `<area>:read`, everything else `<area>:write`.
+fn api_key_scope(req: &HttpRequest) -> Option<&'static str> {
+    let path = req.path().strip_prefix("/api/v1/")?;
+    let read = req.method() == Method::GET;
+
+    match path.split('/').next()? {
+        "team" => Some(if read { "team:read" } else { "team:write" }),
+        "auth" if path == "auth/info" && read => Some("user:read"),
+        _ => None,
+    }
+}
+
+fn check_api_key(req: &HttpRequest, principal: &ApiKeyPrincipal) -> Result<(), &'static str> {
+    let scope = api_key_scope(req).ok_or("API keys can not access this route")?;
+    if !principal.has_scope(scope) {
+        return Err("API key is missing the scope for this route");
+    }
+
+    // Team keys only reach routes addressing their own team.
+    if let Some(team_id) = principal.team_id {
+        let team_id = team_id.to_string();
+        if !req.path().split('/').any(|segment| segment == team_id) {
+            return Err("Team API key can only access its own team");
+        }
+    }
+
+    Ok(())
+}
+
+/// Resolve the bearer token or API key of a request to its caller, with every
+/// check the route needs. Used by the guard and by `CurrentUser` on routes the
+/// guard let through without looking at the token.
+pub async fn authenticate(req: &HttpRequest, token: &str) -> Result<CurrentUser, Error> {
+    let service = req
+        .app_data::<web::Data<Arc<Service>>>()
+        .ok_or(ErrorInternalServerError("Service is not configured"))?;
+
+    if ApiKeyService::is_api_key(token) {
+        let principal = service
+            .api_key_service
+            .authenticate(token)
+            .await
+            .map_err(|_| ErrorUnauthorized("Invalid API key"))?;
+        // Keys stop working with the account that owns them.
+        if let Err(e) = service.user_service.ensure_active(principal.user_id).await {
+            log::info!("Refused API key of user {}: {}", principal.user_id, e);
+            return Err(ErrorUnauthorized("Invalid API key"));
+        }
+        check_api_key(req, &principal).map_err(ErrorForbidden)?;
+        return Ok(CurrentUser {
+            user_id: principal.user_id,
+            actor_id: None,
+            session_id: None,
+            token: None,
+        });
+    }
+
+    let info = service
+        .auth_service
+        .validate_token(token)
+        .await
+        .ok_or(ErrorUnauthorized("Invalid Bearer token"))?;
+
+    // Every request of an impersonation is audited, refused ones included.
+    if info.actor_id.is_some() {
+        if let Err(e) = service
+            .auth_service
+            .record_impersonated_request(&info, req.method().as_str(), req.path())
+            .await
+        {
+            log::error!("Record impersonated request error: {:#?}", e);
+            return Err(ErrorInternalServerError("Could not audit the request"));
+        }
+        if IMPERSONATION_REFUSED_ROUTES
+            .iter()
+            .any(|route| req.path().starts_with(route))
+        {
+            return Err(ErrorForbidden(
+                "This action is not allowed while impersonating a user",
+            ));
+        }
+    }
+
+    if info.limited && !LIMITED_ROUTES.contains(&req.path()) {
+        return Err(ErrorForbidden(
+            "Please verify your email or enable two-factor authentication first",
+        ));
+    }
+
+    if req.path().starts_with(ADMIN_PREFIX) {
+        match service.user_service.is_platform_admin(info.user_id).await {
+            Ok(true) => {}
+            Ok(false) => return Err(ErrorForbidden("Only platform admins can do this")),
+            Err(e) => return Err(ErrorInternalServerError(e)),
+        }
+    }
+
+    // `last_seen_at` is only shown in the session list, it can lag a little.
+    if let Some(session_id) = info.session_id {
+        let service = service.clone();
+        rt::spawn(async move {
+            if let Err(e) = service.session_service.touch_session(session_id).await {
+                log::error!("Touch session error: {:#?}", e);
+            }
+        });
+    }
+
+    Ok(CurrentUser {
+        user_id: info.user_id,
+        actor_id: info.actor_id,
+        session_id: info.session_id,
+        token: Some(info),
+    })
+}
 
 pub async fn bearer_validator(
     req: ServiceRequest,
@@ -12,46 +165,31 @@ pub async fn bearer_validator(
 ) -> Result<ServiceRequest, (actix_web::Error, ServiceRequest)> {
     log::info!("\n ================== Auth validator start ======================= \n");
 
+    let access = req
+        .app_data::<web::Data<PublicRoutes>>()
+        .and_then(|routes| routes.find(req.method(), req.path()));
+    if access == Some(RouteAccess::Public) {
+        return Ok(req);
+    }
+
     match credentials {
         Some(credentials) => {
             let token = credentials.token();
-            log::info!(
-                "\nBeaere validator uri: {:#?} \n token: {}",
-                &req.uri(),
-                token
-            );
-
-            let service = req
-                .app_data::<actix_web::web::Data<Arc<Service>>>()
-                .unwrap();
-            let bool = service.auth_service.verify_token(token).await;
-
-            if bool {
-                Ok(req)
-            } else {
-                Err((
-                    actix_web::error::ErrorUnauthorized("Invalid Bearer token"),
-                    req,
-                ))
+            log::debug!("Bearer validator uri: {}", req.uri());
+
+            match authenticate(req.request(), token).await {
+                Ok(user) => {
+                    req.extensions_mut().insert(user);
+                    Ok(req)
+                }
+                Err(e) => Err((e, req)),
             }
         }
+        None if access == Some(RouteAccess::Optional) => Ok(req),
         None => {
-            if let Some(settings) = req.app_data::<actix_web::web::Data<AppSettings>>() {
-                let uri = req.path().to_string();
-                log::info!("Check uri: {} is not validator authorized", &uri);
-                let route_white = settings
-                    .application
-                    .clone()
-                    .route_whites
-                    .unwrap_or(Vec::new());
-                if route_white.contains(&uri) {
-                    return Ok(req);
-                }
-            }
+            log::info!("Uri: {} requires authorization", req.path());
             Err((
-                actix_web::error::ErrorUnauthorized(
-                    "Authorization header not found or missing Bearer token",
-                ),
+                ErrorUnauthorized("Authorization header not found or missing Bearer token"),
                 req,
             ))
         }
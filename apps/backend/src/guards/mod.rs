@@ -1 +1,2 @@
 pub mod auth;
+pub mod public_routes;
@@ -0,0 +1,230 @@
+use actix_web::http::Method;
+
+use crate::settings::{AppSettings, RouteWhite};
+
+/// How the auth guard treats a request to a whitelisted route.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RouteAccess {
+    /// No token needed, one sent along is not checked either.
+    Public,
+    /// A token is validated when sent, but not required.
+    Optional,
+}
+
+#[derive(Debug)]
+enum Segment {
+    Literal(String),
+    /// A segment with `*` wildcards, like `*` or `*.json`.
+    Wildcard(String),
+    /// `**`, any number of segments including none.
+    Any,
+}
+
+impl Segment {
+    fn parse(segment: &str) -> Self {
+        match segment {
+            "**" => Segment::Any,
+            _ if segment.contains('*') => Segment::Wildcard(segment.to_string()),
+            _ => Segment::Literal(segment.to_string()),
+        }
+    }
+
+    /// Whether the segment matches a single path segment.
+    fn matches(&self, segment: &str) -> bool {
+        match self {
+            Segment::Literal(literal) => literal == segment,
+            Segment::Wildcard(pattern) => matches_wildcard(pattern, segment),
+            Segment::Any => true,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Rule {
+    segments: Vec<Segment>,
+    /// `None` matches every method.
+    methods: Option<Vec<Method>>,
+    access: RouteAccess,
+}
+
+impl Rule {
+    fn new(white: &RouteWhite) -> Result<Self, String> {
+        let (path, methods, optional) = match white {
+            RouteWhite::Path(path) => (path, None, false),
+            RouteWhite::Rule {
+                path,
+                methods,
+                optional,
+            } => (path, methods.as_ref(), optional.unwrap_or(false)),
+        };
+
+        if !path.starts_with('/') {
+            return Err(format!("Route white `{}` must start with `/`", path));
+        }
+        let methods = methods
+            .map(|methods| {
+                methods
+                    .iter()
+                    .map(|method| {
+                        Method::from_bytes(method.to_uppercase().as_bytes()).map_err(|_| {
+                            format!("Route white `{}` has an invalid method `{}`", path, method)
+                        })
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .transpose()?;
+
+        Ok(Self {
+            segments: path.split('/').map(Segment::parse).collect(),
+            methods,
+            access: if optional {
+                RouteAccess::Optional
+            } else {
+                RouteAccess::Public
+            },
+        })
+    }
+
+    fn matches(&self, method: &Method, path: &[&str]) -> bool {
+        self.methods
+            .as_ref()
+            .is_none_or(|methods| methods.contains(method))
+            && matches_segments(&self.segments, path)
+    }
+}
+
+fn matches_segments(segments: &[Segment], path: &[&str]) -> bool {
+    match segments.split_first() {
+        None => path.is_empty(),
+        Some((Segment::Any, rest)) => (0..=path.len()).any(|i| matches_segments(rest, &path[i..])),
+        Some((segment, rest)) => path
+            .split_first()
+            .is_some_and(|(first, tail)| segment.matches(first) && matches_segments(rest, tail)),
+    }
+}
+
+/// `*` stands for any run of characters within a single segment.
+fn matches_wildcard(pattern: &str, text: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == text,
+        Some((head, tail)) => text.strip_prefix(head).is_some_and(|text| {
+            (0..=text.len())
+                .filter(|i| text.is_char_boundary(*i))
+                .any(|i| matches_wildcard(tail, &text[i..]))
+        }),
+    }
+}
+
+/// The `route_whites` of the settings, compiled once at startup and shared
+/// by every worker.
+#[derive(Debug, Default)]
+pub struct PublicRoutes {
+    rules: Vec<Rule>,
+}
+
+impl PublicRoutes {
+    pub fn new(whites: &[RouteWhite]) -> Result<Self, String> {
+        Ok(Self {
+            rules: whites.iter().map(Rule::new).collect::<Result<_, _>>()?,
+        })
+    }
+
+    pub fn from_settings(settings: &AppSettings) -> std::io::Result<Self> {
+        let whites = settings.application.route_whites.as_deref().unwrap_or(&[]);
+        Self::new(whites).map_err(std::io::Error::other)
+    }
+
+    /// `None` when the route needs a token. A public rule wins over an
+    /// optional one matching the same request.
+    pub fn find(&self, method: &Method, path: &str) -> Option<RouteAccess> {
+        let path = path.split('/').collect::<Vec<_>>();
+        let mut access = None;
+        for rule in self.rules.iter().filter(|rule| rule.matches(method, &path)) {
+            if rule.access == RouteAccess::Public {
+                return Some(RouteAccess::Public);
+            }
+            access = Some(rule.access);
+        }
+        access
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(path: &str) -> RouteWhite {
+        RouteWhite::Path(path.to_string())
+    }
+
+    fn rule(path: &str, methods: &[&str], optional: bool) -> RouteWhite {
+        RouteWhite::Rule {
+            path: path.to_string(),
+            methods: (!methods.is_empty()).then(|| methods.iter().map(|m| m.to_string()).collect()),
+            optional: Some(optional),
+        }
+    }
+
+    #[test]
+    fn matches_literal_paths_exactly() {
+        let routes = PublicRoutes::new(&[path("/api/v1/auth/login")]).unwrap();
+        assert_eq!(
+            routes.find(&Method::POST, "/api/v1/auth/login"),
+            Some(RouteAccess::Public)
+        );
+        assert_eq!(routes.find(&Method::POST, "/api/v1/auth/login/mfa"), None);
+        assert_eq!(routes.find(&Method::POST, "/api/v1/auth"), None);
+    }
+
+    #[test]
+    fn matches_wildcards_within_a_segment() {
+        let routes = PublicRoutes::new(&[path("/static/*.json"), path("/files/*")]).unwrap();
+        assert!(routes.find(&Method::GET, "/static/app.json").is_some());
+        assert!(routes.find(&Method::GET, "/static/.json").is_some());
+        assert!(routes.find(&Method::GET, "/static/app.js").is_none());
+        assert!(routes.find(&Method::GET, "/files/a").is_some());
+        assert!(routes.find(&Method::GET, "/files/a/b").is_none());
+    }
+
+    #[test]
+    fn matches_any_number_of_segments() {
+        let routes = PublicRoutes::new(&[path("/public/**"), path("/**/health")]).unwrap();
+        assert!(routes.find(&Method::GET, "/public").is_some());
+        assert!(routes.find(&Method::GET, "/public/a/b/c").is_some());
+        assert!(routes.find(&Method::GET, "/health").is_some());
+        assert!(routes.find(&Method::GET, "/api/v1/health").is_some());
+        assert!(routes.find(&Method::GET, "/api/v1/health/deep").is_none());
+    }
+
+    #[test]
+    fn limits_rules_to_their_methods() {
+        let routes =
+            PublicRoutes::new(&[rule("/api/v1/teams/*", &["get", "HEAD"], false)]).unwrap();
+        assert!(routes.find(&Method::GET, "/api/v1/teams/1").is_some());
+        assert!(routes.find(&Method::HEAD, "/api/v1/teams/1").is_some());
+        assert!(routes.find(&Method::POST, "/api/v1/teams/1").is_none());
+    }
+
+    #[test]
+    fn public_wins_over_optional() {
+        let optional = rule("/api/v1/invitations/**", &[], true);
+        let routes = PublicRoutes::new(std::slice::from_ref(&optional)).unwrap();
+        assert_eq!(
+            routes.find(&Method::GET, "/api/v1/invitations/abc"),
+            Some(RouteAccess::Optional)
+        );
+
+        let routes =
+            PublicRoutes::new(&[optional, rule("/api/v1/invitations/*", &[], false)]).unwrap();
+        assert_eq!(
+            routes.find(&Method::GET, "/api/v1/invitations/abc"),
+            Some(RouteAccess::Public)
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_rules() {
+        assert!(PublicRoutes::new(&[path("api/v1")]).is_err());
+        assert!(PublicRoutes::new(&[rule("/api", &["NOT A METHOD"], false)]).is_err());
+    }
+}
@@ -0,0 +1,20 @@
+use std::{sync::Arc, time::Duration};
+
+use actix_web::rt;
+use service::{config::AuditConfig, Service};
+
+/// Purge activity log entries past their team's retention every
+/// `purge_interval` seconds, starting right away.
+pub fn spawn_log_purge(service: Arc<Service>, config: AuditConfig) {
+    rt::spawn(async move {
+        let mut interval = rt::time::interval(Duration::from_secs(config.purge_interval.max(60)));
+        loop {
+            interval.tick().await;
+            match service.activity_log_service.purge_expired(&config).await {
+                Ok(0) => {}
+                Ok(purged) => log::info!("Purged {} expired activity log entries", purged),
+                Err(e) => log::error!("Purge activity log error: {}", e),
+            }
+        }
+    });
+}
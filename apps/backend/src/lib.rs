@@ -1,20 +1,27 @@
 #[allow(dead_code)]
 mod common;
 
+pub mod extractors;
 pub mod guards;
 pub mod handlers;
+pub mod jobs;
 pub mod middlewares;
 pub mod routes;
 pub mod settings;
 pub mod utils;
 
 use actix_settings::ApplySettings;
-use actix_web::middleware::{Compress, Condition, Logger};
+use actix_web::middleware::{from_fn, Compress, Condition, Logger};
 use actix_web::{web, App, HttpServer};
 use actix_web_httpauth::middleware::HttpAuthentication;
-use guards::auth::bearer_validator;
-use middlewares::{cors, error_interceptor};
+use guards::{auth::bearer_validator, public_routes::PublicRoutes};
+use middlewares::{audit, cors, error_interceptor};
+use service::mailer::{FileMailer, LogMailer, Mailer, MailerConfig, SmtpMailer};
 use service::sea_orm::{ConnectOptions, Database, DatabaseConnection};
+use service::store::{
+    MemoryRevocationStore, PgRevocationStore, RevocationStore, RevocationStoreKind,
+};
+use service::utils::{jwt::Jwt, password_policy::PasswordPolicy};
 use service::Service;
 use settings::{init_settings, AppSettings};
 use std::sync::Arc;
@@ -23,22 +30,78 @@ async fn init_pg(settings: &AppSettings) -> std::io::Result<DatabaseConnection>
     let pg_config = &settings.application.pg_database;
     log::info!("Connection database url: {}", &pg_config.to_string());
 
-    let mut database_opt = ConnectOptions::new(&pg_config.to_string());
+    let mut database_opt = ConnectOptions::new(pg_config.to_string());
 
     database_opt
         .sqlx_logging(true)
         .sqlx_logging_level(log::LevelFilter::Info);
 
     let conn = Database::connect(database_opt).await.map_err(|_| {
-        std::io::Error::new(
-            std::io::ErrorKind::Other,
-            "Connection database error, Check your database config.",
-        )
+        std::io::Error::other("Connection database error, Check your database config.")
     })?;
 
     Ok(conn)
 }
 
+fn init_revocation_store(
+    settings: &AppSettings,
+    conn: Arc<DatabaseConnection>,
+) -> Arc<dyn RevocationStore> {
+    let kind = settings
+        .application
+        .token
+        .as_ref()
+        .and_then(|token| token.revocation_store)
+        .unwrap_or_default();
+    log::info!("Token revocation store: {:?}", kind);
+
+    match kind {
+        RevocationStoreKind::Memory => Arc::new(MemoryRevocationStore::default()),
+        RevocationStoreKind::Postgres => Arc::new(PgRevocationStore::new(conn)),
+    }
+}
+
+fn init_jwt(settings: &AppSettings) -> std::io::Result<Arc<Jwt>> {
+    let config = settings.application.jwt.as_ref().ok_or_else(|| {
+        std::io::Error::other("Missing `jwt` config, tokens can not be signed without a key")
+    })?;
+    let jwt = Jwt::new(config).map_err(|e| std::io::Error::other(e.to_string()))?;
+
+    Ok(Arc::new(jwt))
+}
+
+fn init_password_policy(settings: &AppSettings) -> std::io::Result<Arc<PasswordPolicy>> {
+    let config = settings.application.password.clone().unwrap_or_default();
+    let policy = PasswordPolicy::new(&config).map_err(|e| std::io::Error::other(e.to_string()))?;
+
+    Ok(Arc::new(policy))
+}
+
+fn init_mailer(settings: &AppSettings) -> std::io::Result<Arc<dyn Mailer>> {
+    let config = settings.application.mailer.clone().unwrap_or_default();
+
+    Ok(match config {
+        MailerConfig::Log => Arc::new(LogMailer),
+        MailerConfig::File { from, dir } => {
+            log::info!("Mails will be written to: {}", &dir);
+            Arc::new(FileMailer::new(&from, &dir))
+        }
+        MailerConfig::Smtp {
+            from,
+            host,
+            port,
+            username,
+            password,
+            encryption,
+        } => {
+            let credentials = username.zip(password);
+            let mailer = SmtpMailer::new(&from, &host, port, credentials, encryption)
+                .map_err(|e| std::io::Error::other(e.to_string()))?;
+            Arc::new(mailer)
+        }
+    })
+}
+
 pub fn init_logger(settings: &AppSettings) {
     if !settings.actix.enable_log {
         return;
@@ -63,7 +126,27 @@ fn init_config() -> std::io::Result<AppSettings> {
 pub async fn app_run() -> std::io::Result<()> {
     let settings = init_config()?;
     let conn = Arc::new(init_pg(&settings).await?);
-    let service = Arc::new(Service::new(conn.clone()));
+    let jwt = init_jwt(&settings)?;
+    let password_policy = init_password_policy(&settings)?;
+    let revocation_store = init_revocation_store(&settings, conn.clone());
+    let mailer = init_mailer(&settings)?;
+    let auth_config = settings.application.auth.clone().unwrap_or_default();
+    let oidc_providers = settings.application.oidc.clone().unwrap_or_default();
+    let service = Arc::new(Service::new(
+        conn.clone(),
+        auth_config,
+        oidc_providers,
+        jwt,
+        password_policy,
+        revocation_store,
+        mailer,
+    ));
+
+    let public_routes = web::Data::new(PublicRoutes::from_settings(&settings)?);
+    jobs::spawn_log_purge(
+        service.clone(),
+        settings.application.audit.clone().unwrap_or_default(),
+    );
 
     log::info!("============ Server starting =================");
     HttpServer::new({
@@ -79,10 +162,12 @@ pub async fn app_run() -> std::io::Result<()> {
                 .wrap(Logger::default())
                 .wrap(cors::init_cors(&settings))
                 .wrap(auth)
+                .wrap(from_fn(audit::audit_client))
                 .wrap(error_interceptor::ErrorInterceptor)
                 .app_data(web::Data::new(settings.clone()))
                 .app_data(web::Data::new(conn.clone()))
                 .app_data(web::Data::new(service.clone()))
+                .app_data(public_routes.clone())
                 .configure(routes::config)
         }
     })
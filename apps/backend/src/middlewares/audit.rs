@@ -0,0 +1,19 @@
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    middleware::Next,
+    Error,
+};
+use service::common::activity_log_service::ActivityLogService;
+
+use crate::utils::client_info;
+
+/// Makes the caller's address and user agent part of every activity log
+/// entry the request writes.
+pub async fn audit_client(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let client = client_info(req.request());
+    ActivityLogService::scope(client, next.call(req)).await
+}
@@ -1,2 +1,3 @@
+pub mod audit;
 pub mod cors;
 pub mod error_interceptor;
@@ -0,0 +1,155 @@
+use std::sync::Arc;
+
+use crate::common::handle_response_by_service;
+use crate::extractors::CurrentUser;
+use actix_web::{get, post, put, web, Responder};
+use serde::Deserialize;
+use service::{
+    _entities::sea_orm_active_enums::PlatformRoles,
+    common::{
+        activity_log_service::ActivityLogQuery,
+        auth_service::{ImpersonatePayload, SuspendUserPayload},
+        user_service::SearchUserQuery,
+    },
+    sea_orm::sqlx::types::Uuid,
+    Service,
+};
+
+/// Platform administration, the guard only lets platform admins through.
+pub(crate) fn init_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/admin")
+            .service(get_stats)
+            .service(get_users)
+            .service(get_user)
+            .service(get_user_teams)
+            .service(get_teams)
+            .service(reset_password)
+            .service(update_role)
+            .service(impersonate_user)
+            .service(suspend_user)
+            .service(reactivate_user)
+            .service(get_logs),
+    );
+}
+
+#[get("/stats")]
+async fn get_stats(service: web::Data<Arc<Service>>) -> impl Responder {
+    let res = service.admin_service.platform_stats().await;
+    handle_response_by_service(res)
+}
+
+#[get("/users")]
+async fn get_users(
+    service: web::Data<Arc<Service>>,
+    query: web::Query<SearchUserQuery>,
+) -> impl Responder {
+    let res = service.user_service.search_users(query.into_inner()).await;
+    handle_response_by_service(res)
+}
+
+#[get("/users/{id}")]
+async fn get_user(service: web::Data<Arc<Service>>, id: web::Path<Uuid>) -> impl Responder {
+    let res = service.user_service.find_user_by_id(id.into_inner()).await;
+    handle_response_by_service(res)
+}
+
+#[get("/users/{id}/teams")]
+async fn get_user_teams(service: web::Data<Arc<Service>>, id: web::Path<Uuid>) -> impl Responder {
+    let res = service
+        .team_user_service
+        .find_memberships_by_user(id.into_inner())
+        .await;
+    handle_response_by_service(res)
+}
+
+#[get("/teams")]
+async fn get_teams(service: web::Data<Arc<Service>>) -> impl Responder {
+    let res = service.team_service.find_project_all().await;
+    handle_response_by_service(res)
+}
+
+#[post("/users/{id}/reset-password")]
+async fn reset_password(
+    service: web::Data<Arc<Service>>,
+    id: web::Path<Uuid>,
+    admin: CurrentUser,
+) -> impl Responder {
+    let res = service
+        .auth_service
+        .force_password_reset(admin.user_id, id.into_inner())
+        .await;
+    handle_response_by_service(res)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateRoleBody {
+    pub role: PlatformRoles,
+}
+
+#[put("/users/{id}/role")]
+async fn update_role(
+    service: web::Data<Arc<Service>>,
+    id: web::Path<Uuid>,
+    body: web::Json<UpdateRoleBody>,
+    admin: CurrentUser,
+) -> impl Responder {
+    let res = service
+        .auth_service
+        .update_platform_role(admin.user_id, id.into_inner(), body.into_inner().role)
+        .await;
+    handle_response_by_service(res)
+}
+
+#[post("/users/{id}/impersonate")]
+async fn impersonate_user(
+    service: web::Data<Arc<Service>>,
+    id: web::Path<Uuid>,
+    payload: web::Json<ImpersonatePayload>,
+    admin: CurrentUser,
+) -> impl Responder {
+    let res = service
+        .auth_service
+        .impersonate(admin.user_id, id.into_inner(), payload.into_inner())
+        .await;
+    handle_response_by_service(res)
+}
+
+#[post("/users/{id}/suspend")]
+async fn suspend_user(
+    service: web::Data<Arc<Service>>,
+    id: web::Path<Uuid>,
+    payload: web::Json<SuspendUserPayload>,
+    admin: CurrentUser,
+) -> impl Responder {
+    let res = service
+        .auth_service
+        .suspend_user(admin.user_id, id.into_inner(), payload.into_inner())
+        .await;
+    handle_response_by_service(res)
+}
+
+#[post("/users/{id}/reactivate")]
+async fn reactivate_user(
+    service: web::Data<Arc<Service>>,
+    id: web::Path<Uuid>,
+    admin: CurrentUser,
+) -> impl Responder {
+    let res = service
+        .auth_service
+        .reactivate_user(admin.user_id, id.into_inner())
+        .await;
+    handle_response_by_service(res)
+}
+
+#[get("/logs")]
+async fn get_logs(
+    service: web::Data<Arc<Service>>,
+    query: web::Query<ActivityLogQuery>,
+) -> impl Responder {
+    let res = service
+        .activity_log_service
+        .find_all(query.into_inner())
+        .await;
+    handle_response_by_service(res)
+}
@@ -0,0 +1,120 @@
+use std::sync::Arc;
+
+use crate::common::{handle_response_by_service, ApiResponse};
+use crate::extractors::{CurrentUser, TeamMember};
+use actix_web::{delete, get, post, put, web, Responder};
+use service::{
+    common::api_key_service::{ApiKeyInfo, CreateApiKeyDto, CreatedApiKey, UpdateApiKeyDto},
+    permission::Permission,
+    sea_orm::sqlx::types::Uuid,
+    Service,
+};
+
+pub(crate) fn init_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/api-key")
+            .service(get_keys)
+            .service(get_team_keys)
+            .service(create_key)
+            .service(update_key)
+            .service(delete_key),
+    );
+}
+
+/// Personal keys are managed by their owner, team keys by members holding `api_key.manage`.
+async fn can_manage_keys(service: &Service, user_id: Uuid, team_id: Option<Uuid>) -> bool {
+    match team_id {
+        Some(team_id) => service
+            .permission_service
+            .authorize(user_id, team_id, Permission::ApiKeyManage)
+            .await
+            .unwrap_or(false),
+        None => true,
+    }
+}
+
+#[get("/list")]
+async fn get_keys(service: web::Data<Arc<Service>>, user: CurrentUser) -> impl Responder {
+    let res = service
+        .api_key_service
+        .find_keys_by_user(user.user_id)
+        .await;
+    handle_response_by_service(res)
+}
+
+#[get("/team/{team_id}")]
+async fn get_team_keys(
+    service: web::Data<Arc<Service>>,
+    member: TeamMember,
+) -> actix_web::Result<impl Responder> {
+    member.require(Permission::ApiKeyManage)?;
+
+    let res = service
+        .api_key_service
+        .find_keys_by_team(member.team_id)
+        .await;
+    Ok(handle_response_by_service(res))
+}
+
+#[post("/create")]
+async fn create_key(
+    service: web::Data<Arc<Service>>,
+    payload: web::Json<CreateApiKeyDto>,
+    user: CurrentUser,
+) -> impl Responder {
+    let payload = payload.into_inner();
+    if !can_manage_keys(&service, user.user_id, payload.team_id).await {
+        return ApiResponse::<CreatedApiKey>::forbidden(Some(&Permission::ApiKeyManage.denied()));
+    }
+
+    let res = service
+        .api_key_service
+        .create_key(user.user_id, payload)
+        .await;
+    handle_response_by_service(res)
+}
+
+#[put("/update/{id}")]
+async fn update_key(
+    service: web::Data<Arc<Service>>,
+    id: web::Path<Uuid>,
+    payload: web::Json<UpdateApiKeyDto>,
+    user: CurrentUser,
+) -> impl Responder {
+    let key = match service.api_key_service.find_key(id.into_inner()).await {
+        Ok(key) => key,
+        Err(e) => return ApiResponse::<ApiKeyInfo>::bad_request(Some(&e.to_string())),
+    };
+    let owned = key.team_id.is_some() || key.user_id == user.user_id;
+    if !owned || !can_manage_keys(&service, user.user_id, key.team_id).await {
+        return ApiResponse::<ApiKeyInfo>::forbidden(Some("Cannot manage this API key"));
+    }
+
+    let res = service
+        .api_key_service
+        .update_key(user.user_id, key.key_id, payload.into_inner())
+        .await;
+    handle_response_by_service(res)
+}
+
+#[delete("/delete/{id}")]
+async fn delete_key(
+    service: web::Data<Arc<Service>>,
+    id: web::Path<Uuid>,
+    user: CurrentUser,
+) -> impl Responder {
+    let key = match service.api_key_service.find_key(id.into_inner()).await {
+        Ok(key) => key,
+        Err(e) => return ApiResponse::<()>::bad_request(Some(&e.to_string())),
+    };
+    let owned = key.team_id.is_some() || key.user_id == user.user_id;
+    if !owned || !can_manage_keys(&service, user.user_id, key.team_id).await {
+        return ApiResponse::<()>::forbidden(Some("Cannot manage this API key"));
+    }
+
+    let res = service
+        .api_key_service
+        .delete_key(user.user_id, key.key_id)
+        .await;
+    handle_response_by_service(res)
+}
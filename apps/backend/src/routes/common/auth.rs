@@ -1,14 +1,22 @@
 use std::sync::Arc;
 
-use crate::common::{handle_response_by_service, ApiResponse, Empty};
-use actix_web::{get, post, web, Responder};
-use actix_web_httpauth::extractors::bearer::BearerAuth;
+use crate::{
+    common::{handle_response_by_service, ApiResponse, Empty},
+    extractors::CurrentUser,
+    utils::client_info,
+};
+use actix_web::{delete, get, post, web, HttpRequest, Responder};
 use serde::{Deserialize, Serialize};
 use service::{
     common::{
-        auth_service::{AuthResponse, LoginPayload},
+        auth_service::{
+            ChangePasswordPayload, ConfirmPasswordPayload, ForgetPasswordPayload, LoginMfaPayload,
+            LoginPayload,
+        },
+        oidc_service::{OidcAuthorizePayload, OidcCallbackPayload},
         user_service::{CreateUserDto, UpdateUserDto},
     },
+    sea_orm::sqlx::types::Uuid,
     Service,
 };
 
@@ -16,13 +24,37 @@ pub(crate) fn init_routes(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/auth")
             .service(login)
+            .service(login_mfa)
+            .service(oidc_providers)
+            .service(oidc_authorize)
+            .service(oidc_callback)
+            .service(oidc_link)
+            .service(identities)
+            .service(unlink_identity)
             .service(register)
             .service(forget)
+            .service(forget_send_email)
+            .service(forget_verify_code)
+            .service(unlock)
+            .service(unlock_link)
             .service(logout)
+            .service(sessions)
+            .service(revoke_other_sessions)
+            .service(revoke_session)
             .service(info)
             .service(teams)
-            .service(reset_token)
+            .service(refresh_token)
             .service(update_info)
+            .service(change_password)
+            .service(deactivate_account)
+            .service(delete_account)
+            .service(verify_email)
+            .service(verify_email_link)
+            .service(resend_verify_email)
+            .service(setup_totp)
+            .service(confirm_totp)
+            .service(disable_totp)
+            .service(regenerate_recovery_codes)
             .service(check),
     );
 }
@@ -35,83 +67,421 @@ pub struct LoginBody {
 }
 
 #[post("/login")]
-async fn login(service: web::Data<Arc<Service>>, payload: web::Json<LoginBody>) -> impl Responder {
+async fn login(
+    service: web::Data<Arc<Service>>,
+    req: HttpRequest,
+    payload: web::Json<LoginBody>,
+) -> impl Responder {
     let payload = payload.into_inner();
-    // TODO: Storeage user login status in redis
-    // let remmeber = payload.remember.unwrap_or(false);
     let res = service
         .auth_service
         .login(LoginPayload {
             email: payload.email,
             password: payload.password,
+            remember: payload.remember.unwrap_or(false),
             expire: None,
+            client: client_info(&req),
         })
         .await;
     handle_response_by_service(res)
 }
 
+#[post("/login/mfa")]
+async fn login_mfa(
+    service: web::Data<Arc<Service>>,
+    req: HttpRequest,
+    payload: web::Json<LoginMfaPayload>,
+) -> impl Responder {
+    let res = service
+        .auth_service
+        .login_mfa(payload.into_inner(), client_info(&req))
+        .await;
+    handle_response_by_service(res)
+}
+
+#[get("/oidc/providers")]
+async fn oidc_providers(service: web::Data<Arc<Service>>) -> impl Responder {
+    handle_response_by_service(Ok(service.oidc_service.providers()))
+}
+
+/// Returns the provider URL to send the browser to.
+#[get("/oidc/authorize")]
+async fn oidc_authorize(
+    service: web::Data<Arc<Service>>,
+    query: web::Query<OidcAuthorizePayload>,
+) -> impl Responder {
+    let res = service
+        .oidc_service
+        .authorize(query.into_inner(), None)
+        .await;
+    handle_response_by_service(res)
+}
+
+/// Called by the frontend with the `code` and `state` the provider redirected back with.
+#[post("/oidc/callback")]
+async fn oidc_callback(
+    service: web::Data<Arc<Service>>,
+    req: HttpRequest,
+    payload: web::Json<OidcCallbackPayload>,
+) -> impl Responder {
+    let res = service
+        .auth_service
+        .login_oidc(payload.into_inner(), client_info(&req))
+        .await;
+    handle_response_by_service(res)
+}
+
+/// Same as `/oidc/authorize`, but the callback links the identity to the current user.
+#[post("/oidc/link")]
+async fn oidc_link(
+    service: web::Data<Arc<Service>>,
+    user: CurrentUser,
+    payload: web::Json<OidcAuthorizePayload>,
+) -> impl Responder {
+    let res = service
+        .oidc_service
+        .authorize(payload.into_inner(), Some(user.user_id))
+        .await;
+    handle_response_by_service(res)
+}
+
+#[get("/identities")]
+async fn identities(service: web::Data<Arc<Service>>, user: CurrentUser) -> impl Responder {
+    let res = service
+        .oidc_service
+        .find_identities_by_user(user.user_id)
+        .await;
+    handle_response_by_service(res)
+}
+
+#[delete("/identities/{id}")]
+async fn unlink_identity(
+    service: web::Data<Arc<Service>>,
+    id: web::Path<Uuid>,
+    user: CurrentUser,
+) -> impl Responder {
+    let res = service
+        .oidc_service
+        .unlink_identity(user.user_id, id.into_inner())
+        .await;
+    handle_response_by_service(res)
+}
+
 #[post("/register")]
 async fn register(
     service: web::Data<Arc<Service>>,
+    req: HttpRequest,
     payload: web::Json<CreateUserDto>,
 ) -> impl Responder {
-    let res = service.auth_service.register(payload.into_inner()).await;
+    let res = service
+        .auth_service
+        .register(payload.into_inner(), client_info(&req))
+        .await;
     handle_response_by_service(res)
 }
 
 #[post("/logout")]
-async fn logout(service: web::Data<Arc<Service>>) -> impl Responder {
-    service.auth_service.logout().await;
+async fn logout(service: web::Data<Arc<Service>>, user: CurrentUser) -> impl Responder {
+    let Some(token) = &user.token else {
+        return ApiResponse::<Empty>::bad_request(Some("API keys can not log out"));
+    };
+    if let Err(e) = service.auth_service.logout(token).await {
+        return ApiResponse::<Empty>::bad_request(Some(&e.to_string()));
+    }
     ApiResponse::<Empty>::ok(Some("Logout successful!"))
 }
 
-#[get("/reset-token")]
-async fn reset_token(service: web::Data<Arc<Service>>, token: BearerAuth) -> impl Responder {
-    let token_str = token.token();
-    let res = service.auth_service.reset_token_by_jwt(token_str).await;
-    ApiResponse::<AuthResponse>::success(None, Some(res))
+#[get("/sessions")]
+async fn sessions(service: web::Data<Arc<Service>>, user: CurrentUser) -> impl Responder {
+    let res = service
+        .auth_service
+        .list_sessions(user.user_id, user.session_id)
+        .await;
+    handle_response_by_service(res)
 }
 
-#[get("/info")]
-async fn info(service: web::Data<Arc<Service>>, token: BearerAuth) -> impl Responder {
-    let token_str = token.token();
-    let res = service.auth_service.get_user_info_by_token(token_str).await;
+#[post("/sessions/revoke-others")]
+async fn revoke_other_sessions(
+    service: web::Data<Arc<Service>>,
+    user: CurrentUser,
+) -> impl Responder {
+    let res = service
+        .auth_service
+        .revoke_other_sessions(user.user_id, user.actor_id, user.session_id)
+        .await;
     handle_response_by_service(res)
 }
 
-#[get("/teams")]
-async fn teams(service: web::Data<Arc<Service>>, token: BearerAuth) -> impl Responder {
-    let token_str = token.token();
-    let user_id = service
+#[delete("/sessions/{id}")]
+async fn revoke_session(
+    service: web::Data<Arc<Service>>,
+    id: web::Path<Uuid>,
+    user: CurrentUser,
+) -> impl Responder {
+    let res = service
+        .auth_service
+        .revoke_session(user.user_id, user.actor_id, id.into_inner())
+        .await;
+    handle_response_by_service(res)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RefreshTokenBody {
+    #[serde(rename = "refreshToken")]
+    pub refresh_token: String,
+}
+
+#[post("/refresh-token")]
+async fn refresh_token(
+    service: web::Data<Arc<Service>>,
+    payload: web::Json<RefreshTokenBody>,
+) -> impl Responder {
+    let res = service
         .auth_service
-        .get_user_id_by_token(token_str)
-        .await
-        .unwrap();
+        .refresh_token(&payload.refresh_token)
+        .await;
+    handle_response_by_service(res)
+}
 
+#[get("/info")]
+async fn info(service: web::Data<Arc<Service>>, user: CurrentUser) -> impl Responder {
+    let res = service.auth_service.get_user_info(user.user_id).await;
+    handle_response_by_service(res)
+}
+
+#[get("/teams")]
+async fn teams(service: web::Data<Arc<Service>>, user: CurrentUser) -> impl Responder {
     let res = service
         .team_user_service
-        .find_teams_by_user(user_id)
+        .find_teams_by_user(user.user_id)
         .await;
 
     handle_response_by_service(res)
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ForgetSendEmailBody {
+    pub email: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ForgetVerifyCodeBody {
+    pub email: String,
+    pub code: String,
+}
+
+#[post("/forget_send_email")]
+async fn forget_send_email(
+    service: web::Data<Arc<Service>>,
+    payload: web::Json<ForgetSendEmailBody>,
+) -> impl Responder {
+    let res = service.auth_service.forget_send_email(&payload.email).await;
+    handle_response_by_service(res)
+}
+
+#[post("/forget_verify_code")]
+async fn forget_verify_code(
+    service: web::Data<Arc<Service>>,
+    payload: web::Json<ForgetVerifyCodeBody>,
+) -> impl Responder {
+    let res = service
+        .auth_service
+        .forget_verify_code(&payload.email, &payload.code)
+        .await;
+    handle_response_by_service(res)
+}
+
 #[post("/forget")]
-async fn forget() -> impl Responder {
-    // TODO: forget password
-    ApiResponse::<Empty>::ok(Some("forget successful!"))
+async fn forget(
+    service: web::Data<Arc<Service>>,
+    payload: web::Json<ForgetPasswordPayload>,
+) -> impl Responder {
+    let res = service.auth_service.forget(payload.into_inner()).await;
+    handle_response_by_service(res)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UnlockBody {
+    pub email: String,
+    pub code: String,
+}
+
+#[post("/unlock")]
+async fn unlock(
+    service: web::Data<Arc<Service>>,
+    payload: web::Json<UnlockBody>,
+) -> impl Responder {
+    let res = service
+        .auth_service
+        .unlock_account(&payload.email, &payload.code)
+        .await;
+    handle_response_by_service(res)
+}
+
+/// Target of the link in the lockout mail.
+#[get("/unlock")]
+async fn unlock_link(
+    service: web::Data<Arc<Service>>,
+    query: web::Query<UnlockBody>,
+) -> impl Responder {
+    let res = service
+        .auth_service
+        .unlock_account(&query.email, &query.code)
+        .await;
+    handle_response_by_service(res)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VerifyEmailBody {
+    pub email: String,
+    pub code: String,
+}
+
+#[post("/verify-email")]
+async fn verify_email(
+    service: web::Data<Arc<Service>>,
+    payload: web::Json<VerifyEmailBody>,
+) -> impl Responder {
+    let res = service
+        .auth_service
+        .verify_email(&payload.email, &payload.code)
+        .await;
+    handle_response_by_service(res)
+}
+
+/// Target of the link in the verification mail.
+#[get("/verify-email")]
+async fn verify_email_link(
+    service: web::Data<Arc<Service>>,
+    query: web::Query<VerifyEmailBody>,
+) -> impl Responder {
+    let res = service
+        .auth_service
+        .verify_email(&query.email, &query.code)
+        .await;
+    handle_response_by_service(res)
+}
+
+#[post("/verify-email/resend")]
+async fn resend_verify_email(
+    service: web::Data<Arc<Service>>,
+    user: CurrentUser,
+) -> impl Responder {
+    let res = service.auth_service.resend_verify_email(user.user_id).await;
+    handle_response_by_service(res)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MfaCodeBody {
+    pub code: String,
+}
+
+#[post("/mfa/totp/setup")]
+async fn setup_totp(service: web::Data<Arc<Service>>, user: CurrentUser) -> impl Responder {
+    let user = match service.auth_service.get_user_info(user.user_id).await {
+        Ok(user) => user,
+        Err(e) => return handle_response_by_service(Err(e)),
+    };
+
+    let res = service
+        .mfa_service
+        .setup_totp(user.user_id, &user.email)
+        .await;
+    handle_response_by_service(res)
+}
+
+#[post("/mfa/totp/confirm")]
+async fn confirm_totp(
+    service: web::Data<Arc<Service>>,
+    user: CurrentUser,
+    payload: web::Json<MfaCodeBody>,
+) -> impl Responder {
+    let res = service
+        .mfa_service
+        .confirm_totp(user.user_id, &payload.code)
+        .await;
+    handle_response_by_service(res)
+}
+
+#[post("/mfa/totp/disable")]
+async fn disable_totp(
+    service: web::Data<Arc<Service>>,
+    user: CurrentUser,
+    payload: web::Json<MfaCodeBody>,
+) -> impl Responder {
+    let res = service
+        .auth_service
+        .disable_mfa(user.user_id, &payload.code)
+        .await;
+    handle_response_by_service(res)
+}
+
+#[post("/mfa/recovery-codes")]
+async fn regenerate_recovery_codes(
+    service: web::Data<Arc<Service>>,
+    user: CurrentUser,
+    payload: web::Json<MfaCodeBody>,
+) -> impl Responder {
+    let res = service
+        .mfa_service
+        .regenerate_recovery_codes(user.user_id, &payload.code)
+        .await;
+    handle_response_by_service(res)
 }
 
 #[post("/update-info")]
 async fn update_info(
     service: web::Data<Arc<Service>>,
-    token: BearerAuth,
+    user: CurrentUser,
     payload: web::Json<UpdateUserDto>,
 ) -> impl Responder {
-    let token_str = token.token();
     let res = service
         .auth_service
-        .update_user_by_token(token_str, payload.into_inner())
+        .update_user_info(user.user_id, payload.into_inner())
+        .await;
+    handle_response_by_service(res)
+}
+
+#[post("/change-password")]
+async fn change_password(
+    service: web::Data<Arc<Service>>,
+    user: CurrentUser,
+    payload: web::Json<ChangePasswordPayload>,
+) -> impl Responder {
+    let res = service
+        .auth_service
+        .change_password(
+            user.user_id,
+            user.actor_id,
+            user.session_id,
+            payload.into_inner(),
+        )
+        .await;
+    handle_response_by_service(res)
+}
+
+#[post("/deactivate")]
+async fn deactivate_account(
+    service: web::Data<Arc<Service>>,
+    user: CurrentUser,
+    payload: web::Json<ConfirmPasswordPayload>,
+) -> impl Responder {
+    let res = service
+        .auth_service
+        .deactivate_account(user.user_id, user.actor_id, payload.into_inner())
+        .await;
+    handle_response_by_service(res)
+}
+
+#[delete("/account")]
+async fn delete_account(
+    service: web::Data<Arc<Service>>,
+    user: CurrentUser,
+    payload: web::Json<ConfirmPasswordPayload>,
+) -> impl Responder {
+    let res = service
+        .auth_service
+        .delete_account(user.user_id, user.actor_id, payload.into_inner())
         .await;
     handle_response_by_service(res)
 }
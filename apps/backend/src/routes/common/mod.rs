@@ -1,11 +1,15 @@
+mod admin;
+mod api_key;
 mod auth;
-mod user;
+mod notification;
 mod team;
 
 use actix_web::web;
 
 pub(crate) fn init_routes(cfg: &mut web::ServiceConfig) {
     auth::init_routes(cfg);
-    user::init_routes(cfg);
     team::init_routes(cfg);
+    api_key::init_routes(cfg);
+    notification::init_routes(cfg);
+    admin::init_routes(cfg);
 }
@@ -0,0 +1,56 @@
+use std::sync::Arc;
+
+use crate::common::handle_response_by_service;
+use crate::extractors::CurrentUser;
+use actix_web::{get, put, web, Responder};
+use serde::Deserialize;
+use service::{sea_orm::sqlx::types::Uuid, Service};
+
+pub(crate) fn init_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/notification")
+            .service(get_notifications)
+            .service(read_all)
+            .service(read_notification),
+    );
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NotificationQuery {
+    unread: Option<bool>,
+}
+
+#[get("/list")]
+async fn get_notifications(
+    service: web::Data<Arc<Service>>,
+    query: web::Query<NotificationQuery>,
+    user: CurrentUser,
+) -> impl Responder {
+    let res = service
+        .notification_service
+        .find_notifications_by_user(user.user_id, query.unread.unwrap_or(false))
+        .await;
+    handle_response_by_service(res)
+}
+
+#[put("/read-all")]
+async fn read_all(service: web::Data<Arc<Service>>, user: CurrentUser) -> impl Responder {
+    let res = service
+        .notification_service
+        .mark_read(user.user_id, None)
+        .await;
+    handle_response_by_service(res)
+}
+
+#[put("/read/{id}")]
+async fn read_notification(
+    service: web::Data<Arc<Service>>,
+    id: web::Path<Uuid>,
+    user: CurrentUser,
+) -> impl Responder {
+    let res = service
+        .notification_service
+        .mark_read(user.user_id, Some(id.into_inner()))
+        .await;
+    handle_response_by_service(res)
+}
@@ -1,76 +1,116 @@
 use std::sync::Arc;
 
 use crate::common::{handle_response_by_service, ApiResponse, Empty};
-use actix_web::{delete, get, post, put, web, Responder};
-use actix_web_httpauth::extractors::bearer::BearerAuth;
+use crate::extractors::{CurrentUser, TeamMember};
+use actix_web::{
+    delete, error::ErrorBadRequest, get, http::header, post, put, web, HttpResponse, Responder,
+};
+use futures_util::TryStreamExt;
+use serde::Deserialize;
 use service::{
+    _entities::sea_orm_active_enums::TeamUserRoles,
     common::{
+        activity_log_service::{ActivityLogExportQuery, ActivityLogQuery},
+        permission_service::{AssignCustomRoleDto, TeamRoleDto},
+        team_invitation_service::{InvitationTokenPayload, InviteMemberPayload},
+        team_join_request_service::{ApproveJoinRequestDto, CreateJoinRequestDto},
+        team_ownership_service::NominateOwnerDto,
         team_service::{CreateTeamDto, UpdateTeamDto},
-        team_user_service::{JoinTeamDto, LeftTeamDto},
-        user_service::UpdateUserDto,
+        team_user_service::{JoinTeamDto, LeftTeamDto, UpdateMemberRoleDto},
     },
-    sea_orm::{sqlx::types::Uuid, TryIntoModel},
+    permission::Permission,
+    sea_orm::{sqlx::types::Uuid, ActiveEnum},
     Service,
-    _entities::sea_orm_active_enums::TeamUserRoles,
 };
 
 pub(crate) fn init_routes(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/team")
-            .service(get_teams)
             .service(get_team)
             .service(get_users)
-            .service(join_team)
+            .service(preview_invitation)
+            .service(accept_invitation)
+            .service(decline_invitation)
+            .service(get_invitations)
+            .service(invite_member)
+            .service(resend_invitation)
+            .service(revoke_invitation)
+            .service(request_to_join)
+            .service(get_my_join_requests)
+            .service(withdraw_join_request)
+            .service(get_join_requests)
+            .service(approve_join_request)
+            .service(reject_join_request)
+            .service(get_my_ownership_transfers)
+            .service(accept_ownership_transfer)
+            .service(decline_ownership_transfer)
+            .service(get_ownership_transfer)
+            .service(nominate_owner)
+            .service(cancel_ownership_transfer)
             .service(left_team)
+            .service(update_member_role)
+            .service(assign_custom_role)
+            .service(get_permissions)
+            .service(get_roles)
+            .service(create_role)
+            .service(update_role)
+            .service(delete_role)
+            .service(get_logs)
+            .service(export_logs)
             .service(create_team)
             .service(update_team)
+            .service(require_mfa)
+            .service(allow_join_requests)
+            .service(allow_multiple_owners)
+            .service(log_retention)
             .service(delete_team),
     );
 }
 
-#[get("/list")]
-async fn get_teams(service: web::Data<Arc<Service>>) -> impl Responder {
-    let res = service.team_service.find_project_all().await;
-    handle_response_by_service(res)
-}
+#[get("/detail/{team_id}")]
+async fn get_team(
+    service: web::Data<Arc<Service>>,
+    member: TeamMember,
+) -> actix_web::Result<impl Responder> {
+    member.require(Permission::TeamView)?;
 
-#[get("/detail/{id}")]
-async fn get_team(service: web::Data<Arc<Service>>, id: web::Path<Uuid>) -> impl Responder {
-    let res = service.team_service.find_by_id(id.into_inner()).await;
-    handle_response_by_service(res)
+    let res = service.team_service.find_by_id(member.team_id).await;
+    Ok(handle_response_by_service(res))
 }
 
-#[get("/users/{id}")]
-async fn get_users(service: web::Data<Arc<Service>>, id: web::Path<Uuid>) -> impl Responder {
+#[get("/users/{team_id}")]
+async fn get_users(
+    service: web::Data<Arc<Service>>,
+    member: TeamMember,
+) -> actix_web::Result<impl Responder> {
+    member.require(Permission::TeamView)?;
+
     let res = service
         .team_user_service
-        .find_users_by_team(id.into_inner())
+        .find_users_by_team(member.team_id)
         .await;
-    handle_response_by_service(res)
+    Ok(handle_response_by_service(res))
 }
 
 #[post("/create")]
 async fn create_team(
     service: web::Data<Arc<Service>>,
     form: web::Json<CreateTeamDto>,
-    token: BearerAuth,
+    user: CurrentUser,
 ) -> impl Responder {
     let form = form.into_inner();
-    let token_str = token.token();
 
-    let user_info = service
-        .auth_service
-        .get_user_info_by_token(token_str)
-        .await
-        .unwrap();
+    let user_info = match service.user_service.find_user_by_id(user.user_id).await {
+        Ok(Some(user_info)) => user_info,
+        Ok(None) => return ApiResponse::<Empty>::unauthorized(Some("Cannot find user")),
+        Err(e) => return ApiResponse::<Empty>::bad_request(Some(&e.to_string())),
+    };
 
     log::info!("Create team form data: {:#?}", form);
-    let res = service.team_service.create_team(form).await;
+    let res = service.team_service.create_team(user.user_id, form).await;
 
     match res {
         Ok(team) => {
-            let team = team.try_into_model().unwrap();
-
             let payload = JoinTeamDto {
                 team_id: team.team_id,
                 user_id: user_info.user_id,
@@ -78,20 +118,13 @@ async fn create_team(
             };
 
             if user_info.default_team_id.is_none() {
-                service
+                if let Err(e) = service
                     .user_service
-                    .update_user_by_id(
-                        user_info.user_id,
-                        UpdateUserDto {
-                            username: None,
-                            display_name: None,
-                            email: None,
-                            avatar: None,
-                            default_team_id: Some(team.team_id),
-                        },
-                    )
+                    .set_default_team(user_info.user_id, Some(team.team_id))
                     .await
-                    .unwrap();
+                {
+                    return ApiResponse::<Empty>::bad_request(Some(&e.to_string()));
+                }
             }
 
             if let Err(e) = service.team_user_service.join_team(payload).await {
@@ -108,129 +141,599 @@ async fn create_team(
 async fn left_team(
     service: web::Data<Arc<Service>>,
     payload: web::Json<LeftTeamDto>,
-    token: BearerAuth,
+    user: CurrentUser,
 ) -> impl Responder {
-    let token_str = token.token();
-    let user_id = service
-        .auth_service
-        .get_user_id_by_token(token_str)
-        .await
-        .unwrap();
-
     let payload = payload.into_inner();
 
-    if payload.user_id == user_id {
+    if payload.user_id == user.user_id {
         return ApiResponse::<()>::bad_request(Some("Cannot remove yourself from team"));
     }
 
-    let role = service
-        .team_user_service
-        .get_user_role_by_team(payload.team_id, user_id)
-        .await;
+    match service
+        .permission_service
+        .authorize(user.user_id, payload.team_id, Permission::MemberRemove)
+        .await
+    {
+        Ok(true) => {}
+        Ok(false) => return ApiResponse::<()>::forbidden(Some(&Permission::MemberRemove.denied())),
+        Err(e) => return ApiResponse::<()>::bad_request(Some(&e.to_string())),
+    }
 
-    match role {
-        Ok(role) => {
-            if !role.can_remove_user_by_team() {
-                return ApiResponse::<()>::forbidden(Some(
-                    "Only Owner or Admin can remove user in team.",
-                ));
+    // Nobody removes a member at or above their own role, Owners aside.
+    let roles = (
+        service
+            .team_user_service
+            .get_user_role_by_team(payload.team_id, user.user_id)
+            .await,
+        service
+            .team_user_service
+            .get_user_role_by_team(payload.team_id, payload.user_id)
+            .await,
+    );
+    match roles {
+        (Ok(role), Ok(member_role)) => {
+            if !role.can_manage_member(&member_role) {
+                return ApiResponse::<()>::forbidden(Some(&format!(
+                    "{} cannot remove {} from team.",
+                    role.to_value(),
+                    member_role.to_value()
+                )));
             }
         }
-        Err(e) => return ApiResponse::<()>::bad_request(Some(&e.to_string())),
+        (Err(e), _) | (_, Err(e)) => return ApiResponse::<()>::bad_request(Some(&e.to_string())),
     }
 
-    let res = service.team_user_service.left_team(payload).await;
+    let res = service
+        .team_user_service
+        .left_team(user.user_id, payload)
+        .await;
 
     handle_response_by_service(res)
 }
 
-#[post("/join-team")]
-async fn join_team(
+#[put("/{team_id}/members/{user_id}/role")]
+async fn update_member_role(
     service: web::Data<Arc<Service>>,
-    payload: web::Json<JoinTeamDto>,
-) -> impl Responder {
+    member: TeamMember,
+    path: web::Path<(Uuid, Uuid)>,
+    payload: web::Json<UpdateMemberRoleDto>,
+) -> actix_web::Result<impl Responder> {
+    let (team_id, member_id) = path.into_inner();
+    member.require(Permission::MemberUpdateRole)?;
+
     let res = service
         .team_user_service
-        .join_team(payload.into_inner())
+        .update_member_role(team_id, member.user_id, member_id, payload.into_inner())
         .await;
+    Ok(handle_response_by_service(res))
+}
 
-    handle_response_by_service(res)
+#[put("/{team_id}/members/{user_id}/custom-role")]
+async fn assign_custom_role(
+    service: web::Data<Arc<Service>>,
+    member: TeamMember,
+    path: web::Path<(Uuid, Uuid)>,
+    payload: web::Json<AssignCustomRoleDto>,
+) -> actix_web::Result<impl Responder> {
+    let (team_id, member_id) = path.into_inner();
+    member.require(Permission::MemberUpdateRole)?;
+
+    let res = service
+        .permission_service
+        .assign_role(team_id, member.user_id, member_id, payload.into_inner())
+        .await;
+    Ok(handle_response_by_service(res))
 }
 
-#[put("/update/{id}")]
+#[put("/update/{team_id}")]
 async fn update_team(
     service: web::Data<Arc<Service>>,
-    id: web::Path<Uuid>,
+    member: TeamMember,
     form: web::Json<UpdateTeamDto>,
-    token: BearerAuth,
-) -> impl Responder {
-    let token_str = token.token();
-    let user_id = service
-        .auth_service
-        .get_user_id_by_token(token_str)
-        .await
-        .unwrap();
-
-    let team_id = id.into_inner();
-    let role = service
-        .team_user_service
-        .get_user_role_by_team(team_id, user_id)
-        .await;
+) -> actix_web::Result<impl Responder> {
+    member.require(Permission::TeamUpdate)?;
 
     let res = service
         .team_service
-        .update_team(team_id, form.into_inner())
+        .update_team(member.team_id, member.user_id, form.into_inner())
         .await;
 
-    match role {
-        Ok(role) => {
-            if !role.can_update_team() {
-                return ApiResponse::<()>::forbidden(Some("Only Owner or admin can delete team"));
+    Ok(handle_response_by_service(res))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RequireMfaBody {
+    pub required: bool,
+}
+
+#[put("/require-mfa/{team_id}")]
+async fn require_mfa(
+    service: web::Data<Arc<Service>>,
+    member: TeamMember,
+    payload: web::Json<RequireMfaBody>,
+) -> actix_web::Result<impl Responder> {
+    member.require(Permission::TeamSecurity)?;
+
+    // Keep the owner from locking themselves into limited access.
+    if payload.required {
+        match service.mfa_service.is_enabled(member.user_id).await {
+            Ok(true) => {}
+            Ok(false) => {
+                return Ok(ApiResponse::<()>::bad_request(Some(
+                    "Enable two-factor authentication on your account first",
+                )))
             }
+            Err(e) => return Ok(ApiResponse::<()>::bad_request(Some(&e.to_string()))),
         }
-        Err(e) => return ApiResponse::<()>::bad_request(Some(&e.to_string())),
     }
 
-    handle_response_by_service(res)
+    let res = service
+        .mfa_service
+        .set_team_required(member.team_id, member.user_id, payload.required)
+        .await;
+
+    Ok(handle_response_by_service(res))
 }
 
-#[delete("/delete/{id}")]
-async fn delete_team(
+#[derive(Debug, Deserialize)]
+pub struct AllowJoinRequestsBody {
+    pub allowed: bool,
+}
+
+#[put("/allow-join-requests/{team_id}")]
+async fn allow_join_requests(
     service: web::Data<Arc<Service>>,
-    id: web::Path<Uuid>,
-    token: BearerAuth,
-) -> impl Responder {
-    let token_str = token.token();
-    let user_id = service
-        .auth_service
-        .get_user_id_by_token(token_str)
-        .await
-        .unwrap();
+    member: TeamMember,
+    payload: web::Json<AllowJoinRequestsBody>,
+) -> actix_web::Result<impl Responder> {
+    member.require(Permission::TeamUpdate)?;
 
-    let team_id = id.into_inner();
-    let role = service
-        .team_user_service
-        .get_user_role_by_team(team_id, user_id)
+    let res = service
+        .team_service
+        .set_allow_join_requests(member.team_id, member.user_id, payload.allowed)
         .await;
+    Ok(handle_response_by_service(res))
+}
 
-    match role {
-        Ok(role) => {
-            if !role.can_remove_team() {
-                return ApiResponse::<()>::forbidden(Some("Only Owner can delete team"));
-            }
-        }
-        Err(e) => return ApiResponse::<()>::bad_request(Some(&e.to_string())),
-    }
+#[derive(Debug, Deserialize)]
+pub struct AllowMultipleOwnersBody {
+    pub allowed: bool,
+}
+
+#[put("/allow-multiple-owners/{team_id}")]
+async fn allow_multiple_owners(
+    service: web::Data<Arc<Service>>,
+    member: TeamMember,
+    payload: web::Json<AllowMultipleOwnersBody>,
+) -> actix_web::Result<impl Responder> {
+    member.require(Permission::TeamTransfer)?;
+
+    let res = service
+        .team_service
+        .set_allow_multiple_owners(member.team_id, member.user_id, payload.allowed)
+        .await;
+    Ok(handle_response_by_service(res))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LogRetentionBody {
+    /// Leave out to keep the log forever.
+    pub days: Option<u32>,
+}
+
+#[put("/log-retention/{team_id}")]
+async fn log_retention(
+    service: web::Data<Arc<Service>>,
+    member: TeamMember,
+    payload: web::Json<LogRetentionBody>,
+) -> actix_web::Result<impl Responder> {
+    member.require(Permission::TeamSecurity)?;
+
+    let res = service
+        .team_service
+        .set_log_retention(member.team_id, member.user_id, payload.days)
+        .await;
+    Ok(handle_response_by_service(res))
+}
+
+#[delete("/delete/{team_id}")]
+async fn delete_team(
+    service: web::Data<Arc<Service>>,
+    member: TeamMember,
+) -> actix_web::Result<impl Responder> {
+    member.require(Permission::TeamDelete)?;
 
     if let Err(e) = service
         .team_user_service
-        .clean_relation_by_team(team_id.clone())
+        .clean_relation_by_team(member.team_id)
         .await
     {
-        return ApiResponse::<()>::bad_request(Some(&e.to_string()));
+        return Ok(ApiResponse::<()>::bad_request(Some(&e.to_string())));
     }
 
-    let res = service.team_service.delete_team(team_id).await;
+    let res = service
+        .team_service
+        .delete_team(member.team_id, member.user_id)
+        .await;
+
+    Ok(handle_response_by_service(res))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InvitationQuery {
+    pub token: String,
+}
+
+/// Public, the invite link leads here before the invitee signs in or up.
+#[get("/invitations/preview")]
+async fn preview_invitation(
+    service: web::Data<Arc<Service>>,
+    query: web::Query<InvitationQuery>,
+) -> impl Responder {
+    let res = service.team_invitation_service.preview(&query.token).await;
+    handle_response_by_service(res)
+}
+
+#[post("/invitations/accept")]
+async fn accept_invitation(
+    service: web::Data<Arc<Service>>,
+    payload: web::Json<InvitationTokenPayload>,
+    user: CurrentUser,
+) -> impl Responder {
+    let res = service
+        .team_invitation_service
+        .accept(&payload.token, user.user_id)
+        .await;
+    handle_response_by_service(res)
+}
 
+/// Public, declining needs no account.
+#[post("/invitations/decline")]
+async fn decline_invitation(
+    service: web::Data<Arc<Service>>,
+    payload: web::Json<InvitationTokenPayload>,
+) -> impl Responder {
+    let res = service
+        .team_invitation_service
+        .decline(&payload.token)
+        .await;
     handle_response_by_service(res)
 }
+
+#[get("/{team_id}/invitations")]
+async fn get_invitations(
+    service: web::Data<Arc<Service>>,
+    member: TeamMember,
+) -> actix_web::Result<impl Responder> {
+    member.require(Permission::MemberInvite)?;
+
+    let res = service
+        .team_invitation_service
+        .find_pending_by_team(member.team_id)
+        .await;
+    Ok(handle_response_by_service(res))
+}
+
+#[post("/{team_id}/invitations")]
+async fn invite_member(
+    service: web::Data<Arc<Service>>,
+    member: TeamMember,
+    payload: web::Json<InviteMemberPayload>,
+) -> actix_web::Result<impl Responder> {
+    member.require(Permission::MemberInvite)?;
+
+    let res = service
+        .team_invitation_service
+        .invite(member.team_id, member.user_id, payload.into_inner())
+        .await;
+    Ok(handle_response_by_service(res))
+}
+
+#[post("/{team_id}/invitations/{invitation_id}/resend")]
+async fn resend_invitation(
+    service: web::Data<Arc<Service>>,
+    member: TeamMember,
+    path: web::Path<(Uuid, Uuid)>,
+) -> actix_web::Result<impl Responder> {
+    let (team_id, invitation_id) = path.into_inner();
+    member.require(Permission::MemberInvite)?;
+
+    let res = service
+        .team_invitation_service
+        .resend(team_id, invitation_id, member.user_id)
+        .await;
+    Ok(handle_response_by_service(res))
+}
+
+#[delete("/{team_id}/invitations/{invitation_id}")]
+async fn revoke_invitation(
+    service: web::Data<Arc<Service>>,
+    member: TeamMember,
+    path: web::Path<(Uuid, Uuid)>,
+) -> actix_web::Result<impl Responder> {
+    let (team_id, invitation_id) = path.into_inner();
+    member.require(Permission::MemberInvite)?;
+
+    let res = service
+        .team_invitation_service
+        .revoke(team_id, invitation_id, member.user_id)
+        .await;
+    Ok(handle_response_by_service(res))
+}
+
+#[post("/join-requests")]
+async fn request_to_join(
+    service: web::Data<Arc<Service>>,
+    payload: web::Json<CreateJoinRequestDto>,
+    user: CurrentUser,
+) -> impl Responder {
+    let res = service
+        .team_join_request_service
+        .create_request(user.user_id, payload.into_inner())
+        .await;
+    handle_response_by_service(res)
+}
+
+#[get("/join-requests/mine")]
+async fn get_my_join_requests(
+    service: web::Data<Arc<Service>>,
+    user: CurrentUser,
+) -> impl Responder {
+    let res = service
+        .team_join_request_service
+        .find_requests_by_user(user.user_id)
+        .await;
+    handle_response_by_service(res)
+}
+
+#[delete("/join-requests/{request_id}")]
+async fn withdraw_join_request(
+    service: web::Data<Arc<Service>>,
+    request_id: web::Path<Uuid>,
+    user: CurrentUser,
+) -> impl Responder {
+    let res = service
+        .team_join_request_service
+        .withdraw(user.user_id, request_id.into_inner())
+        .await;
+    handle_response_by_service(res)
+}
+
+#[get("/{team_id}/join-requests")]
+async fn get_join_requests(
+    service: web::Data<Arc<Service>>,
+    member: TeamMember,
+) -> actix_web::Result<impl Responder> {
+    member.require(Permission::MemberReview)?;
+
+    let res = service
+        .team_join_request_service
+        .find_pending_by_team(member.team_id)
+        .await;
+    Ok(handle_response_by_service(res))
+}
+
+#[post("/{team_id}/join-requests/{request_id}/approve")]
+async fn approve_join_request(
+    service: web::Data<Arc<Service>>,
+    member: TeamMember,
+    path: web::Path<(Uuid, Uuid)>,
+    payload: web::Json<ApproveJoinRequestDto>,
+) -> actix_web::Result<impl Responder> {
+    let (team_id, request_id) = path.into_inner();
+    member.require(Permission::MemberReview)?;
+
+    let res = service
+        .team_join_request_service
+        .approve(team_id, request_id, member.user_id, payload.into_inner())
+        .await;
+    Ok(handle_response_by_service(res))
+}
+
+#[post("/{team_id}/join-requests/{request_id}/reject")]
+async fn reject_join_request(
+    service: web::Data<Arc<Service>>,
+    member: TeamMember,
+    path: web::Path<(Uuid, Uuid)>,
+) -> actix_web::Result<impl Responder> {
+    let (team_id, request_id) = path.into_inner();
+    member.require(Permission::MemberReview)?;
+
+    let res = service
+        .team_join_request_service
+        .reject(team_id, request_id, member.user_id)
+        .await;
+    Ok(handle_response_by_service(res))
+}
+
+#[get("/ownership-transfers/mine")]
+async fn get_my_ownership_transfers(
+    service: web::Data<Arc<Service>>,
+    user: CurrentUser,
+) -> impl Responder {
+    let res = service
+        .team_ownership_service
+        .find_pending_by_nominee(user.user_id)
+        .await;
+    handle_response_by_service(res)
+}
+
+#[post("/ownership-transfers/{transfer_id}/accept")]
+async fn accept_ownership_transfer(
+    service: web::Data<Arc<Service>>,
+    transfer_id: web::Path<Uuid>,
+    user: CurrentUser,
+) -> impl Responder {
+    let res = service
+        .team_ownership_service
+        .accept(transfer_id.into_inner(), user.user_id)
+        .await;
+    handle_response_by_service(res)
+}
+
+#[post("/ownership-transfers/{transfer_id}/decline")]
+async fn decline_ownership_transfer(
+    service: web::Data<Arc<Service>>,
+    transfer_id: web::Path<Uuid>,
+    user: CurrentUser,
+) -> impl Responder {
+    let res = service
+        .team_ownership_service
+        .decline(transfer_id.into_inner(), user.user_id)
+        .await;
+    handle_response_by_service(res)
+}
+
+#[get("/{team_id}/ownership-transfer")]
+async fn get_ownership_transfer(
+    service: web::Data<Arc<Service>>,
+    member: TeamMember,
+) -> actix_web::Result<impl Responder> {
+    member.require(Permission::TeamTransfer)?;
+
+    let res = service
+        .team_ownership_service
+        .find_pending_by_team(member.team_id)
+        .await;
+    Ok(handle_response_by_service(res))
+}
+
+#[post("/{team_id}/ownership-transfer")]
+async fn nominate_owner(
+    service: web::Data<Arc<Service>>,
+    member: TeamMember,
+    payload: web::Json<NominateOwnerDto>,
+) -> actix_web::Result<impl Responder> {
+    member.require(Permission::TeamTransfer)?;
+
+    let res = service
+        .team_ownership_service
+        .nominate(member.team_id, member.user_id, payload.into_inner())
+        .await;
+    Ok(handle_response_by_service(res))
+}
+
+#[delete("/{team_id}/ownership-transfer/{transfer_id}")]
+async fn cancel_ownership_transfer(
+    service: web::Data<Arc<Service>>,
+    member: TeamMember,
+    path: web::Path<(Uuid, Uuid)>,
+) -> actix_web::Result<impl Responder> {
+    let (team_id, transfer_id) = path.into_inner();
+    member.require(Permission::TeamTransfer)?;
+
+    let res = service
+        .team_ownership_service
+        .cancel(team_id, transfer_id, member.user_id)
+        .await;
+    Ok(handle_response_by_service(res))
+}
+
+/// What the caller may do in the team.
+#[get("/{team_id}/permissions")]
+async fn get_permissions(member: TeamMember) -> impl Responder {
+    ApiResponse::success(None, Some(member.permissions))
+}
+
+#[get("/{team_id}/roles")]
+async fn get_roles(
+    service: web::Data<Arc<Service>>,
+    member: TeamMember,
+) -> actix_web::Result<impl Responder> {
+    member.require(Permission::RoleManage)?;
+
+    let res = service.permission_service.find_roles(member.team_id).await;
+    Ok(handle_response_by_service(res))
+}
+
+#[post("/{team_id}/roles")]
+async fn create_role(
+    service: web::Data<Arc<Service>>,
+    member: TeamMember,
+    payload: web::Json<TeamRoleDto>,
+) -> actix_web::Result<impl Responder> {
+    member.require(Permission::RoleManage)?;
+
+    let res = service
+        .permission_service
+        .create_role(member.team_id, member.user_id, payload.into_inner())
+        .await;
+    Ok(handle_response_by_service(res))
+}
+
+#[put("/{team_id}/roles/{role_id}")]
+async fn update_role(
+    service: web::Data<Arc<Service>>,
+    member: TeamMember,
+    path: web::Path<(Uuid, Uuid)>,
+    payload: web::Json<TeamRoleDto>,
+) -> actix_web::Result<impl Responder> {
+    let (team_id, role_id) = path.into_inner();
+    member.require(Permission::RoleManage)?;
+
+    let res = service
+        .permission_service
+        .update_role(team_id, role_id, member.user_id, payload.into_inner())
+        .await;
+    Ok(handle_response_by_service(res))
+}
+
+#[delete("/{team_id}/roles/{role_id}")]
+async fn delete_role(
+    service: web::Data<Arc<Service>>,
+    member: TeamMember,
+    path: web::Path<(Uuid, Uuid)>,
+) -> actix_web::Result<impl Responder> {
+    let (team_id, role_id) = path.into_inner();
+    member.require(Permission::RoleManage)?;
+
+    let res = service
+        .permission_service
+        .delete_role(team_id, role_id, member.user_id)
+        .await;
+    Ok(handle_response_by_service(res))
+}
+
+#[get("/{team_id}/logs")]
+async fn get_logs(
+    service: web::Data<Arc<Service>>,
+    member: TeamMember,
+    query: web::Query<ActivityLogQuery>,
+) -> actix_web::Result<impl Responder> {
+    member.require(Permission::LogView)?;
+
+    let res = service
+        .activity_log_service
+        .find_by_team(member.team_id, query.into_inner())
+        .await;
+    Ok(handle_response_by_service(res))
+}
+
+#[get("/{team_id}/logs/export")]
+async fn export_logs(
+    service: web::Data<Arc<Service>>,
+    member: TeamMember,
+    query: web::Query<ActivityLogExportQuery>,
+) -> actix_web::Result<impl Responder> {
+    member.require(Permission::LogView)?;
+
+    let query = query.into_inner();
+    let format = query.format;
+    let lines = service
+        .activity_log_service
+        .clone()
+        .export(member.team_id, query)
+        .map_err(|e| ErrorBadRequest(e.to_string()))?;
+
+    Ok(HttpResponse::Ok()
+        .content_type(format.content_type())
+        .insert_header((
+            header::CONTENT_DISPOSITION,
+            format!(
+                "attachment; filename=\"activity-log-{}.{}\"",
+                member.team_id,
+                format.extension()
+            ),
+        ))
+        .streaming(lines.map_ok(web::Bytes::from).map_err(ErrorBadRequest)))
+}
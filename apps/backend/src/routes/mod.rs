@@ -1,9 +1,11 @@
 mod common;
+mod well_known;
 use actix_web::web;
 
 pub fn config(cfg: &mut web::ServiceConfig) {
     let api = web::scope("/api/v1").configure(init_api_routes);
     cfg.service(api);
+    cfg.service(web::scope("/.well-known").configure(well_known::init_routes));
 }
 
 pub fn init_api_routes(cfg: &mut web::ServiceConfig) {
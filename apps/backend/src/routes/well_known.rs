@@ -0,0 +1,17 @@
+use std::sync::Arc;
+
+use actix_web::{get, http::header, web, HttpResponse, Responder};
+use service::Service;
+
+pub(crate) fn init_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(jwks);
+}
+
+/// Public keys to verify our access tokens, served as a plain JWK Set rather
+/// than wrapped in `ApiResponse` so standard JWT libraries can consume it.
+#[get("/jwks.json")]
+async fn jwks(service: web::Data<Arc<Service>>) -> impl Responder {
+    HttpResponse::Ok()
+        .insert_header((header::CACHE_CONTROL, "public, max-age=300"))
+        .json(service.auth_service.jwt.jwks())
+}
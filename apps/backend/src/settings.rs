@@ -3,6 +3,11 @@ use std::{env::set_var, fmt::Display};
 use actix_settings::{BasicSettings, Mode};
 use derive_more::derive::Debug;
 use serde::Deserialize;
+use service::{
+    config::{AuditConfig, AuthConfig, JwtConfig, OidcProviderConfig, PasswordPolicyConfig},
+    mailer::MailerConfig,
+    store::RevocationStoreKind,
+};
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize)]
 pub struct PGDb {
@@ -36,8 +41,25 @@ pub struct Cors {
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize)]
 pub struct Token {
-    pub secret: String,
     pub expire: i64,
+    pub refresh_expire: Option<i64>,
+    pub remember_expire: Option<i64>,
+    pub revocation_store: Option<RevocationStoreKind>,
+}
+
+/// A route the auth guard lets through without a token. Either a bare path
+/// pattern or a table narrowing it to some methods, or making the token
+/// optional rather than skipped. Patterns take `*` for one path segment and
+/// `**` for any number of them, e.g. `/api/v1/public/**`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize)]
+#[serde(untagged)]
+pub enum RouteWhite {
+    Path(String),
+    Rule {
+        path: String,
+        methods: Option<Vec<String>>,
+        optional: Option<bool>,
+    },
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize)]
@@ -45,8 +67,14 @@ pub struct ApplicationSettings {
     #[serde(rename = "pg-database")]
     pub pg_database: PGDb,
     pub token: Option<Token>,
-    pub route_whites: Option<Vec<String>>,
+    pub auth: Option<AuthConfig>,
+    pub jwt: Option<JwtConfig>,
+    pub oidc: Option<Vec<OidcProviderConfig>>,
+    pub password: Option<PasswordPolicyConfig>,
+    pub route_whites: Option<Vec<RouteWhite>>,
     pub cors: Option<Cors>,
+    pub mailer: Option<MailerConfig>,
+    pub audit: Option<AuditConfig>,
 }
 
 pub type AppSettings = BasicSettings<ApplicationSettings>;
@@ -57,14 +85,21 @@ pub fn init_settings() -> std::io::Result<AppSettings> {
 
     AppSettings::override_field_with_env_var(&mut settings.actix.hosts, "APPLICATION__HOSTS")?;
 
-    let token = settings.application.token.clone().unwrap_or_else(|| Token {
-        secret: "some-secret".to_string(),
+    let token = settings.application.token.clone().unwrap_or(Token {
         expire: 1000 * 60 * 60,
+        refresh_expire: None,
+        remember_expire: None,
+        revocation_store: None,
     });
 
     unsafe {
-        set_var("JWT_SECRET", token.secret);
         set_var("JWT_EXIPRE", token.expire.to_string());
+        if let Some(refresh_expire) = token.refresh_expire {
+            set_var("JWT_REFRESH_EXPIRE", refresh_expire.to_string());
+        }
+        if let Some(remember_expire) = token.remember_expire {
+            set_var("JWT_REMEMBER_EXPIRE", remember_expire.to_string());
+        }
         set_var(
             "RUST_LOG",
             match &settings.actix.mode {
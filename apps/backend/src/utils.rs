@@ -1,8 +1,22 @@
 use actix_http::h1;
-use actix_web::dev;
+use actix_web::{dev, http::header, HttpRequest};
+use service::common::session_service::ClientInfo;
 
 pub fn bytes_to_payload(buf: actix_web::web::Bytes) -> dev::Payload {
     let (_, mut pl) = h1::Payload::create(true);
     pl.unread_data(buf);
     dev::Payload::from(pl)
 }
+
+/// User agent and address of the caller. The address is the socket peer,
+/// forwarded headers are trivial to spoof.
+pub fn client_info(req: &HttpRequest) -> ClientInfo {
+    ClientInfo {
+        user_agent: req
+            .headers()
+            .get(header::USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.chars().take(255).collect()),
+        ip: req.peer_addr().map(|addr| addr.ip().to_string()),
+    }
+}
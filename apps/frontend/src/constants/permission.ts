@@ -1,3 +1,22 @@
 export const Roles = ['Owner', 'Admin', 'Manager', 'Member', 'Guest'] as const
 
 export type Role = typeof Roles[number]
+
+export const Permissions = [
+  'team.view',
+  'team.update',
+  'team.delete',
+  'team.security',
+  'team.transfer',
+  'member.invite',
+  'member.review',
+  'member.update_role',
+  'member.remove',
+  'role.manage',
+  'api_key.manage',
+  'log.view',
+  'campaign.view',
+  'campaign.write',
+] as const
+
+export type Permission = typeof Permissions[number]
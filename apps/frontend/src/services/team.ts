@@ -1,6 +1,6 @@
 import { z } from 'zod'
 import type { Status } from '~/constants/enum'
-import type { Role } from '~/constants/permission'
+import type { Permission, Role } from '~/constants/permission'
 import { Roles } from '~/constants/permission'
 import { get, post } from '~/lib/request'
 
@@ -30,6 +30,40 @@ export interface TeamUserItem {
   leftedAt?: string | null
 }
 
+/**
+ * Activity log entry, names are missing once the user or target is gone
+ */
+export interface ActivityLogItem {
+  logId: string
+  action: string
+  targetType: string
+  targetId: string
+  targetLabel?: string | null
+  userId: string
+  userName?: string | null
+  actorId?: string | null // Admin impersonating the user
+  actorName?: string | null
+  teamId?: string | null
+  description: string
+  changes?: Record<string, { before: unknown, after: unknown }> | null
+  ip?: string | null
+  userAgent?: string | null
+  createdAt: string // formatted as 'YYYY-MM-DD HH:mm:ss'
+}
+
+export interface ActivityLogPage {
+  items: ActivityLogItem[]
+  nextCursor?: string | null
+}
+
+export interface ActivityLogFilters {
+  actorId?: string
+  action?: string
+  targetType?: string
+  from?: string // 'YYYY-MM-DD'
+  to?: string // 'YYYY-MM-DD'
+}
+
 //
 // Schemas for API Validation
 //
@@ -53,23 +87,29 @@ export const leaveTeamSchema = z.object({
 })
 export type LeaveTeamSchema = z.infer<typeof leaveTeamSchema>
 
-// Join Team Schema
-export const joinTeamSchema = z.object({
-  teamId: z.string().uuid('Invalid team ID.'),
-  userId: z.string().uuid('Invalid user ID.'),
+// Invite Member Schema
+export const inviteMemberSchema = z.object({
+  email: z.string().email('Invalid email address.'),
   role: z.enum(Roles),
 })
-export type JoinTeamSchema = z.infer<typeof joinTeamSchema>
+export type InviteMemberSchema = z.infer<typeof inviteMemberSchema>
+
+// Join Request Schema
+export const joinRequestSchema = z.object({
+  teamNamespace: z.string().min(1, 'Namespace is required.'),
+  message: z.string().max(500, 'Message is too long.').optional(),
+})
+export type JoinRequestSchema = z.infer<typeof joinRequestSchema>
 //
 // API Requests
 //
 
 /**
  * Get All Team List
- * Fetch all teams in the system.
+ * Fetch all teams in the system, platform admins only.
  */
 export async function getTeamList() {
-  return await get<TeamItem[]>('/team/list')
+  return await get<TeamItem[]>('/admin/teams')
 }
 
 /**
@@ -118,12 +158,51 @@ export async function leaveTeam(data: LeaveTeamSchema) {
 }
 
 /**
- * Join Team
- * Join a specific team with a given role.
- * @param data The data for joining the team.
+ * Invite Member
+ * Email an invitation to join the team with a given role.
+ * @param teamId The unique ID of the team.
+ * @param data The email and role of the invitee.
  */
-export async function joinTeam(data: JoinTeamSchema) {
-  return await post('/team/join-team', data)
+export async function inviteMember(teamId: string, data: InviteMemberSchema) {
+  return await post(`/team/${teamId}/invitations`, data)
+}
+
+/**
+ * Accept Invitation
+ * Join the team an invitation link was sent for.
+ * @param token The token from the invitation link.
+ */
+export async function acceptInvitation(token: string) {
+  return await post('/team/invitations/accept', { token })
+}
+
+/**
+ * Request To Join Team
+ * Ask the admins of the team with the given namespace to let you in.
+ * @param data The namespace of the team and an optional message.
+ */
+export async function requestToJoinTeam(data: JoinRequestSchema) {
+  return await post('/team/join-requests', data)
+}
+
+/**
+ * Approve Join Request
+ * Let the requester join the team with the chosen role.
+ * @param teamId The unique ID of the team.
+ * @param requestId The unique ID of the join request.
+ * @param role The role the requester joins with.
+ */
+export async function approveJoinRequest(teamId: string, requestId: string, role: Role) {
+  return await post(`/team/${teamId}/join-requests/${requestId}/approve`, { role })
+}
+
+/**
+ * Get Team Permissions
+ * Fetch the permissions the current user holds in the team.
+ * @param teamId The unique ID of the team.
+ */
+export async function getTeamPermissions(teamId: string) {
+  return await get<Permission[]>(`/team/${teamId}/permissions`)
 }
 
 /**
@@ -144,3 +223,19 @@ export async function updateTeamInfo(teamId: string, data: Partial<CreateTeamSch
 export async function deleteTeam(teamId: string) {
   return await post(`/team/delete/${teamId}`)
 }
+
+/**
+ * Get Team Logs
+ * Fetch one page of the activity log of the team, newest first.
+ * @param teamId The unique ID of the team.
+ * @param filters Optional filters on actor, action, target type and dates.
+ * @param cursor The `nextCursor` of the previous page.
+ */
+export async function getTeamLogs(teamId: string, filters: ActivityLogFilters = {}, cursor?: string) {
+  const params: Record<string, string> = {}
+  for (const [key, value] of Object.entries({ ...filters, cursor })) {
+    if (value)
+      params[key] = value
+  }
+  return await get<ActivityLogPage>(`/team/${teamId}/logs`, params)
+}
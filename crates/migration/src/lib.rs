@@ -5,6 +5,28 @@ mod m20241114_124406_team;
 mod m20241114_124420_team_user;
 mod m20241114_124434_user;
 mod m20241114_125409_activity_log;
+mod m20241201_093012_session;
+mod m20241203_101544_token_revocation;
+mod m20241205_143210_verification_code;
+mod m20241208_110327_user_email_verified;
+mod m20241210_152248_mfa;
+mod m20241212_094215_api_key;
+mod m20241214_160402_oidc_identity;
+mod m20241216_101837_login_attempt;
+mod m20241218_140522_session_device;
+mod m20241220_091530_user_suspension;
+mod m20241222_103318_super_admin;
+mod m20241224_150244_impersonation;
+mod m20241226_111907_team_invitation;
+mod m20241228_093642_team_join_request;
+mod m20241230_104512_ownership_transfer;
+mod m20250102_094517_team_role;
+mod m20250105_101230_activity_log_audit;
+mod m20250107_092140_activity_log_team;
+mod m20250109_101522_team_log_retention;
+mod m20250111_094208_rotated_refresh_token;
+mod m20250113_103517_totp_lock;
+mod m20250115_091044_platform_role;
 
 #[async_trait]
 impl MigratorTrait for Migrator {
@@ -14,6 +36,28 @@ impl MigratorTrait for Migrator {
             Box::new(m20241114_124420_team_user::Migration),
             Box::new(m20241114_124434_user::Migration),
             Box::new(m20241114_125409_activity_log::Migration),
+            Box::new(m20241201_093012_session::Migration),
+            Box::new(m20241203_101544_token_revocation::Migration),
+            Box::new(m20241205_143210_verification_code::Migration),
+            Box::new(m20241208_110327_user_email_verified::Migration),
+            Box::new(m20241210_152248_mfa::Migration),
+            Box::new(m20241212_094215_api_key::Migration),
+            Box::new(m20241214_160402_oidc_identity::Migration),
+            Box::new(m20241216_101837_login_attempt::Migration),
+            Box::new(m20241218_140522_session_device::Migration),
+            Box::new(m20241220_091530_user_suspension::Migration),
+            Box::new(m20241222_103318_super_admin::Migration),
+            Box::new(m20241224_150244_impersonation::Migration),
+            Box::new(m20241226_111907_team_invitation::Migration),
+            Box::new(m20241228_093642_team_join_request::Migration),
+            Box::new(m20241230_104512_ownership_transfer::Migration),
+            Box::new(m20250102_094517_team_role::Migration),
+            Box::new(m20250105_101230_activity_log_audit::Migration),
+            Box::new(m20250107_092140_activity_log_team::Migration),
+            Box::new(m20250109_101522_team_log_retention::Migration),
+            Box::new(m20250111_094208_rotated_refresh_token::Migration),
+            Box::new(m20250113_103517_totp_lock::Migration),
+            Box::new(m20250115_091044_platform_role::Migration),
         ]
     }
 }
@@ -0,0 +1,72 @@
+use async_trait::async_trait;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum Sessions {
+    Table,
+    SessionId,
+    UserId,
+    RefreshTokenHash,
+    Remember,
+    ExpiresAt,
+    RevokedAt,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Sessions::Table)
+                    .if_not_exists()
+                    .col(
+                        uuid(Sessions::SessionId)
+                            .default(SimpleExpr::Custom("gen_random_uuid()".into()))
+                            .not_null()
+                            .primary_key()
+                            .comment("Session family identifier"),
+                    )
+                    .col(uuid(Sessions::UserId).not_null())
+                    .col(
+                        string(Sessions::RefreshTokenHash)
+                            .not_null()
+                            .comment("SHA-256 of the only refresh token currently valid"),
+                    )
+                    .col(boolean(Sessions::Remember).default(false))
+                    .col(date_time(Sessions::ExpiresAt).not_null())
+                    .col(date_time_null(Sessions::RevokedAt))
+                    .col(
+                        date_time(Sessions::CreatedAt)
+                            .default(SimpleExpr::Custom("CURRENT_TIMESTAMP".into())),
+                    )
+                    .col(
+                        date_time(Sessions::UpdatedAt)
+                            .default(SimpleExpr::Custom("CURRENT_TIMESTAMP".into())),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-sessions-user_id")
+                    .table(Sessions::Table)
+                    .col(Sessions::UserId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Sessions::Table).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,74 @@
+use async_trait::async_trait;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum RevokedTokens {
+    Table,
+    Jti,
+    ExpiresAt,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum UserTokenRevocations {
+    Table,
+    UserId,
+    RevokedBefore,
+}
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(RevokedTokens::Table)
+                    .if_not_exists()
+                    .col(
+                        string(RevokedTokens::Jti)
+                            .not_null()
+                            .primary_key()
+                            .comment("Token id of a single revoked token"),
+                    )
+                    .col(
+                        date_time(RevokedTokens::ExpiresAt)
+                            .not_null()
+                            .comment("Row can be purged once the token itself has expired"),
+                    )
+                    .col(
+                        date_time(RevokedTokens::CreatedAt)
+                            .default(SimpleExpr::Custom("CURRENT_TIMESTAMP".into())),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(UserTokenRevocations::Table)
+                    .if_not_exists()
+                    .col(uuid(UserTokenRevocations::UserId).not_null().primary_key())
+                    .col(
+                        date_time(UserTokenRevocations::RevokedBefore)
+                            .not_null()
+                            .comment("Every token of the user issued before this time is revoked"),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(UserTokenRevocations::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(RevokedTokens::Table).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,98 @@
+use async_trait::async_trait;
+use extension::postgres::Type;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+#[sea_orm(enum_name = "code_purposes")]
+pub enum CodePurposes {
+    #[sea_orm(iden = "code_purposes")]
+    Enum,
+    #[sea_orm(iden = "password_reset")]
+    PasswordReset,
+}
+
+#[derive(DeriveIden)]
+enum VerificationCodes {
+    Table,
+    CodeId,
+    UserId,
+    Email,
+    Purpose,
+    CodeHash,
+    Attempts,
+    ExpiresAt,
+    UsedAt,
+    CreatedAt,
+}
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_type(
+                Type::create()
+                    .as_enum(CodePurposes::Enum)
+                    .values(vec![CodePurposes::PasswordReset])
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(VerificationCodes::Table)
+                    .if_not_exists()
+                    .col(
+                        uuid(VerificationCodes::CodeId)
+                            .default(SimpleExpr::Custom("gen_random_uuid()".into()))
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(uuid(VerificationCodes::UserId).not_null())
+                    .col(string(VerificationCodes::Email).not_null())
+                    .col(
+                        ColumnDef::new(VerificationCodes::Purpose)
+                            .custom(CodePurposes::Enum)
+                            .not_null(),
+                    )
+                    .col(
+                        string(VerificationCodes::CodeHash)
+                            .not_null()
+                            .comment("SHA-256 of the code sent to the user"),
+                    )
+                    .col(integer(VerificationCodes::Attempts).default(0))
+                    .col(date_time(VerificationCodes::ExpiresAt).not_null())
+                    .col(date_time_null(VerificationCodes::UsedAt))
+                    .col(
+                        date_time(VerificationCodes::CreatedAt)
+                            .default(SimpleExpr::Custom("CURRENT_TIMESTAMP".into())),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-verification_codes-email-purpose")
+                    .table(VerificationCodes::Table)
+                    .col(VerificationCodes::Email)
+                    .col(VerificationCodes::Purpose)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(VerificationCodes::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_type(Type::drop().name(CodePurposes::Enum).to_owned())
+            .await
+    }
+}
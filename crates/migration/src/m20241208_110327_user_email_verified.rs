@@ -0,0 +1,71 @@
+use async_trait::async_trait;
+use extension::postgres::Type;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+#[sea_orm(enum_name = "code_purposes")]
+pub enum CodePurposes {
+    #[sea_orm(iden = "code_purposes")]
+    Enum,
+    #[sea_orm(iden = "email_verify")]
+    EmailVerify,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    EmailVerifiedAt,
+    CreatedAt,
+}
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_type(
+                Type::alter()
+                    .name(CodePurposes::Enum)
+                    .add_value(CodePurposes::EmailVerify)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Users::Table)
+                    .add_column_if_not_exists(
+                        date_time_null(Users::EmailVerifiedAt)
+                            .comment("When the current email address was verified"),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Accounts created before verification existed are trusted as-is.
+        manager
+            .exec_stmt(
+                Query::update()
+                    .table(Users::Table)
+                    .value(Users::EmailVerifiedAt, Expr::col(Users::CreatedAt))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Postgres cannot drop a value from an enum type, `email_verify` stays.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Users::Table)
+                    .drop_column(Users::EmailVerifiedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,170 @@
+use async_trait::async_trait;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum UserTotp {
+    Table,
+    UserId,
+    Secret,
+    LastUsedStep,
+    EnabledAt,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum RecoveryCodes {
+    Table,
+    CodeId,
+    UserId,
+    CodeHash,
+    UsedAt,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum MfaChallenges {
+    Table,
+    ChallengeId,
+    UserId,
+    TokenHash,
+    Remember,
+    Attempts,
+    ExpiresAt,
+    UsedAt,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Teams {
+    Table,
+    RequireMfa,
+}
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(UserTotp::Table)
+                    .if_not_exists()
+                    .col(uuid(UserTotp::UserId).not_null().primary_key())
+                    .col(
+                        string(UserTotp::Secret)
+                            .not_null()
+                            .comment("Base32 encoded TOTP secret"),
+                    )
+                    .col(
+                        big_integer(UserTotp::LastUsedStep)
+                            .default(0)
+                            .comment("Time step of the last accepted code, blocks replays"),
+                    )
+                    .col(
+                        date_time_null(UserTotp::EnabledAt)
+                            .comment("Null until the user confirmed the first code"),
+                    )
+                    .col(
+                        date_time(UserTotp::CreatedAt)
+                            .default(SimpleExpr::Custom("CURRENT_TIMESTAMP".into())),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(RecoveryCodes::Table)
+                    .if_not_exists()
+                    .col(
+                        uuid(RecoveryCodes::CodeId)
+                            .default(SimpleExpr::Custom("gen_random_uuid()".into()))
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(uuid(RecoveryCodes::UserId).not_null())
+                    .col(string(RecoveryCodes::CodeHash).not_null())
+                    .col(date_time_null(RecoveryCodes::UsedAt))
+                    .col(
+                        date_time(RecoveryCodes::CreatedAt)
+                            .default(SimpleExpr::Custom("CURRENT_TIMESTAMP".into())),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-recovery_codes-user_id")
+                    .table(RecoveryCodes::Table)
+                    .col(RecoveryCodes::UserId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(MfaChallenges::Table)
+                    .if_not_exists()
+                    .col(
+                        uuid(MfaChallenges::ChallengeId)
+                            .default(SimpleExpr::Custom("gen_random_uuid()".into()))
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(uuid(MfaChallenges::UserId).not_null())
+                    .col(string(MfaChallenges::TokenHash).not_null())
+                    .col(boolean(MfaChallenges::Remember).default(false))
+                    .col(integer(MfaChallenges::Attempts).default(0))
+                    .col(date_time(MfaChallenges::ExpiresAt).not_null())
+                    .col(date_time_null(MfaChallenges::UsedAt))
+                    .col(
+                        date_time(MfaChallenges::CreatedAt)
+                            .default(SimpleExpr::Custom("CURRENT_TIMESTAMP".into())),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Teams::Table)
+                    .add_column_if_not_exists(
+                        boolean(Teams::RequireMfa)
+                            .default(false)
+                            .comment("Members must enable two-factor authentication"),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Teams::Table)
+                    .drop_column(Teams::RequireMfa)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(MfaChallenges::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(RecoveryCodes::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(UserTotp::Table).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,121 @@
+use async_trait::async_trait;
+use extension::postgres::Type;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+#[sea_orm(enum_name = "target_types")]
+pub enum TargetTypes {
+    #[sea_orm(iden = "target_types")]
+    Enum,
+    #[sea_orm(iden = "api_key")]
+    ApiKey,
+}
+
+#[derive(DeriveIden)]
+enum ApiKeys {
+    Table,
+    KeyId,
+    Name,
+    Prefix,
+    KeyHash,
+    UserId,
+    TeamId,
+    Scopes,
+    ExpiresAt,
+    LastUsedAt,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_type(
+                Type::alter()
+                    .name(TargetTypes::Enum)
+                    .add_value(TargetTypes::ApiKey)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(ApiKeys::Table)
+                    .if_not_exists()
+                    .col(
+                        uuid(ApiKeys::KeyId)
+                            .default(SimpleExpr::Custom("gen_random_uuid()".into()))
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(string(ApiKeys::Name).not_null())
+                    .col(
+                        string(ApiKeys::Prefix)
+                            .not_null()
+                            .unique_key()
+                            .comment("Public head of the key, used to look it up and identify it"),
+                    )
+                    .col(
+                        string(ApiKeys::KeyHash)
+                            .not_null()
+                            .comment("SHA-256 of the full key"),
+                    )
+                    .col(
+                        uuid(ApiKeys::UserId)
+                            .not_null()
+                            .comment("Owner of a personal key, creator of a team key"),
+                    )
+                    .col(uuid_null(ApiKeys::TeamId).comment("Set for team-owned keys"))
+                    .col(
+                        string(ApiKeys::Scopes)
+                            .not_null()
+                            .comment("Space separated list of granted scopes"),
+                    )
+                    .col(date_time(ApiKeys::ExpiresAt).not_null())
+                    .col(date_time_null(ApiKeys::LastUsedAt))
+                    .col(
+                        date_time(ApiKeys::CreatedAt)
+                            .default(SimpleExpr::Custom("CURRENT_TIMESTAMP".into())),
+                    )
+                    .col(
+                        date_time(ApiKeys::UpdatedAt)
+                            .default(SimpleExpr::Custom("CURRENT_TIMESTAMP".into())),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-api_keys-user_id")
+                    .table(ApiKeys::Table)
+                    .col(ApiKeys::UserId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-api_keys-team_id")
+                    .table(ApiKeys::Table)
+                    .col(ApiKeys::TeamId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Postgres cannot drop a value from an enum type, `api_key` stays.
+        manager
+            .drop_table(Table::drop().table(ApiKeys::Table).to_owned())
+            .await
+    }
+}
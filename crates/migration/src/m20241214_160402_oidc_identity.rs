@@ -0,0 +1,127 @@
+use async_trait::async_trait;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum UserIdentities {
+    Table,
+    IdentityId,
+    UserId,
+    Provider,
+    Subject,
+    Email,
+    LastLoginAt,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum OidcStates {
+    Table,
+    StateHash,
+    Provider,
+    CodeVerifier,
+    Nonce,
+    UserId,
+    Remember,
+    ExpiresAt,
+    CreatedAt,
+}
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(UserIdentities::Table)
+                    .if_not_exists()
+                    .col(
+                        uuid(UserIdentities::IdentityId)
+                            .default(SimpleExpr::Custom("gen_random_uuid()".into()))
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(uuid(UserIdentities::UserId).not_null())
+                    .col(
+                        string(UserIdentities::Provider)
+                            .not_null()
+                            .comment("Provider name from the `oidc` settings"),
+                    )
+                    .col(
+                        string(UserIdentities::Subject)
+                            .not_null()
+                            .comment("`sub` claim of the provider's ID token"),
+                    )
+                    .col(string_null(UserIdentities::Email))
+                    .col(date_time_null(UserIdentities::LastLoginAt))
+                    .col(
+                        date_time(UserIdentities::CreatedAt)
+                            .default(SimpleExpr::Custom("CURRENT_TIMESTAMP".into())),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-user_identities-provider-subject")
+                    .table(UserIdentities::Table)
+                    .col(UserIdentities::Provider)
+                    .col(UserIdentities::Subject)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-user_identities-user_id")
+                    .table(UserIdentities::Table)
+                    .col(UserIdentities::UserId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(OidcStates::Table)
+                    .if_not_exists()
+                    .col(
+                        string(OidcStates::StateHash)
+                            .not_null()
+                            .primary_key()
+                            .comment("SHA-256 of the `state` parameter"),
+                    )
+                    .col(string(OidcStates::Provider).not_null())
+                    .col(string(OidcStates::CodeVerifier).not_null())
+                    .col(string(OidcStates::Nonce).not_null())
+                    .col(
+                        uuid_null(OidcStates::UserId)
+                            .comment("Set when a logged in user links a new identity"),
+                    )
+                    .col(boolean(OidcStates::Remember).default(false))
+                    .col(date_time(OidcStates::ExpiresAt).not_null())
+                    .col(
+                        date_time(OidcStates::CreatedAt)
+                            .default(SimpleExpr::Custom("CURRENT_TIMESTAMP".into())),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(OidcStates::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(UserIdentities::Table).to_owned())
+            .await
+    }
+}
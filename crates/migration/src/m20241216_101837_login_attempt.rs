@@ -0,0 +1,92 @@
+use async_trait::async_trait;
+use extension::postgres::Type;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+#[sea_orm(enum_name = "actions")]
+pub enum Actions {
+    #[sea_orm(iden = "actions")]
+    Enum,
+    #[sea_orm(iden = "locked")]
+    Locked,
+    #[sea_orm(iden = "unlocked")]
+    Unlocked,
+}
+
+#[derive(DeriveIden)]
+#[sea_orm(enum_name = "code_purposes")]
+pub enum CodePurposes {
+    #[sea_orm(iden = "code_purposes")]
+    Enum,
+    #[sea_orm(iden = "account_unlock")]
+    AccountUnlock,
+}
+
+#[derive(DeriveIden)]
+enum LoginAttempts {
+    Table,
+    AttemptKey,
+    Failures,
+    LockedUntil,
+    LastFailedAt,
+}
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for value in [Actions::Locked, Actions::Unlocked] {
+            manager
+                .alter_type(
+                    Type::alter()
+                        .name(Actions::Enum)
+                        .add_value(value)
+                        .if_not_exists()
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        manager
+            .alter_type(
+                Type::alter()
+                    .name(CodePurposes::Enum)
+                    .add_value(CodePurposes::AccountUnlock)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(LoginAttempts::Table)
+                    .if_not_exists()
+                    .col(
+                        string(LoginAttempts::AttemptKey)
+                            .not_null()
+                            .primary_key()
+                            .comment("`account:<email>` or `ip:<address>`"),
+                    )
+                    .col(
+                        integer(LoginAttempts::Failures)
+                            .default(0)
+                            .comment("Failed logins since the last success or quiet period"),
+                    )
+                    .col(date_time_null(LoginAttempts::LockedUntil))
+                    .col(date_time(LoginAttempts::LastFailedAt).not_null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Postgres cannot drop a value from an enum type, `locked`, `unlocked`
+        // and `account_unlock` stay.
+        manager
+            .drop_table(Table::drop().table(LoginAttempts::Table).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,133 @@
+use async_trait::async_trait;
+use extension::postgres::Type;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum Sessions {
+    Table,
+    UserAgent,
+    Ip,
+    LastSeenAt,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+#[sea_orm(enum_name = "notification_types")]
+pub enum NotificationTypes {
+    #[sea_orm(iden = "notification_types")]
+    Enum,
+    #[sea_orm(iden = "new_device")]
+    NewDevice,
+}
+
+#[derive(DeriveIden)]
+enum Notifications {
+    Table,
+    NotificationId,
+    UserId,
+    NotificationType,
+    Title,
+    Content,
+    ReadAt,
+    CreatedAt,
+}
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Sessions::Table)
+                    .add_column_if_not_exists(string_null(Sessions::UserAgent))
+                    .add_column_if_not_exists(string_null(Sessions::Ip))
+                    .add_column_if_not_exists(
+                        date_time(Sessions::LastSeenAt)
+                            .default(SimpleExpr::Custom("CURRENT_TIMESTAMP".into()))
+                            .comment("Last authenticated request, updated at most once a minute"),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .exec_stmt(
+                Query::update()
+                    .table(Sessions::Table)
+                    .value(Sessions::LastSeenAt, Expr::col(Sessions::CreatedAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_type(
+                Type::create()
+                    .as_enum(NotificationTypes::Enum)
+                    .values(vec![NotificationTypes::NewDevice])
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(Notifications::Table)
+                    .if_not_exists()
+                    .col(
+                        uuid(Notifications::NotificationId)
+                            .default(SimpleExpr::Custom("gen_random_uuid()".into()))
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(uuid(Notifications::UserId).not_null())
+                    .col(
+                        ColumnDef::new(Notifications::NotificationType)
+                            .custom(NotificationTypes::Enum)
+                            .not_null(),
+                    )
+                    .col(string(Notifications::Title).not_null())
+                    .col(text(Notifications::Content).not_null())
+                    .col(date_time_null(Notifications::ReadAt))
+                    .col(
+                        date_time(Notifications::CreatedAt)
+                            .default(SimpleExpr::Custom("CURRENT_TIMESTAMP".into())),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-notifications-user_id")
+                    .table(Notifications::Table)
+                    .col(Notifications::UserId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Notifications::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_type(Type::drop().name(NotificationTypes::Enum).to_owned())
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Sessions::Table)
+                    .drop_column(Sessions::UserAgent)
+                    .drop_column(Sessions::Ip)
+                    .drop_column(Sessions::LastSeenAt)
+                    .to_owned(),
+            )
+            .await
+    }
+}
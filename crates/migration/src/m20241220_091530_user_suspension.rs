@@ -0,0 +1,122 @@
+use async_trait::async_trait;
+use extension::postgres::Type;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+#[sea_orm(enum_name = "status")]
+pub enum Status {
+    #[sea_orm(iden = "status")]
+    Enum,
+    #[sea_orm(iden = "suspended")]
+    Suspended,
+}
+
+#[derive(DeriveIden)]
+#[sea_orm(enum_name = "actions")]
+pub enum Actions {
+    #[sea_orm(iden = "actions")]
+    Enum,
+    #[sea_orm(iden = "suspended")]
+    Suspended,
+    #[sea_orm(iden = "reactivated")]
+    Reactivated,
+}
+
+#[derive(DeriveIden)]
+#[sea_orm(enum_name = "platform_roles")]
+pub enum PlatformRoles {
+    #[sea_orm(iden = "platform_roles")]
+    Enum,
+    #[sea_orm(iden = "user")]
+    User,
+    #[sea_orm(iden = "admin")]
+    Admin,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    PlatformRole,
+    SuspendedReason,
+    SuspendedUntil,
+}
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_type(
+                Type::alter()
+                    .name(Status::Enum)
+                    .add_value(Status::Suspended)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await?;
+
+        for value in [Actions::Suspended, Actions::Reactivated] {
+            manager
+                .alter_type(
+                    Type::alter()
+                        .name(Actions::Enum)
+                        .add_value(value)
+                        .if_not_exists()
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        manager
+            .create_type(
+                Type::create()
+                    .as_enum(PlatformRoles::Enum)
+                    .values(vec![PlatformRoles::User, PlatformRoles::Admin])
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Users::Table)
+                    .add_column_if_not_exists(
+                        ColumnDef::new(Users::PlatformRole)
+                            .custom(PlatformRoles::Enum)
+                            .default(PlatformRoles::User.to_string())
+                            .not_null()
+                            .comment("Role across the whole platform, unrelated to team roles"),
+                    )
+                    .add_column_if_not_exists(
+                        string_null(Users::SuspendedReason).comment("Shown to a suspended user"),
+                    )
+                    .add_column_if_not_exists(
+                        date_time_null(Users::SuspendedUntil)
+                            .comment("Suspension end, null while suspended means indefinitely"),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Postgres cannot drop a value from an enum type, `suspended` and
+        // `reactivated` stay.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Users::Table)
+                    .drop_column(Users::PlatformRole)
+                    .drop_column(Users::SuspendedReason)
+                    .drop_column(Users::SuspendedUntil)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_type(Type::drop().name(PlatformRoles::Enum).to_owned())
+            .await
+    }
+}
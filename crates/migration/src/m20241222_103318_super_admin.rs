@@ -0,0 +1,35 @@
+use async_trait::async_trait;
+use extension::postgres::Type;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+#[sea_orm(enum_name = "platform_roles")]
+pub enum PlatformRoles {
+    #[sea_orm(iden = "platform_roles")]
+    Enum,
+    #[sea_orm(iden = "super_admin")]
+    SuperAdmin,
+}
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_type(
+                Type::alter()
+                    .name(PlatformRoles::Enum)
+                    .add_value(PlatformRoles::SuperAdmin)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        // Postgres cannot drop a value from an enum type, `super_admin` stays.
+        Ok(())
+    }
+}
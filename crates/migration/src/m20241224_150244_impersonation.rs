@@ -0,0 +1,65 @@
+use async_trait::async_trait;
+use extension::postgres::Type;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+#[sea_orm(enum_name = "actions")]
+pub enum Actions {
+    #[sea_orm(iden = "actions")]
+    Enum,
+    #[sea_orm(iden = "impersonated")]
+    Impersonated,
+    #[sea_orm(iden = "requested")]
+    Requested,
+}
+
+#[derive(DeriveIden)]
+enum ActivityLog {
+    Table,
+    ActorId,
+}
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for value in [Actions::Impersonated, Actions::Requested] {
+            manager
+                .alter_type(
+                    Type::alter()
+                        .name(Actions::Enum)
+                        .add_value(value)
+                        .if_not_exists()
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ActivityLog::Table)
+                    .add_column_if_not_exists(
+                        uuid_null(ActivityLog::ActorId)
+                            .comment("Admin acting as `user_id` while impersonating"),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Postgres cannot drop a value from an enum type, `impersonated` and
+        // `requested` stay.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ActivityLog::Table)
+                    .drop_column(ActivityLog::ActorId)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,163 @@
+use async_trait::async_trait;
+use extension::postgres::Type;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+#[sea_orm(enum_name = "invitation_status")]
+pub enum InvitationStatus {
+    #[sea_orm(iden = "invitation_status")]
+    Enum,
+    #[sea_orm(iden = "pending")]
+    Pending,
+    #[sea_orm(iden = "accepted")]
+    Accepted,
+    #[sea_orm(iden = "declined")]
+    Declined,
+    #[sea_orm(iden = "revoked")]
+    Revoked,
+}
+
+#[derive(DeriveIden)]
+#[sea_orm(enum_name = "notification_types")]
+pub enum NotificationTypes {
+    #[sea_orm(iden = "notification_types")]
+    Enum,
+    #[sea_orm(iden = "team_invitation")]
+    TeamInvitation,
+}
+
+#[derive(DeriveIden)]
+#[sea_orm(enum_name = "team_user_roles")]
+pub enum TeamUserRoles {
+    #[sea_orm(iden = "team_user_roles")]
+    Enum,
+}
+
+#[derive(DeriveIden)]
+enum TeamInvitations {
+    Table,
+    InvitationId,
+    TeamId,
+    Email,
+    Role,
+    TokenHash,
+    InvitedBy,
+    Status,
+    ExpiresAt,
+    SentAt,
+    RespondedAt,
+    CreatedAt,
+}
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_type(
+                Type::create()
+                    .as_enum(InvitationStatus::Enum)
+                    .values(vec![
+                        InvitationStatus::Pending,
+                        InvitationStatus::Accepted,
+                        InvitationStatus::Declined,
+                        InvitationStatus::Revoked,
+                    ])
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_type(
+                Type::alter()
+                    .name(NotificationTypes::Enum)
+                    .add_value(NotificationTypes::TeamInvitation)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(TeamInvitations::Table)
+                    .if_not_exists()
+                    .col(
+                        uuid(TeamInvitations::InvitationId)
+                            .default(SimpleExpr::Custom("gen_random_uuid()".into()))
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(uuid(TeamInvitations::TeamId).not_null())
+                    .col(
+                        string(TeamInvitations::Email)
+                            .not_null()
+                            .comment("Lowercased address the invite was sent to"),
+                    )
+                    .col(
+                        ColumnDef::new(TeamInvitations::Role)
+                            .custom(TeamUserRoles::Enum)
+                            .not_null(),
+                    )
+                    .col(
+                        string(TeamInvitations::TokenHash)
+                            .not_null()
+                            .unique_key()
+                            .comment("SHA-256 of the token in the invite link"),
+                    )
+                    .col(uuid(TeamInvitations::InvitedBy).not_null())
+                    .col(
+                        ColumnDef::new(TeamInvitations::Status)
+                            .custom(InvitationStatus::Enum)
+                            .default(InvitationStatus::Pending.to_string())
+                            .not_null(),
+                    )
+                    .col(date_time(TeamInvitations::ExpiresAt).not_null())
+                    .col(
+                        date_time(TeamInvitations::SentAt)
+                            .not_null()
+                            .comment("Last time the invite was mailed"),
+                    )
+                    .col(date_time_null(TeamInvitations::RespondedAt))
+                    .col(
+                        date_time(TeamInvitations::CreatedAt)
+                            .default(SimpleExpr::Custom("CURRENT_TIMESTAMP".into())),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-team_invitations-team_id")
+                    .table(TeamInvitations::Table)
+                    .col(TeamInvitations::TeamId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-team_invitations-email")
+                    .table(TeamInvitations::Table)
+                    .col(TeamInvitations::Email)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Postgres cannot drop a value from an enum type, `team_invitation` stays.
+        manager
+            .drop_table(Table::drop().table(TeamInvitations::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_type(Type::drop().name(InvitationStatus::Enum).to_owned())
+            .await
+    }
+}
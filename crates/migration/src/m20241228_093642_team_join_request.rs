@@ -0,0 +1,183 @@
+use async_trait::async_trait;
+use extension::postgres::Type;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+#[sea_orm(enum_name = "join_request_status")]
+pub enum JoinRequestStatus {
+    #[sea_orm(iden = "join_request_status")]
+    Enum,
+    #[sea_orm(iden = "pending")]
+    Pending,
+    #[sea_orm(iden = "approved")]
+    Approved,
+    #[sea_orm(iden = "rejected")]
+    Rejected,
+    #[sea_orm(iden = "withdrawn")]
+    Withdrawn,
+}
+
+#[derive(DeriveIden)]
+#[sea_orm(enum_name = "notification_types")]
+pub enum NotificationTypes {
+    #[sea_orm(iden = "notification_types")]
+    Enum,
+    #[sea_orm(iden = "join_request")]
+    JoinRequest,
+    #[sea_orm(iden = "join_request_reviewed")]
+    JoinRequestReviewed,
+}
+
+#[derive(DeriveIden)]
+#[sea_orm(enum_name = "team_user_roles")]
+pub enum TeamUserRoles {
+    #[sea_orm(iden = "team_user_roles")]
+    Enum,
+}
+
+#[derive(DeriveIden)]
+enum Teams {
+    Table,
+    AllowJoinRequests,
+}
+
+#[derive(DeriveIden)]
+enum TeamJoinRequests {
+    Table,
+    RequestId,
+    TeamId,
+    UserId,
+    Message,
+    Status,
+    Role,
+    ReviewedBy,
+    ReviewedAt,
+    CreatedAt,
+}
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Teams::Table)
+                    .add_column_if_not_exists(
+                        boolean(Teams::AllowJoinRequests)
+                            .default(true)
+                            .comment("Whether users may ask to join the team"),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_type(
+                Type::create()
+                    .as_enum(JoinRequestStatus::Enum)
+                    .values(vec![
+                        JoinRequestStatus::Pending,
+                        JoinRequestStatus::Approved,
+                        JoinRequestStatus::Rejected,
+                        JoinRequestStatus::Withdrawn,
+                    ])
+                    .to_owned(),
+            )
+            .await?;
+
+        for value in [
+            NotificationTypes::JoinRequest,
+            NotificationTypes::JoinRequestReviewed,
+        ] {
+            manager
+                .alter_type(
+                    Type::alter()
+                        .name(NotificationTypes::Enum)
+                        .add_value(value)
+                        .if_not_exists()
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(TeamJoinRequests::Table)
+                    .if_not_exists()
+                    .col(
+                        uuid(TeamJoinRequests::RequestId)
+                            .default(SimpleExpr::Custom("gen_random_uuid()".into()))
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(uuid(TeamJoinRequests::TeamId).not_null())
+                    .col(uuid(TeamJoinRequests::UserId).not_null())
+                    .col(text_null(TeamJoinRequests::Message))
+                    .col(
+                        ColumnDef::new(TeamJoinRequests::Status)
+                            .custom(JoinRequestStatus::Enum)
+                            .default(JoinRequestStatus::Pending.to_string())
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(TeamJoinRequests::Role)
+                            .custom(TeamUserRoles::Enum)
+                            .null()
+                            .comment("Role granted on approval"),
+                    )
+                    .col(uuid_null(TeamJoinRequests::ReviewedBy))
+                    .col(date_time_null(TeamJoinRequests::ReviewedAt))
+                    .col(
+                        date_time(TeamJoinRequests::CreatedAt)
+                            .default(SimpleExpr::Custom("CURRENT_TIMESTAMP".into())),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-team_join_requests-team_id")
+                    .table(TeamJoinRequests::Table)
+                    .col(TeamJoinRequests::TeamId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-team_join_requests-user_id")
+                    .table(TeamJoinRequests::Table)
+                    .col(TeamJoinRequests::UserId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Postgres cannot drop a value from an enum type, `join_request` and
+        // `join_request_reviewed` stay.
+        manager
+            .drop_table(Table::drop().table(TeamJoinRequests::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_type(Type::drop().name(JoinRequestStatus::Enum).to_owned())
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Teams::Table)
+                    .drop_column(Teams::AllowJoinRequests)
+                    .to_owned(),
+            )
+            .await
+    }
+}
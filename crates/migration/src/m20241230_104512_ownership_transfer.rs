@@ -0,0 +1,162 @@
+use async_trait::async_trait;
+use extension::postgres::Type;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+#[sea_orm(enum_name = "transfer_status")]
+pub enum TransferStatus {
+    #[sea_orm(iden = "transfer_status")]
+    Enum,
+    #[sea_orm(iden = "pending")]
+    Pending,
+    #[sea_orm(iden = "accepted")]
+    Accepted,
+    #[sea_orm(iden = "declined")]
+    Declined,
+    #[sea_orm(iden = "cancelled")]
+    Cancelled,
+}
+
+#[derive(DeriveIden)]
+#[sea_orm(enum_name = "notification_types")]
+pub enum NotificationTypes {
+    #[sea_orm(iden = "notification_types")]
+    Enum,
+    #[sea_orm(iden = "ownership_transfer")]
+    OwnershipTransfer,
+}
+
+#[derive(DeriveIden)]
+enum Teams {
+    Table,
+    AllowMultipleOwners,
+}
+
+#[derive(DeriveIden)]
+enum TeamOwnershipTransfers {
+    Table,
+    TransferId,
+    TeamId,
+    FromUserId,
+    ToUserId,
+    KeepOwnership,
+    Status,
+    ExpiresAt,
+    RespondedAt,
+    CreatedAt,
+}
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Teams::Table)
+                    .add_column_if_not_exists(
+                        boolean(Teams::AllowMultipleOwners)
+                            .default(false)
+                            .comment("Whether a transfer may add an Owner instead of swapping"),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_type(
+                Type::create()
+                    .as_enum(TransferStatus::Enum)
+                    .values(vec![
+                        TransferStatus::Pending,
+                        TransferStatus::Accepted,
+                        TransferStatus::Declined,
+                        TransferStatus::Cancelled,
+                    ])
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_type(
+                Type::alter()
+                    .name(NotificationTypes::Enum)
+                    .add_value(NotificationTypes::OwnershipTransfer)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(TeamOwnershipTransfers::Table)
+                    .if_not_exists()
+                    .col(
+                        uuid(TeamOwnershipTransfers::TransferId)
+                            .default(SimpleExpr::Custom("gen_random_uuid()".into()))
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(uuid(TeamOwnershipTransfers::TeamId).not_null())
+                    .col(uuid(TeamOwnershipTransfers::FromUserId).not_null())
+                    .col(uuid(TeamOwnershipTransfers::ToUserId).not_null())
+                    .col(
+                        boolean(TeamOwnershipTransfers::KeepOwnership)
+                            .default(false)
+                            .comment("The nominating Owner stays Owner next to the nominee"),
+                    )
+                    .col(
+                        ColumnDef::new(TeamOwnershipTransfers::Status)
+                            .custom(TransferStatus::Enum)
+                            .default(TransferStatus::Pending.to_string())
+                            .not_null(),
+                    )
+                    .col(date_time(TeamOwnershipTransfers::ExpiresAt))
+                    .col(date_time_null(TeamOwnershipTransfers::RespondedAt))
+                    .col(
+                        date_time(TeamOwnershipTransfers::CreatedAt)
+                            .default(SimpleExpr::Custom("CURRENT_TIMESTAMP".into())),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-team_ownership_transfers-team_id")
+                    .table(TeamOwnershipTransfers::Table)
+                    .col(TeamOwnershipTransfers::TeamId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Postgres cannot drop a value from an enum type, `ownership_transfer`
+        // stays.
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(TeamOwnershipTransfers::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_type(Type::drop().name(TransferStatus::Enum).to_owned())
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Teams::Table)
+                    .drop_column(Teams::AllowMultipleOwners)
+                    .to_owned(),
+            )
+            .await
+    }
+}
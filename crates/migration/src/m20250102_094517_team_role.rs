@@ -0,0 +1,98 @@
+use async_trait::async_trait;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum TeamRoles {
+    Table,
+    RoleId,
+    TeamId,
+    Name,
+    Description,
+    Permissions,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum TeamUsers {
+    Table,
+    CustomRoleId,
+}
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(TeamRoles::Table)
+                    .if_not_exists()
+                    .col(
+                        uuid(TeamRoles::RoleId)
+                            .default(SimpleExpr::Custom("gen_random_uuid()".into()))
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(uuid(TeamRoles::TeamId).not_null())
+                    .col(string(TeamRoles::Name).not_null())
+                    .col(string_null(TeamRoles::Description))
+                    .col(
+                        json_binary(TeamRoles::Permissions)
+                            .default(SimpleExpr::Custom("'[]'::jsonb".into()))
+                            .comment("Names of the granted permissions, e.g. `member.invite`"),
+                    )
+                    .col(
+                        date_time(TeamRoles::CreatedAt)
+                            .default(SimpleExpr::Custom("CURRENT_TIMESTAMP".into())),
+                    )
+                    .col(
+                        date_time(TeamRoles::UpdatedAt)
+                            .default(SimpleExpr::Custom("CURRENT_TIMESTAMP".into())),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-team_roles-team_id-name")
+                    .table(TeamRoles::Table)
+                    .col(TeamRoles::TeamId)
+                    .col(TeamRoles::Name)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TeamUsers::Table)
+                    .add_column_if_not_exists(
+                        uuid_null(TeamUsers::CustomRoleId)
+                            .comment("Custom role whose permissions replace the built-in ones"),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TeamUsers::Table)
+                    .drop_column(TeamUsers::CustomRoleId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(TeamRoles::Table).to_owned())
+            .await
+    }
+}
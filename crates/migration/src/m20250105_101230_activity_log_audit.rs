@@ -0,0 +1,108 @@
+use async_trait::async_trait;
+use extension::postgres::Type;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+#[sea_orm(enum_name = "actions")]
+pub enum Actions {
+    #[sea_orm(iden = "actions")]
+    Enum,
+    #[sea_orm(iden = "invited")]
+    Invited,
+    #[sea_orm(iden = "joined")]
+    Joined,
+    #[sea_orm(iden = "left")]
+    Left,
+    #[sea_orm(iden = "logged_in")]
+    LoggedIn,
+    #[sea_orm(iden = "logged_out")]
+    LoggedOut,
+    #[sea_orm(iden = "login_failed")]
+    LoginFailed,
+}
+
+#[derive(DeriveIden)]
+#[sea_orm(enum_name = "target_types")]
+pub enum TargetTypes {
+    #[sea_orm(iden = "target_types")]
+    Enum,
+    #[sea_orm(iden = "campaign")]
+    Campaign,
+    #[sea_orm(iden = "invitation")]
+    Invitation,
+}
+
+#[derive(DeriveIden)]
+enum ActivityLog {
+    Table,
+    Changes,
+    Ip,
+    UserAgent,
+}
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for value in [
+            Actions::Invited,
+            Actions::Joined,
+            Actions::Left,
+            Actions::LoggedIn,
+            Actions::LoggedOut,
+            Actions::LoginFailed,
+        ] {
+            manager
+                .alter_type(
+                    Type::alter()
+                        .name(Actions::Enum)
+                        .add_value(value)
+                        .if_not_exists()
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        for value in [TargetTypes::Campaign, TargetTypes::Invitation] {
+            manager
+                .alter_type(
+                    Type::alter()
+                        .name(TargetTypes::Enum)
+                        .add_value(value)
+                        .if_not_exists()
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ActivityLog::Table)
+                    .add_column_if_not_exists(json_binary_null(ActivityLog::Changes).comment(
+                        "Changed fields as `{\"field\": {\"before\": .., \"after\": ..}}`",
+                    ))
+                    .add_column_if_not_exists(string_null(ActivityLog::Ip))
+                    .add_column_if_not_exists(string_null(ActivityLog::UserAgent))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Postgres cannot drop a value from an enum type, the new `actions` and
+        // `target_types` values stay.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ActivityLog::Table)
+                    .drop_column(ActivityLog::Changes)
+                    .drop_column(ActivityLog::Ip)
+                    .drop_column(ActivityLog::UserAgent)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,98 @@
+use async_trait::async_trait;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum ActivityLog {
+    Table,
+    TeamId,
+    TargetId,
+    CreatedAt,
+}
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ActivityLog::Table)
+                    .add_column_if_not_exists(
+                        uuid_null(ActivityLog::TeamId)
+                            .comment("Team whose log shows the entry, if any"),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Team and role entries have always pointed at their team, team API
+        // keys know theirs as long as the key still exists. The enum is compared
+        // as text, Postgres refuses values added earlier in the same transaction.
+        manager
+            .exec_stmt(
+                Query::update()
+                    .table(ActivityLog::Table)
+                    .value(ActivityLog::TeamId, Expr::col(ActivityLog::TargetId))
+                    .and_where(Expr::cust("target_type::text IN ('team', 'role')"))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .exec_stmt(
+                Query::update()
+                    .table(ActivityLog::Table)
+                    .value(
+                        ActivityLog::TeamId,
+                        Expr::cust(
+                            "(SELECT team_id FROM api_keys WHERE api_keys.key_id = activity_log.target_id)",
+                        ),
+                    )
+                    .and_where(Expr::cust("target_type::text = 'api_key'"))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-activity_log-team_id-created_at")
+                    .table(ActivityLog::Table)
+                    .col(ActivityLog::TeamId)
+                    .col(ActivityLog::CreatedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-activity_log-created_at")
+                    .table(ActivityLog::Table)
+                    .col(ActivityLog::CreatedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx-activity_log-created_at")
+                    .table(ActivityLog::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ActivityLog::Table)
+                    .drop_column(ActivityLog::TeamId)
+                    .to_owned(),
+            )
+            .await
+    }
+}
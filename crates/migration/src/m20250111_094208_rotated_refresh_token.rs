@@ -0,0 +1,44 @@
+use async_trait::async_trait;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum RotatedRefreshTokens {
+    Table,
+    TokenHash,
+    SessionId,
+    CreatedAt,
+}
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(RotatedRefreshTokens::Table)
+                    .if_not_exists()
+                    .col(
+                        string(RotatedRefreshTokens::TokenHash)
+                            .not_null()
+                            .primary_key()
+                            .comment("SHA-256 of a refresh token that was already exchanged"),
+                    )
+                    .col(uuid(RotatedRefreshTokens::SessionId).not_null())
+                    .col(
+                        date_time(RotatedRefreshTokens::CreatedAt)
+                            .default(SimpleExpr::Custom("CURRENT_TIMESTAMP".into())),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(RotatedRefreshTokens::Table).to_owned())
+            .await
+    }
+}
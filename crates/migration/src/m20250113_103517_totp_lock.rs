@@ -0,0 +1,43 @@
+use async_trait::async_trait;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum UserTotp {
+    Table,
+    FailedAttempts,
+    LockedUntil,
+}
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(UserTotp::Table)
+                    .add_column_if_not_exists(
+                        integer(UserTotp::FailedAttempts)
+                            .default(0)
+                            .comment("Wrong codes in a row, login challenges included"),
+                    )
+                    .add_column_if_not_exists(date_time_null(UserTotp::LockedUntil))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(UserTotp::Table)
+                    .drop_column(UserTotp::FailedAttempts)
+                    .drop_column(UserTotp::LockedUntil)
+                    .to_owned(),
+            )
+            .await
+    }
+}
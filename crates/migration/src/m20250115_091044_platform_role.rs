@@ -0,0 +1,72 @@
+use async_trait::async_trait;
+use extension::postgres::Type;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+#[sea_orm(enum_name = "platform_roles")]
+pub enum PlatformRoles {
+    #[sea_orm(iden = "platform_roles")]
+    Enum,
+    #[sea_orm(iden = "user")]
+    User,
+    #[sea_orm(iden = "super_admin")]
+    SuperAdmin,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    PlatformRole,
+}
+
+/// Takes over the platform role schema from the user suspension migration.
+/// Every step is a no-op on databases where the earlier migrations already
+/// created it, so applied and fresh databases end up with the same schema.
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .get_connection()
+            .execute_unprepared(
+                "DO $$ BEGIN
+                    IF NOT EXISTS (SELECT 1 FROM pg_type WHERE typname = 'platform_roles') THEN
+                        CREATE TYPE platform_roles AS ENUM ('user', 'admin', 'super_admin');
+                    END IF;
+                END $$",
+            )
+            .await?;
+
+        manager
+            .alter_type(
+                Type::alter()
+                    .name(PlatformRoles::Enum)
+                    .add_value(PlatformRoles::SuperAdmin)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Users::Table)
+                    .add_column_if_not_exists(
+                        ColumnDef::new(Users::PlatformRole)
+                            .custom(PlatformRoles::Enum)
+                            .default(PlatformRoles::User.to_string())
+                            .not_null()
+                            .comment("Role across the whole platform, unrelated to team roles"),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        // The user suspension migration drops the column and the type.
+        Ok(())
+    }
+}
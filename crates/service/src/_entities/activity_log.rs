@@ -15,6 +15,12 @@ pub struct Model {
     pub target_id: Uuid,
     pub target_type: TargetTypes,
     pub created_at: DateTime,
+    pub actor_id: Option<Uuid>,
+    #[sea_orm(column_type = "JsonBinary", nullable)]
+    pub changes: Option<Json>,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub team_id: Option<Uuid>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -3,7 +3,24 @@
 pub mod prelude;
 
 pub mod activity_log;
+pub mod api_keys;
+pub mod login_attempts;
+pub mod mfa_challenges;
+pub mod notifications;
+pub mod oidc_states;
+pub mod recovery_codes;
+pub mod revoked_tokens;
+pub mod rotated_refresh_tokens;
 pub mod sea_orm_active_enums;
+pub mod sessions;
+pub mod team_invitations;
+pub mod team_join_requests;
+pub mod team_ownership_transfers;
+pub mod team_roles;
 pub mod team_users;
 pub mod teams;
+pub mod user_identities;
+pub mod user_token_revocations;
+pub mod user_totp;
 pub mod users;
+pub mod verification_codes;
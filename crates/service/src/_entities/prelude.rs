@@ -1,6 +1,23 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.0
 
 pub use super::activity_log::Entity as ActivityLog;
+pub use super::api_keys::Entity as ApiKeys;
+pub use super::login_attempts::Entity as LoginAttempts;
+pub use super::mfa_challenges::Entity as MfaChallenges;
+pub use super::notifications::Entity as Notifications;
+pub use super::oidc_states::Entity as OidcStates;
+pub use super::recovery_codes::Entity as RecoveryCodes;
+pub use super::revoked_tokens::Entity as RevokedTokens;
+pub use super::rotated_refresh_tokens::Entity as RotatedRefreshTokens;
+pub use super::sessions::Entity as Sessions;
+pub use super::team_invitations::Entity as TeamInvitations;
+pub use super::team_join_requests::Entity as TeamJoinRequests;
+pub use super::team_ownership_transfers::Entity as TeamOwnershipTransfers;
+pub use super::team_roles::Entity as TeamRoles;
 pub use super::team_users::Entity as TeamUsers;
 pub use super::teams::Entity as Teams;
+pub use super::user_identities::Entity as UserIdentities;
+pub use super::user_token_revocations::Entity as UserTokenRevocations;
+pub use super::user_totp::Entity as UserTotp;
 pub use super::users::Entity as Users;
+pub use super::verification_codes::Entity as VerificationCodes;
@@ -7,22 +7,112 @@ use sea_orm::entity::prelude::*;
 pub enum Actions {
     #[sea_orm(string_value = "created")]
     Created,
+    #[sea_orm(string_value = "impersonated")]
+    Impersonated,
+    #[sea_orm(string_value = "invited")]
+    Invited,
+    #[sea_orm(string_value = "joined")]
+    Joined,
+    #[sea_orm(string_value = "left")]
+    Left,
+    #[sea_orm(string_value = "locked")]
+    Locked,
+    #[sea_orm(string_value = "logged_in")]
+    LoggedIn,
+    #[sea_orm(string_value = "logged_out")]
+    LoggedOut,
+    #[sea_orm(string_value = "login_failed")]
+    LoginFailed,
+    #[sea_orm(string_value = "reactivated")]
+    Reactivated,
     #[sea_orm(string_value = "removed")]
     Removed,
+    #[sea_orm(string_value = "requested")]
+    Requested,
+    #[sea_orm(string_value = "suspended")]
+    Suspended,
+    #[sea_orm(string_value = "unlocked")]
+    Unlocked,
     #[sea_orm(string_value = "updated")]
     Updated,
 }
 #[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "code_purposes")]
+pub enum CodePurposes {
+    #[sea_orm(string_value = "account_unlock")]
+    AccountUnlock,
+    #[sea_orm(string_value = "email_verify")]
+    EmailVerify,
+    #[sea_orm(string_value = "password_reset")]
+    PasswordReset,
+}
+#[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "invitation_status")]
+pub enum InvitationStatus {
+    #[sea_orm(string_value = "accepted")]
+    Accepted,
+    #[sea_orm(string_value = "declined")]
+    Declined,
+    #[sea_orm(string_value = "pending")]
+    Pending,
+    #[sea_orm(string_value = "revoked")]
+    Revoked,
+}
+#[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "join_request_status")]
+pub enum JoinRequestStatus {
+    #[sea_orm(string_value = "approved")]
+    Approved,
+    #[sea_orm(string_value = "pending")]
+    Pending,
+    #[sea_orm(string_value = "rejected")]
+    Rejected,
+    #[sea_orm(string_value = "withdrawn")]
+    Withdrawn,
+}
+#[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "notification_types")]
+pub enum NotificationTypes {
+    #[sea_orm(string_value = "join_request")]
+    JoinRequest,
+    #[sea_orm(string_value = "join_request_reviewed")]
+    JoinRequestReviewed,
+    #[sea_orm(string_value = "new_device")]
+    NewDevice,
+    #[sea_orm(string_value = "ownership_transfer")]
+    OwnershipTransfer,
+    #[sea_orm(string_value = "team_invitation")]
+    TeamInvitation,
+}
+#[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "platform_roles")]
+pub enum PlatformRoles {
+    #[sea_orm(string_value = "admin")]
+    Admin,
+    #[sea_orm(string_value = "super_admin")]
+    SuperAdmin,
+    #[sea_orm(string_value = "user")]
+    User,
+}
+#[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum)]
 #[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "status")]
 pub enum Status {
     #[sea_orm(string_value = "active")]
     Active,
     #[sea_orm(string_value = "inactive")]
     Inactive,
+    #[sea_orm(string_value = "suspended")]
+    Suspended,
 }
 #[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum)]
 #[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "target_types")]
 pub enum TargetTypes {
+    #[sea_orm(string_value = "api_key")]
+    ApiKey,
+    #[sea_orm(string_value = "campaign")]
+    Campaign,
+    #[sea_orm(string_value = "invitation")]
+    Invitation,
     #[sea_orm(string_value = "role")]
     Role,
     #[sea_orm(string_value = "team")]
@@ -52,3 +142,15 @@ pub enum TeamUserStatus {
     #[sea_orm(string_value = "lefted")]
     Lefted,
 }
+#[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "transfer_status")]
+pub enum TransferStatus {
+    #[sea_orm(string_value = "accepted")]
+    Accepted,
+    #[sea_orm(string_value = "cancelled")]
+    Cancelled,
+    #[sea_orm(string_value = "declined")]
+    Declined,
+    #[sea_orm(string_value = "pending")]
+    Pending,
+}
@@ -15,6 +15,7 @@ pub struct Model {
     pub lefted_at: Option<DateTime>,
     pub role: TeamUserRoles,
     pub status: TeamUserStatus,
+    pub custom_role_id: Option<Uuid>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -14,6 +14,10 @@ pub struct Model {
     pub description: Option<String>,
     pub created_at: DateTime,
     pub updated_at: DateTime,
+    pub require_mfa: bool,
+    pub allow_join_requests: bool,
+    pub allow_multiple_owners: bool,
+    pub log_retention_days: Option<i32>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -1,6 +1,6 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.0
 
-use super::sea_orm_active_enums::Status;
+use super::sea_orm_active_enums::{PlatformRoles, Status};
 use sea_orm::entity::prelude::*;
 
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
@@ -18,6 +18,10 @@ pub struct Model {
     pub status: Status,
     pub created_at: DateTime,
     pub updated_at: DateTime,
+    pub email_verified_at: Option<DateTime>,
+    pub platform_role: PlatformRoles,
+    pub suspended_reason: Option<String>,
+    pub suspended_until: Option<DateTime>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -0,0 +1,827 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::future::Future;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::_entities::prelude::{ActivityLog, ApiKeys, Teams, Users};
+use crate::_entities::sea_orm_active_enums::{Actions, TargetTypes};
+use crate::_entities::{
+    activity_log, api_keys, team_invitations, team_roles, team_users, teams, users,
+};
+use crate::common::session_service::ClientInfo;
+use crate::config::{AuditConfig, RetentionAction};
+use crate::utils::{CursorPage, DbResult};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use flate2::{write::GzEncoder, Compression};
+use futures_util::{stream, Stream};
+use sea_orm::prelude::{Json, Uuid};
+use sea_orm::sqlx::types::chrono;
+use sea_orm::*;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map};
+use tokio::task;
+
+const DEFAULT_PAGE_SIZE: u64 = 50;
+const MAX_PAGE_SIZE: u64 = 200;
+/// Entries archived and deleted at once by the purge job.
+const PURGE_BATCH_SIZE: u64 = 1000;
+
+tokio::task_local! {
+    /// Caller of the request being handled, see [`ActivityLogService::scope`].
+    static CLIENT: ClientInfo;
+}
+
+/// Records that can be compared before and after a change. Only fields worth
+/// showing in the audit log are listed, secrets and hashes never are.
+pub trait Audited {
+    fn audit_fields(&self) -> Json;
+}
+
+fn format_date(date: Option<chrono::NaiveDateTime>) -> Option<String> {
+    date.map(|date| date.format("%Y-%m-%d %H:%M:%S").to_string())
+}
+
+impl Audited for users::Model {
+    fn audit_fields(&self) -> Json {
+        json!({
+            "email": self.email,
+            "username": self.username,
+            "displayName": self.display_name,
+            "avatar": self.avatar,
+            "defaultTeamId": self.default_team_id,
+            "status": self.status.to_value(),
+            "platformRole": self.platform_role.to_value(),
+            "emailVerifiedAt": format_date(self.email_verified_at),
+            "suspendedReason": self.suspended_reason,
+            "suspendedUntil": format_date(self.suspended_until),
+        })
+    }
+}
+
+impl Audited for teams::Model {
+    fn audit_fields(&self) -> Json {
+        json!({
+            "teamName": self.team_name,
+            "teamNamespace": self.team_namespace,
+            "teamAvatar": self.team_avatar,
+            "description": self.description,
+            "requireMfa": self.require_mfa,
+            "allowJoinRequests": self.allow_join_requests,
+            "allowMultipleOwners": self.allow_multiple_owners,
+            "logRetentionDays": self.log_retention_days,
+        })
+    }
+}
+
+impl Audited for team_users::Model {
+    fn audit_fields(&self) -> Json {
+        json!({
+            "role": self.role.to_value(),
+            "customRoleId": self.custom_role_id,
+            "status": self.status.to_value(),
+        })
+    }
+}
+
+impl Audited for team_roles::Model {
+    fn audit_fields(&self) -> Json {
+        json!({
+            "name": self.name,
+            "description": self.description,
+            "permissions": self.permissions,
+        })
+    }
+}
+
+impl Audited for team_invitations::Model {
+    fn audit_fields(&self) -> Json {
+        json!({
+            "email": self.email,
+            "role": self.role.to_value(),
+            "status": self.status.to_value(),
+            "expiresAt": format_date(Some(self.expires_at)),
+        })
+    }
+}
+
+impl Audited for api_keys::Model {
+    fn audit_fields(&self) -> Json {
+        json!({
+            "name": self.name,
+            "prefix": self.prefix,
+            "teamId": self.team_id,
+            "scopes": self.scopes,
+            "expiresAt": format_date(Some(self.expires_at)),
+        })
+    }
+}
+
+/// Filters of a log listing, every one is optional.
+#[derive(Debug, Default, Deserialize)]
+pub struct ActivityLogQuery {
+    /// `nextCursor` of the previous page.
+    pub cursor: Option<String>,
+    pub limit: Option<u64>,
+    /// Entries done by this user, or by an admin impersonating someone.
+    #[serde(rename = "actorId")]
+    pub actor_id: Option<Uuid>,
+    pub action: Option<Actions>,
+    #[serde(rename = "targetType")]
+    pub target_type: Option<TargetTypes>,
+    /// First day to include, as `YYYY-MM-DD` in UTC.
+    pub from: Option<String>,
+    /// Last day to include, as `YYYY-MM-DD` in UTC.
+    pub to: Option<String>,
+}
+
+/// A log entry with the user and target ids resolved to names.
+#[derive(Debug, Serialize)]
+pub struct ActivityLogEntry {
+    #[serde(rename = "logId")]
+    pub log_id: Uuid,
+    pub action: Actions,
+    #[serde(rename = "targetType")]
+    pub target_type: TargetTypes,
+    #[serde(rename = "targetId")]
+    pub target_id: Uuid,
+    /// `None` when the target is gone or has no name.
+    #[serde(rename = "targetLabel")]
+    pub target_label: Option<String>,
+    #[serde(rename = "userId")]
+    pub user_id: Uuid,
+    #[serde(rename = "userName")]
+    pub user_name: Option<String>,
+    /// Admin who acted as `user_id` while impersonating them.
+    #[serde(rename = "actorId")]
+    pub actor_id: Option<Uuid>,
+    #[serde(rename = "actorName")]
+    pub actor_name: Option<String>,
+    #[serde(rename = "teamId")]
+    pub team_id: Option<Uuid>,
+    pub description: String,
+    pub changes: Option<Json>,
+    pub ip: Option<String>,
+    #[serde(rename = "userAgent")]
+    pub user_agent: Option<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    /// One JSON object per line.
+    Jsonl,
+}
+
+impl ExportFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Jsonl => "application/x-ndjson",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Jsonl => "jsonl",
+        }
+    }
+
+    fn header(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "createdAt,action,targetType,targetId,targetLabel,userId,userName,actorId,actorName,description,changes,ip,userAgent\n",
+            ExportFormat::Jsonl => "",
+        }
+    }
+
+    fn line(&self, entry: &ActivityLogEntry) -> DbResult<String> {
+        match self {
+            ExportFormat::Csv => {
+                let fields = [
+                    entry.created_at.clone(),
+                    entry.action.to_value(),
+                    entry.target_type.to_value(),
+                    entry.target_id.to_string(),
+                    entry.target_label.clone().unwrap_or_default(),
+                    entry.user_id.to_string(),
+                    entry.user_name.clone().unwrap_or_default(),
+                    entry.actor_id.map(|id| id.to_string()).unwrap_or_default(),
+                    entry.actor_name.clone().unwrap_or_default(),
+                    entry.description.clone(),
+                    entry
+                        .changes
+                        .as_ref()
+                        .map(Json::to_string)
+                        .unwrap_or_default(),
+                    entry.ip.clone().unwrap_or_default(),
+                    entry.user_agent.clone().unwrap_or_default(),
+                ];
+                let fields = fields
+                    .iter()
+                    .map(|field| csv_field(field))
+                    .collect::<Vec<_>>();
+                Ok(format!("{}\n", fields.join(",")))
+            }
+            ExportFormat::Jsonl => serde_json::to_string(entry)
+                .map(|line| format!("{}\n", line))
+                .map_err(|e| DbErr::Custom(format!("Serialize log entry error: {}", e))),
+        }
+    }
+}
+
+/// Quote a CSV field when needed. Fields a spreadsheet would run as a
+/// formula are prefixed with `'`.
+fn csv_field(field: &str) -> String {
+    let field = if field.starts_with(['=', '+', '-', '@']) {
+        format!("'{}", field)
+    } else {
+        field.to_string()
+    };
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ActivityLogExportQuery {
+    #[serde(default)]
+    pub format: ExportFormat,
+    /// First day to include, as `YYYY-MM-DD` in UTC.
+    pub from: Option<String>,
+    /// Last day to include, as `YYYY-MM-DD` in UTC.
+    pub to: Option<String>,
+}
+
+pub struct ActivityLogService {
+    pub db: Arc<DatabaseConnection>,
+}
+
+impl ActivityLogService {
+    pub fn new(db: Arc<DatabaseConnection>) -> Self {
+        Self { db }
+    }
+
+    /// Run `future`, a request handler, with `client` stored as the origin of
+    /// everything it records.
+    pub async fn scope<F: Future>(client: ClientInfo, future: F) -> F::Output {
+        CLIENT.scope(client, future).await
+    }
+
+    /// Fields that differ between two versions of a record, as
+    /// `{"field": {"before": .., "after": ..}}`. A missing side stands for a
+    /// record being created or deleted. `None` when nothing changed.
+    pub fn changes<T: Audited>(before: Option<&T>, after: Option<&T>) -> Option<Json> {
+        let fields = |model: Option<&T>| match model.map(T::audit_fields) {
+            Some(Json::Object(fields)) => fields,
+            _ => Map::new(),
+        };
+        let (before, after) = (fields(before), fields(after));
+
+        let mut changes = Map::new();
+        for key in before.keys().chain(after.keys()) {
+            let old = before.get(key).unwrap_or(&Json::Null);
+            let new = after.get(key).unwrap_or(&Json::Null);
+            if old != new && !changes.contains_key(key) {
+                changes.insert(key.clone(), json!({ "before": old, "after": new }));
+            }
+        }
+
+        (!changes.is_empty()).then_some(Json::Object(changes))
+    }
+
+    pub async fn record(
+        &self,
+        user_id: Uuid,
+        action_type: Actions,
+        target_type: TargetTypes,
+        target_id: Uuid,
+        description: &str,
+    ) -> DbResult<()> {
+        self.record_changes(
+            user_id,
+            action_type,
+            target_type,
+            target_id,
+            description,
+            None,
+        )
+        .await
+    }
+
+    /// Record an action along with what it changed, see [`Self::changes`].
+    pub async fn record_changes(
+        &self,
+        user_id: Uuid,
+        action_type: Actions,
+        target_type: TargetTypes,
+        target_id: Uuid,
+        description: &str,
+        changes: Option<Json>,
+    ) -> DbResult<()> {
+        let team_id = Self::team_of(&target_type, target_id);
+        self.record_in_team(
+            team_id,
+            user_id,
+            action_type,
+            target_type,
+            target_id,
+            description,
+            changes,
+        )
+        .await
+    }
+
+    /// Like [`Self::record_changes`], for targets that belong to a team
+    /// without being the team, such as team API keys.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn record_in_team(
+        &self,
+        team_id: Option<Uuid>,
+        user_id: Uuid,
+        action_type: Actions,
+        target_type: TargetTypes,
+        target_id: Uuid,
+        description: &str,
+        changes: Option<Json>,
+    ) -> DbResult<()> {
+        self.insert(
+            self.db.as_ref(),
+            activity_log::ActiveModel {
+                team_id: Set(team_id),
+                user_id: Set(user_id),
+                action_type: Set(action_type),
+                description: Set(description.to_string()),
+                target_id: Set(target_id),
+                target_type: Set(target_type),
+                changes: Set(changes),
+                ..Default::default()
+            },
+        )
+        .await
+    }
+
+    /// Like [`Self::record`], as part of a transaction the caller runs.
+    pub async fn record_with<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        user_id: Uuid,
+        action_type: Actions,
+        target_type: TargetTypes,
+        target_id: Uuid,
+        description: &str,
+    ) -> DbResult<()> {
+        self.insert(
+            db,
+            activity_log::ActiveModel {
+                team_id: Set(Self::team_of(&target_type, target_id)),
+                user_id: Set(user_id),
+                action_type: Set(action_type),
+                description: Set(description.to_string()),
+                target_id: Set(target_id),
+                target_type: Set(target_type),
+                ..Default::default()
+            },
+        )
+        .await
+    }
+
+    /// Record something `actor_id` did while impersonating `user_id`.
+    pub async fn record_impersonated(
+        &self,
+        actor_id: Uuid,
+        user_id: Uuid,
+        action_type: Actions,
+        target_type: TargetTypes,
+        target_id: Uuid,
+        description: &str,
+    ) -> DbResult<()> {
+        self.insert(
+            self.db.as_ref(),
+            activity_log::ActiveModel {
+                team_id: Set(Self::team_of(&target_type, target_id)),
+                actor_id: Set(Some(actor_id)),
+                user_id: Set(user_id),
+                action_type: Set(action_type),
+                description: Set(description.to_string()),
+                target_id: Set(target_id),
+                target_type: Set(target_type),
+                ..Default::default()
+            },
+        )
+        .await
+    }
+
+    /// Adds the address and user agent of the current request, if any.
+    async fn insert<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        log: activity_log::ActiveModel,
+    ) -> DbResult<()> {
+        let client = CLIENT.try_with(ClientInfo::clone).unwrap_or_default();
+
+        activity_log::ActiveModel {
+            ip: Set(client.ip),
+            user_agent: Set(client.user_agent),
+            ..log
+        }
+        .insert(db)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Team and role entries point at their team.
+    fn team_of(target_type: &TargetTypes, target_id: Uuid) -> Option<Uuid> {
+        match target_type {
+            TargetTypes::Team | TargetTypes::Role => Some(target_id),
+            _ => None,
+        }
+    }
+
+    /// Entries of one team, newest first.
+    pub async fn find_by_team(
+        &self,
+        team_id: Uuid,
+        query: ActivityLogQuery,
+    ) -> DbResult<CursorPage<ActivityLogEntry>> {
+        self.find(Some(team_id), query).await
+    }
+
+    /// Entries of the whole platform, newest first.
+    pub async fn find_all(
+        &self,
+        query: ActivityLogQuery,
+    ) -> DbResult<CursorPage<ActivityLogEntry>> {
+        self.find(None, query).await
+    }
+
+    async fn find(
+        &self,
+        team_id: Option<Uuid>,
+        query: ActivityLogQuery,
+    ) -> DbResult<CursorPage<ActivityLogEntry>> {
+        let limit = query
+            .limit
+            .unwrap_or(DEFAULT_PAGE_SIZE)
+            .clamp(1, MAX_PAGE_SIZE);
+
+        let mut select = ActivityLog::find();
+        if let Some(team_id) = team_id {
+            select = select.filter(activity_log::Column::TeamId.eq(team_id));
+        }
+        if let Some(actor_id) = query.actor_id {
+            select = select.filter(
+                Condition::any()
+                    .add(activity_log::Column::UserId.eq(actor_id))
+                    .add(activity_log::Column::ActorId.eq(actor_id)),
+            );
+        }
+        if let Some(action) = query.action {
+            select = select.filter(activity_log::Column::ActionType.eq(action));
+        }
+        if let Some(target_type) = query.target_type {
+            select = select.filter(activity_log::Column::TargetType.eq(target_type));
+        }
+        if let Some(from) = query.from {
+            let from = parse_day(&from)?;
+            select = select.filter(activity_log::Column::CreatedAt.gte(from));
+        }
+        if let Some(to) = query.to {
+            let to = parse_day(&to)? + Duration::from_secs(60 * 60 * 24);
+            select = select.filter(activity_log::Column::CreatedAt.lt(to));
+        }
+        if let Some(cursor) = query.cursor {
+            let (created_at, log_id) = decode_cursor(&cursor)?;
+            select = select.filter(
+                Condition::any()
+                    .add(activity_log::Column::CreatedAt.lt(created_at))
+                    .add(
+                        Condition::all()
+                            .add(activity_log::Column::CreatedAt.eq(created_at))
+                            .add(activity_log::Column::LogId.lt(log_id)),
+                    ),
+            );
+        }
+
+        let mut logs = select
+            .order_by_desc(activity_log::Column::CreatedAt)
+            .order_by_desc(activity_log::Column::LogId)
+            .limit(limit + 1)
+            .all(self.db.as_ref())
+            .await?;
+
+        let next_cursor = if logs.len() as u64 > limit {
+            logs.truncate(limit as usize);
+            logs.last()
+                .map(|log| encode_cursor(log.created_at, log.log_id))
+        } else {
+            None
+        };
+
+        Ok(CursorPage {
+            items: self.resolve(logs).await?,
+            next_cursor,
+        })
+    }
+
+    /// Every entry of the team in the range, written out page by page so
+    /// the whole log never sits in memory.
+    pub fn export(
+        self: Arc<Self>,
+        team_id: Uuid,
+        query: ActivityLogExportQuery,
+    ) -> DbResult<impl Stream<Item = DbResult<String>>> {
+        // Checked up front, a failing page can only cut the response short.
+        for day in [&query.from, &query.to].into_iter().flatten() {
+            parse_day(day)?;
+        }
+
+        let format = query.format;
+        let pages = stream::try_unfold(Some(None), move |cursor: Option<Option<String>>| {
+            let service = self.clone();
+            let query = ActivityLogQuery {
+                cursor: cursor.clone().flatten(),
+                limit: Some(MAX_PAGE_SIZE),
+                from: query.from.clone(),
+                to: query.to.clone(),
+                ..Default::default()
+            };
+            async move {
+                let Some(cursor) = cursor else {
+                    return Ok(None);
+                };
+
+                let page = service.find(Some(team_id), query).await?;
+                let mut chunk = match cursor {
+                    None => format.header().to_string(),
+                    Some(_) => String::new(),
+                };
+                for entry in &page.items {
+                    chunk.push_str(&format.line(entry)?);
+                }
+                Ok(Some((chunk, page.next_cursor.map(Some))))
+            }
+        });
+        Ok(pages)
+    }
+
+    /// Take entries past their team's retention, or the platform retention for
+    /// entries outside of any team, out of the log. They are archived first
+    /// unless configured to only delete. Returns how many left.
+    pub async fn purge_expired(&self, config: &AuditConfig) -> DbResult<u64> {
+        let teams = Teams::find()
+            .filter(teams::Column::LogRetentionDays.is_not_null())
+            .all(self.db.as_ref())
+            .await?;
+
+        let cutoff = |days: u64| {
+            chrono::Utc::now().naive_utc() - Duration::from_secs(days.max(1) * 60 * 60 * 24)
+        };
+
+        let mut purged = 0;
+        for team in teams {
+            let Some(days) = team.log_retention_days else {
+                continue;
+            };
+            purged += self
+                .purge_before(Some(team.team_id), cutoff(days as u64), config)
+                .await?;
+        }
+        if let Some(days) = config.platform_retention_days {
+            purged += self
+                .purge_before(None, cutoff(u64::from(days)), config)
+                .await?;
+        }
+        Ok(purged)
+    }
+
+    /// Purge the entries of one team, or those outside of any team for `None`.
+    async fn purge_before(
+        &self,
+        team_id: Option<Uuid>,
+        cutoff: chrono::NaiveDateTime,
+        config: &AuditConfig,
+    ) -> DbResult<u64> {
+        let (scope, archive_dir) = match team_id {
+            Some(team_id) => (
+                activity_log::Column::TeamId.eq(team_id),
+                team_id.to_string(),
+            ),
+            None => (
+                activity_log::Column::TeamId.is_null(),
+                "platform".to_string(),
+            ),
+        };
+
+        let mut purged = 0;
+        loop {
+            let logs = ActivityLog::find()
+                .filter(scope.clone())
+                .filter(activity_log::Column::CreatedAt.lt(cutoff))
+                .order_by_asc(activity_log::Column::CreatedAt)
+                .order_by_asc(activity_log::Column::LogId)
+                .limit(PURGE_BATCH_SIZE)
+                .all(self.db.as_ref())
+                .await?;
+            let Some(first) = logs.first() else {
+                return Ok(purged);
+            };
+
+            let name = format!(
+                "{}-{}.jsonl.gz",
+                first.created_at.format("%Y%m%dT%H%M%S"),
+                first.log_id
+            );
+            let ids = logs.iter().map(|log| log.log_id).collect::<Vec<_>>();
+            if config.retention_action == RetentionAction::Archive {
+                let entries = self.resolve(logs).await?;
+                let dir = Path::new(&config.archive_dir).join(&archive_dir);
+                // File IO and compression would stall the async workers.
+                task::spawn_blocking(move || write_archive(&dir, &name, &entries))
+                    .await
+                    .map_err(|e| DbErr::Custom(format!("Write log archive error: {}", e)))??;
+            }
+
+            let res = ActivityLog::delete_many()
+                .filter(activity_log::Column::LogId.is_in(ids))
+                .exec(self.db.as_ref())
+                .await?;
+            purged += res.rows_affected;
+        }
+    }
+
+    /// Look up the names of everyone and everything the entries mention,
+    /// with one query per kind of record.
+    async fn resolve(&self, logs: Vec<activity_log::Model>) -> DbResult<Vec<ActivityLogEntry>> {
+        let ids_of = |target_types: &[TargetTypes]| {
+            logs.iter()
+                .filter(|log| target_types.contains(&log.target_type))
+                .map(|log| log.target_id)
+                .collect::<HashSet<_>>()
+        };
+
+        let mut user_ids = ids_of(&[TargetTypes::User]);
+        user_ids.extend(logs.iter().map(|log| log.user_id));
+        user_ids.extend(logs.iter().filter_map(|log| log.actor_id));
+        let users = Users::find()
+            .filter(users::Column::UserId.is_in(user_ids))
+            .all(self.db.as_ref())
+            .await?
+            .into_iter()
+            .map(|user| {
+                let name = user.display_name.or(user.username).unwrap_or(user.email);
+                (user.user_id, name)
+            })
+            .collect::<HashMap<_, _>>();
+
+        let teams = Teams::find()
+            .filter(teams::Column::TeamId.is_in(ids_of(&[TargetTypes::Team, TargetTypes::Role])))
+            .all(self.db.as_ref())
+            .await?
+            .into_iter()
+            .map(|team| (team.team_id, team.team_name))
+            .collect::<HashMap<_, _>>();
+
+        let keys = ApiKeys::find()
+            .filter(api_keys::Column::KeyId.is_in(ids_of(&[TargetTypes::ApiKey])))
+            .all(self.db.as_ref())
+            .await?
+            .into_iter()
+            .map(|key| (key.key_id, format!("{} ({})", key.name, key.prefix)))
+            .collect::<HashMap<_, _>>();
+
+        Ok(logs
+            .into_iter()
+            .map(|log| {
+                let labels = match log.target_type {
+                    TargetTypes::User => Some(&users),
+                    TargetTypes::Team | TargetTypes::Role => Some(&teams),
+                    TargetTypes::ApiKey => Some(&keys),
+                    TargetTypes::Campaign | TargetTypes::Invitation => None,
+                };
+                ActivityLogEntry {
+                    log_id: log.log_id,
+                    target_label: labels.and_then(|labels| labels.get(&log.target_id).cloned()),
+                    user_name: users.get(&log.user_id).cloned(),
+                    actor_name: log.actor_id.and_then(|id| users.get(&id).cloned()),
+                    action: log.action_type,
+                    target_type: log.target_type,
+                    target_id: log.target_id,
+                    user_id: log.user_id,
+                    actor_id: log.actor_id,
+                    team_id: log.team_id,
+                    description: log.description,
+                    changes: log.changes,
+                    ip: log.ip,
+                    user_agent: log.user_agent,
+                    created_at: log.created_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+                }
+            })
+            .collect())
+    }
+}
+
+fn write_archive(dir: &Path, name: &str, entries: &[ActivityLogEntry]) -> DbResult<()> {
+    let error = |e: std::io::Error| DbErr::Custom(format!("Write log archive error: {}", e));
+    fs::create_dir_all(dir).map_err(error)?;
+
+    let mut archive = GzEncoder::new(
+        File::create(dir.join(name)).map_err(error)?,
+        Compression::default(),
+    );
+    for entry in entries {
+        archive
+            .write_all(ExportFormat::Jsonl.line(entry)?.as_bytes())
+            .map_err(error)?;
+    }
+    archive.finish().map_err(error)?;
+    Ok(())
+}
+
+fn parse_day(day: &str) -> DbResult<chrono::NaiveDateTime> {
+    chrono::NaiveDate::parse_from_str(day, "%Y-%m-%d")
+        .map(|day| day.and_time(chrono::NaiveTime::MIN))
+        .map_err(|_| DbErr::Custom(format!("Invalid date `{}`, use YYYY-MM-DD", day)))
+}
+
+/// The cursor is the position of the last entry of a page, opaque to clients.
+fn encode_cursor(created_at: chrono::NaiveDateTime, log_id: Uuid) -> String {
+    URL_SAFE_NO_PAD.encode(format!(
+        "{}_{}",
+        created_at.and_utc().timestamp_micros(),
+        log_id
+    ))
+}
+
+fn decode_cursor(cursor: &str) -> DbResult<(chrono::NaiveDateTime, Uuid)> {
+    let invalid = || DbErr::Custom("Invalid cursor".to_string());
+    let cursor = URL_SAFE_NO_PAD
+        .decode(cursor)
+        .ok()
+        .and_then(|cursor| String::from_utf8(cursor).ok())
+        .ok_or_else(invalid)?;
+    let (micros, log_id) = cursor.split_once('_').ok_or_else(invalid)?;
+
+    let created_at = micros
+        .parse()
+        .ok()
+        .and_then(chrono::DateTime::from_timestamp_micros)
+        .ok_or_else(invalid)?
+        .naive_utc();
+    let log_id = Uuid::parse_str(log_id).map_err(|_| invalid())?;
+    Ok((created_at, log_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_field_quotes_only_when_needed() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+    }
+
+    #[test]
+    fn csv_field_defuses_formulas() {
+        assert_eq!(csv_field("=SUM(A1:A2)"), "'=SUM(A1:A2)");
+        assert_eq!(csv_field("+1"), "'+1");
+        assert_eq!(csv_field("-1"), "'-1");
+        assert_eq!(csv_field("@cmd"), "'@cmd");
+        assert_eq!(csv_field("=1,2"), "\"'=1,2\"");
+    }
+
+    #[test]
+    fn cursor_round_trips() {
+        let created_at = chrono::DateTime::from_timestamp_micros(1_734_000_000_123_456)
+            .unwrap()
+            .naive_utc();
+        let log_id = Uuid::from_u128(0x6f1c_2a3b_4c5d_4e6f_8a9b_0c1d_2e3f_4a5b);
+
+        let cursor = encode_cursor(created_at, log_id);
+        assert_eq!(decode_cursor(&cursor).unwrap(), (created_at, log_id));
+    }
+
+    #[test]
+    fn cursor_rejects_garbage() {
+        assert!(decode_cursor("").is_err());
+        assert!(decode_cursor("not base64!").is_err());
+        assert!(decode_cursor(&URL_SAFE_NO_PAD.encode("123")).is_err());
+        assert!(
+            decode_cursor(&URL_SAFE_NO_PAD.encode("abc_00000000-0000-0000-0000-000000000000"))
+                .is_err()
+        );
+        assert!(decode_cursor(&URL_SAFE_NO_PAD.encode("123_not-a-uuid")).is_err());
+    }
+}
@@ -0,0 +1,85 @@
+use std::{sync::Arc, time::Duration};
+
+use crate::_entities::prelude::{Sessions, Teams, Users};
+use crate::_entities::sea_orm_active_enums::{PlatformRoles, Status};
+use crate::_entities::{sessions, users};
+use crate::utils::DbResult;
+use sea_orm::sqlx::types::chrono;
+use sea_orm::*;
+use serde::Serialize;
+
+/// Window the new account count covers.
+const RECENT_WINDOW: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+#[derive(Debug, Serialize)]
+pub struct PlatformStats {
+    pub users: UserStats,
+    pub teams: u64,
+    #[serde(rename = "activeSessions")]
+    pub active_sessions: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UserStats {
+    pub total: u64,
+    pub active: u64,
+    pub inactive: u64,
+    pub suspended: u64,
+    pub unverified: u64,
+    pub admins: u64,
+    #[serde(rename = "newLast7Days")]
+    pub new_last_7_days: u64,
+}
+
+/// Platform-wide figures for admins.
+pub struct AdminService {
+    pub db: Arc<DatabaseConnection>,
+}
+
+impl AdminService {
+    pub fn new(db: Arc<DatabaseConnection>) -> Self {
+        Self { db }
+    }
+
+    pub async fn platform_stats(&self) -> DbResult<PlatformStats> {
+        let db = self.db.as_ref();
+        let now = chrono::Utc::now().naive_utc();
+        let count_status = |status: Status| {
+            Users::find()
+                .filter(users::Column::Status.eq(status))
+                .count(db)
+        };
+
+        let users = UserStats {
+            total: Users::find().count(db).await?,
+            active: count_status(Status::Active).await?,
+            inactive: count_status(Status::Inactive).await?,
+            suspended: count_status(Status::Suspended).await?,
+            unverified: Users::find()
+                .filter(users::Column::EmailVerifiedAt.is_null())
+                .count(db)
+                .await?,
+            admins: Users::find()
+                .filter(
+                    users::Column::PlatformRole
+                        .is_in([PlatformRoles::Admin, PlatformRoles::SuperAdmin]),
+                )
+                .count(db)
+                .await?,
+            new_last_7_days: Users::find()
+                .filter(users::Column::CreatedAt.gte(now - RECENT_WINDOW))
+                .count(db)
+                .await?,
+        };
+
+        Ok(PlatformStats {
+            users,
+            teams: Teams::find().count(db).await?,
+            active_sessions: Sessions::find()
+                .filter(sessions::Column::RevokedAt.is_null())
+                .filter(sessions::Column::ExpiresAt.gt(now))
+                .count(db)
+                .await?,
+        })
+    }
+}
@@ -0,0 +1,337 @@
+use std::{sync::Arc, time::Duration};
+
+use crate::_entities::api_keys;
+use crate::_entities::prelude::ApiKeys;
+use crate::_entities::sea_orm_active_enums::{Actions, TargetTypes};
+use crate::common::activity_log_service::ActivityLogService;
+use crate::utils::encrypt::{generator_random_token, hash_token};
+use crate::utils::DbResult;
+use sea_orm::prelude::Uuid;
+use sea_orm::sea_query::Expr;
+use sea_orm::sqlx::types::chrono;
+use sea_orm::*;
+use serde::{Deserialize, Serialize};
+
+/// Every API key starts with this, which is how they are told apart from JWTs.
+pub const API_KEY_PREFIX: &str = "mms_";
+pub const API_KEY_SCOPES: [&str; 4] = ["user:read", "user:write", "team:read", "team:write"];
+
+const DEFAULT_EXPIRE_DAYS: u32 = 90;
+const MAX_EXPIRE_DAYS: u32 = 365;
+/// `last_used_at` is only written when older than this, in seconds.
+const LAST_USED_INTERVAL: u64 = 60;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateApiKeyDto {
+    pub name: String,
+    pub scopes: Vec<String>,
+    /// Creates a team-owned key instead of a personal one.
+    #[serde(rename = "teamId")]
+    pub team_id: Option<Uuid>,
+    #[serde(rename = "expireDays")]
+    pub expire_days: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateApiKeyDto {
+    pub name: Option<String>,
+    pub scopes: Option<Vec<String>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ApiKeyInfo {
+    #[serde(rename = "keyId")]
+    pub key_id: Uuid,
+    pub name: String,
+    pub prefix: String,
+    #[serde(rename = "teamId")]
+    pub team_id: Option<Uuid>,
+    pub scopes: Vec<String>,
+    #[serde(rename = "expiresAt")]
+    pub expires_at: String,
+    #[serde(rename = "lastUsedAt")]
+    pub last_used_at: Option<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+}
+
+impl From<api_keys::Model> for ApiKeyInfo {
+    fn from(key: api_keys::Model) -> Self {
+        let format = |date: chrono::NaiveDateTime| date.format("%Y-%m-%d %H:%M:%S").to_string();
+        Self {
+            key_id: key.key_id,
+            name: key.name,
+            prefix: key.prefix,
+            team_id: key.team_id,
+            scopes: key.scopes.split_whitespace().map(String::from).collect(),
+            expires_at: format(key.expires_at),
+            last_used_at: key.last_used_at.map(format),
+            created_at: format(key.created_at),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct CreatedApiKey {
+    /// The full key, only ever returned here.
+    pub key: String,
+    #[serde(flatten)]
+    pub info: ApiKeyInfo,
+}
+
+/// Caller authenticated by an API key instead of a JWT.
+#[derive(Debug, Clone)]
+pub struct ApiKeyPrincipal {
+    pub key_id: Uuid,
+    pub user_id: Uuid,
+    pub team_id: Option<Uuid>,
+    pub scopes: Vec<String>,
+}
+
+impl ApiKeyPrincipal {
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes.iter().any(|s| s == scope)
+    }
+}
+
+pub struct ApiKeyService {
+    pub db: Arc<DatabaseConnection>,
+    pub activity_log_service: Arc<ActivityLogService>,
+}
+
+impl ApiKeyService {
+    pub fn new(db: Arc<DatabaseConnection>, activity_log_service: Arc<ActivityLogService>) -> Self {
+        Self {
+            db,
+            activity_log_service,
+        }
+    }
+
+    pub fn is_api_key(token: &str) -> bool {
+        token.starts_with(API_KEY_PREFIX)
+    }
+
+    pub async fn create_key(
+        &self,
+        user_id: Uuid,
+        payload: CreateApiKeyDto,
+    ) -> DbResult<CreatedApiKey> {
+        let name = Self::check_name(&payload.name)?;
+        let scopes = Self::check_scopes(&payload.scopes, payload.team_id.is_some())?;
+        let expire_days = payload.expire_days.unwrap_or(DEFAULT_EXPIRE_DAYS);
+        if !(1..=MAX_EXPIRE_DAYS).contains(&expire_days) {
+            return Err(DbErr::Custom(format!(
+                "API keys expire within 1 to {} days",
+                MAX_EXPIRE_DAYS
+            )));
+        }
+
+        // `pat` for personal access tokens, `tak` for team API keys.
+        let kind = if payload.team_id.is_some() {
+            "tak"
+        } else {
+            "pat"
+        };
+        let prefix = format!("{}{}_{}", API_KEY_PREFIX, kind, generator_random_token(4));
+        let key = format!("{}_{}", prefix, generator_random_token(32));
+
+        let model = api_keys::ActiveModel {
+            name: Set(name),
+            prefix: Set(prefix),
+            key_hash: Set(hash_token(&key)),
+            user_id: Set(user_id),
+            team_id: Set(payload.team_id),
+            scopes: Set(scopes),
+            expires_at: Set(chrono::Utc::now().naive_utc()
+                + Duration::from_secs(expire_days as u64 * 60 * 60 * 24)),
+            ..Default::default()
+        }
+        .insert(self.db.as_ref())
+        .await?;
+
+        self.activity_log_service
+            .record_in_team(
+                model.team_id,
+                user_id,
+                Actions::Created,
+                TargetTypes::ApiKey,
+                model.key_id,
+                &format!("Created API key `{}` ({})", model.name, model.prefix),
+                ActivityLogService::changes(None, Some(&model)),
+            )
+            .await?;
+
+        Ok(CreatedApiKey {
+            key,
+            info: model.into(),
+        })
+    }
+
+    pub async fn find_key(&self, key_id: Uuid) -> DbResult<api_keys::Model> {
+        ApiKeys::find_by_id(key_id)
+            .one(self.db.as_ref())
+            .await?
+            .ok_or(DbErr::Custom("API key not found".to_string()))
+    }
+
+    pub async fn find_keys_by_user(&self, user_id: Uuid) -> DbResult<Vec<ApiKeyInfo>> {
+        let keys = ApiKeys::find()
+            .filter(api_keys::Column::UserId.eq(user_id))
+            .filter(api_keys::Column::TeamId.is_null())
+            .order_by_desc(api_keys::Column::CreatedAt)
+            .all(self.db.as_ref())
+            .await?;
+
+        Ok(keys.into_iter().map(ApiKeyInfo::from).collect())
+    }
+
+    pub async fn find_keys_by_team(&self, team_id: Uuid) -> DbResult<Vec<ApiKeyInfo>> {
+        let keys = ApiKeys::find()
+            .filter(api_keys::Column::TeamId.eq(team_id))
+            .order_by_desc(api_keys::Column::CreatedAt)
+            .all(self.db.as_ref())
+            .await?;
+
+        Ok(keys.into_iter().map(ApiKeyInfo::from).collect())
+    }
+
+    pub async fn update_key(
+        &self,
+        user_id: Uuid,
+        key_id: Uuid,
+        payload: UpdateApiKeyDto,
+    ) -> DbResult<ApiKeyInfo> {
+        let before = self.find_key(key_id).await?;
+        let is_team_key = before.team_id.is_some();
+        let mut model: api_keys::ActiveModel = before.clone().into();
+
+        if let Some(name) = payload.name {
+            model.name = Set(Self::check_name(&name)?);
+        }
+        if let Some(scopes) = payload.scopes {
+            model.scopes = Set(Self::check_scopes(&scopes, is_team_key)?);
+        }
+        model.updated_at = Set(chrono::Utc::now().naive_utc());
+
+        let key = model.update(self.db.as_ref()).await?;
+
+        self.activity_log_service
+            .record_in_team(
+                key.team_id,
+                user_id,
+                Actions::Updated,
+                TargetTypes::ApiKey,
+                key.key_id,
+                &format!("Updated API key `{}` ({})", key.name, key.prefix),
+                ActivityLogService::changes(Some(&before), Some(&key)),
+            )
+            .await?;
+
+        Ok(key.into())
+    }
+
+    pub async fn delete_key(&self, user_id: Uuid, key_id: Uuid) -> DbResult<()> {
+        let key = self.find_key(key_id).await?;
+        ApiKeys::delete_by_id(key_id).exec(self.db.as_ref()).await?;
+
+        self.activity_log_service
+            .record_in_team(
+                key.team_id,
+                user_id,
+                Actions::Removed,
+                TargetTypes::ApiKey,
+                key.key_id,
+                &format!("Removed API key `{}` ({})", key.name, key.prefix),
+                ActivityLogService::changes(Some(&key), None),
+            )
+            .await
+    }
+
+    /// Drop every key the user owns or created, used when the account goes away.
+    pub async fn delete_keys_by_user<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        user_id: Uuid,
+    ) -> DbResult<u64> {
+        let res = ApiKeys::delete_many()
+            .filter(api_keys::Column::UserId.eq(user_id))
+            .exec(db)
+            .await?;
+
+        Ok(res.rows_affected)
+    }
+
+    /// Resolve a presented key, refusing unknown and expired ones.
+    pub async fn authenticate(&self, key: &str) -> DbResult<ApiKeyPrincipal> {
+        let invalid = || DbErr::Custom("Invalid API key".to_string());
+        let (prefix, _) = key.rsplit_once('_').ok_or_else(invalid)?;
+
+        let model = ApiKeys::find()
+            .filter(api_keys::Column::Prefix.eq(prefix))
+            .one(self.db.as_ref())
+            .await?
+            .filter(|model| model.key_hash == hash_token(key))
+            .ok_or_else(invalid)?;
+
+        let now = chrono::Utc::now().naive_utc();
+        if model.expires_at <= now {
+            return Err(DbErr::Custom("API key has expired".to_string()));
+        }
+
+        // Only write when the stored value is stale so busy keys don't turn
+        // every request into an update.
+        ApiKeys::update_many()
+            .col_expr(api_keys::Column::LastUsedAt, Expr::value(now))
+            .filter(api_keys::Column::KeyId.eq(model.key_id))
+            .filter(
+                Condition::any()
+                    .add(api_keys::Column::LastUsedAt.is_null())
+                    .add(
+                        api_keys::Column::LastUsedAt
+                            .lt(now - Duration::from_secs(LAST_USED_INTERVAL)),
+                    ),
+            )
+            .exec(self.db.as_ref())
+            .await?;
+
+        Ok(ApiKeyPrincipal {
+            key_id: model.key_id,
+            user_id: model.user_id,
+            team_id: model.team_id,
+            scopes: model.scopes.split_whitespace().map(String::from).collect(),
+        })
+    }
+
+    fn check_name(name: &str) -> DbResult<String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(DbErr::Custom("API key name can not be empty".to_string()));
+        }
+        Ok(name.to_string())
+    }
+
+    fn check_scopes(scopes: &[String], team_key: bool) -> DbResult<String> {
+        if scopes.is_empty() {
+            return Err(DbErr::Custom(
+                "API key needs at least one scope".to_string(),
+            ));
+        }
+
+        for scope in scopes {
+            if !API_KEY_SCOPES.contains(&scope.as_str()) {
+                return Err(DbErr::Custom(format!("Unknown scope `{}`", scope)));
+            }
+            if team_key && !scope.starts_with("team:") {
+                return Err(DbErr::Custom(format!(
+                    "Team API keys can not have the `{}` scope",
+                    scope
+                )));
+            }
+        }
+
+        let mut scopes = scopes.to_vec();
+        scopes.sort();
+        scopes.dedup();
+        Ok(scopes.join(" "))
+    }
+}
@@ -1,155 +1,1269 @@
-use crate::utils::{
-    jwt::{Jwt, TokenPayload},
-    DbResult,
+use crate::{
+    _entities::{
+        sea_orm_active_enums::{
+            Actions, CodePurposes, NotificationTypes, PlatformRoles, Status, TargetTypes,
+        },
+        users,
+    },
+    config::{AuthConfig, UnverifiedAccess},
+    mailer::{MailMessage, Mailer},
+    store::RevocationStore,
+    utils::{
+        jwt::{get_current_timestamp, Jwt, TokenInfo, TokenPayload},
+        DbResult,
+    },
 };
 
-use super::user_service::{CreateUserDto, PartialUser, UpdateUserDto, UserService};
+use super::{
+    activity_log_service::ActivityLogService,
+    api_key_service::ApiKeyService,
+    login_attempt_service::{LoginAttemptService, LOCK_DURATION, LOGIN_FAILED},
+    mfa_service::{MfaChallenge, MfaService},
+    notification_service::NotificationService,
+    oidc_service::{OidcCallbackPayload, OidcService, VerifiedIdentity},
+    session_service::{ClientInfo, IssuedSession, SessionInfo, SessionService},
+    team_invitation_service::TeamInvitationService,
+    team_user_service::TeamUserService,
+    user_service::{CreateUserDto, PartialUser, UpdateUserDto, UserService},
+    verification_code_service::VerificationCodeService,
+};
 use sea_orm::*;
 use serde::{Deserialize, Serialize};
 use sqlx::types::Uuid;
 use std::{env::var, sync::Arc};
+use url::Url;
+
+/// Impersonation tokens last 30 minutes unless configured otherwise.
+const DEFAULT_IMPERSONATION_EXPIRE: i64 = 1000 * 60 * 30;
+const REFUSED_WHILE_IMPERSONATING: &str = "This action is not allowed while impersonating a user";
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct LoginPayload {
     pub email: String,
     pub password: String,
+    pub remember: bool,
     pub expire: Option<i64>,
+    /// Failed attempts are also counted per client address.
+    pub client: ClientInfo,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct AuthResponse {
     pub token: String,
     pub expire: i64,
+    #[serde(rename = "refreshToken")]
+    pub refresh_token: String,
+    #[serde(rename = "refreshExpire")]
+    pub refresh_expire: i64,
+}
+
+/// Login either finishes right away or waits for the second factor.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum LoginResponse {
+    Authenticated(AuthResponse),
+    MfaRequired(MfaChallenge),
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct LoginMfaPayload {
+    #[serde(rename = "mfaToken")]
+    pub mfa_token: String,
+    /// TOTP code or one of the recovery codes.
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ChangePasswordPayload {
+    #[serde(rename = "currentPassword")]
+    pub current_password: String,
+    #[serde(rename = "newPassword")]
+    pub new_password: String,
+}
+
+/// Re-entering the password confirms destructive account changes.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ConfirmPasswordPayload {
+    pub password: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ImpersonatePayload {
+    /// Support ticket or other justification, kept in the audit log.
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ImpersonationResponse {
+    pub token: String,
+    pub expire: i64,
+    #[serde(rename = "userId")]
+    pub user_id: Uuid,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SuspendUserPayload {
+    pub reason: Option<String>,
+    /// `YYYY-MM-DD HH:MM:SS` in UTC, suspended until reactivated when missing.
+    pub until: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct ForgetPasswordPayload {
-    email: String,
-    password: String,
+    pub email: String,
+    pub code: String,
+    pub password: String,
 }
 
 pub struct AuthService {
     pub db: Arc<DatabaseConnection>,
+    pub config: AuthConfig,
+    pub jwt: Arc<Jwt>,
+    pub activity_log_service: Arc<ActivityLogService>,
+    pub api_key_service: Arc<ApiKeyService>,
+    pub login_attempt_service: Arc<LoginAttemptService>,
     pub user_service: Arc<UserService>,
+    pub session_service: Arc<SessionService>,
+    pub team_user_service: Arc<TeamUserService>,
+    pub team_invitation_service: Arc<TeamInvitationService>,
+    pub mfa_service: Arc<MfaService>,
+    pub notification_service: Arc<NotificationService>,
+    pub oidc_service: Arc<OidcService>,
+    pub verification_code_service: Arc<VerificationCodeService>,
+    pub revocation_store: Arc<dyn RevocationStore>,
+    pub mailer: Arc<dyn Mailer>,
 }
 
 impl AuthService {
-    pub fn new(db: Arc<DatabaseConnection>, user_service: Arc<UserService>) -> Self {
-        Self { db, user_service }
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        db: Arc<DatabaseConnection>,
+        config: AuthConfig,
+        jwt: Arc<Jwt>,
+        activity_log_service: Arc<ActivityLogService>,
+        api_key_service: Arc<ApiKeyService>,
+        login_attempt_service: Arc<LoginAttemptService>,
+        user_service: Arc<UserService>,
+        session_service: Arc<SessionService>,
+        team_user_service: Arc<TeamUserService>,
+        team_invitation_service: Arc<TeamInvitationService>,
+        mfa_service: Arc<MfaService>,
+        notification_service: Arc<NotificationService>,
+        oidc_service: Arc<OidcService>,
+        verification_code_service: Arc<VerificationCodeService>,
+        revocation_store: Arc<dyn RevocationStore>,
+        mailer: Arc<dyn Mailer>,
+    ) -> Self {
+        Self {
+            db,
+            config,
+            jwt,
+            activity_log_service,
+            api_key_service,
+            login_attempt_service,
+            user_service,
+            session_service,
+            team_user_service,
+            team_invitation_service,
+            mfa_service,
+            notification_service,
+            oidc_service,
+            verification_code_service,
+            revocation_store,
+            mailer,
+        }
+    }
+
+    fn impersonation_expire(&self) -> i64 {
+        self.config
+            .impersonation_expire
+            .unwrap_or(DEFAULT_IMPERSONATION_EXPIRE)
+    }
+
+    fn access_expire() -> i64 {
+        var("JWT_EXIPRE")
+            .ok()
+            .and_then(|v| v.parse::<i64>().ok())
+            .unwrap_or(1000 * 60 * 10)
+    }
+
+    /// Limited tokens only reach the routes needed to finish setting up the
+    /// account: verifying the email or enrolling a second factor a team requires.
+    async fn is_limited(&self, user_id: Uuid, email_verified: bool) -> DbResult<bool> {
+        if !email_verified && self.config.unverified_access == UnverifiedAccess::Limited {
+            return Ok(true);
+        }
+
+        Ok(self.mfa_service.is_required(user_id).await?
+            && !self.mfa_service.is_enabled(user_id).await?)
+    }
+
+    fn sign_session(
+        &self,
+        session: IssuedSession,
+        expire: i64,
+        limited: bool,
+    ) -> DbResult<AuthResponse> {
+        let token = self
+            .jwt
+            .sign(TokenPayload {
+                user_id: session.user_id,
+                session_id: Some(session.session_id),
+                limited,
+                actor_id: None,
+                expire,
+            })
+            .map_err(|e| DbErr::Custom(format!("Sign token error: {}", e)))?;
+
+        Ok(AuthResponse {
+            token,
+            expire,
+            refresh_token: session.refresh_token,
+            refresh_expire: session.refresh_expire,
+        })
+    }
+
+    pub async fn login(&self, payload: LoginPayload) -> Result<LoginResponse, DbErr> {
+        self.login_attempt_service
+            .check(&payload.email, payload.client.ip.as_deref())
+            .await?;
+
+        let user = match self
+            .user_service
+            .verify_credentials_by_email(&payload.email, &payload.password)
+            .await
+        {
+            Ok(user) => user,
+            Err(DbErr::Custom(_)) => {
+                self.record_failed_login(&payload.email, payload.client.ip.as_deref())
+                    .await?;
+                return Err(DbErr::Custom(LOGIN_FAILED.to_string()));
+            }
+            Err(e) => return Err(e),
+        };
+        self.login_attempt_service
+            .reset_account(&payload.email)
+            .await?;
+        // Only told once the password matched, so it does not reveal accounts.
+        self.user_service.ensure_active(user.user_id).await?;
+
+        let expire: i64 = payload.expire.unwrap_or_else(Self::access_expire);
+        self.complete_login(
+            user.user_id,
+            user.email_verified_at.is_some(),
+            payload.remember,
+            expire,
+            payload.client,
+        )
+        .await
+    }
+
+    async fn record_failed_login(&self, email: &str, ip: Option<&str>) -> DbResult<()> {
+        let lockout = self.login_attempt_service.record_failure(email, ip).await?;
+        if lockout.ip {
+            log::warn!("Logins from {:?} locked after repeated failures", ip);
+        }
+
+        let Some(user) = self.user_service.find_user_by_email(email).await? else {
+            if lockout.account || lockout.ip {
+                log::warn!("Logins for unknown email {} locked", email);
+            }
+            return Ok(());
+        };
+        self.activity_log_service
+            .record(
+                user.user_id,
+                Actions::LoginFailed,
+                TargetTypes::User,
+                user.user_id,
+                "Failed login attempt",
+            )
+            .await?;
+        if !lockout.account && !lockout.ip {
+            return Ok(());
+        }
+
+        let minutes = LOCK_DURATION.as_secs() / 60;
+
+        if lockout.account {
+            self.activity_log_service
+                .record(
+                    user.user_id,
+                    Actions::Locked,
+                    TargetTypes::User,
+                    user.user_id,
+                    &format!(
+                        "Login locked for {} minutes after repeated failed attempts",
+                        minutes
+                    ),
+                )
+                .await?;
+            if let Err(e) = self.send_unlock_email(user.user_id, &user.email).await {
+                log::error!("Send unlock email error: {:#?}", e);
+            }
+        }
+        if lockout.ip {
+            self.activity_log_service
+                .record(
+                    user.user_id,
+                    Actions::Locked,
+                    TargetTypes::User,
+                    user.user_id,
+                    &format!(
+                        "Logins from {} locked for {} minutes after repeated failed attempts",
+                        ip.unwrap_or("unknown address"),
+                        minutes
+                    ),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn send_unlock_email(&self, user_id: Uuid, email: &str) -> DbResult<()> {
+        let code = self
+            .verification_code_service
+            .issue_code(user_id, email, CodePurposes::AccountUnlock)
+            .await?;
+
+        let mut body = format!(
+            "We locked logins to your account after several failed password attempts.\n\nIf this was you, unlock it with the code {}.",
+            code
+        );
+        if let Some(unlock_link) = &self.config.unlock_link {
+            let link = Url::parse_with_params(unlock_link, &[("email", email), ("code", &code)])
+                .map_err(|e| DbErr::Custom(format!("Invalid unlock link: {}", e)))?;
+            body.push_str(&format!("\n\nOr open this link to unlock: {}", link));
+        }
+        body.push_str(&format!(
+            "\n\nOtherwise the lock is lifted after {} minutes. If this wasn't you, consider changing your password.",
+            LOCK_DURATION.as_secs() / 60
+        ));
+
+        self.mailer
+            .send(MailMessage {
+                to: email.to_string(),
+                subject: "Your account was locked".to_string(),
+                body,
+            })
+            .await
+    }
+
+    /// Lift a lockout with the code from the unlock mail.
+    pub async fn unlock_account(&self, email: &str, code: &str) -> DbResult<bool> {
+        let verified = self
+            .verification_code_service
+            .verify_code(email, CodePurposes::AccountUnlock, code, true)
+            .await?;
+
+        self.login_attempt_service
+            .reset_account(&verified.email)
+            .await?;
+        self.activity_log_service
+            .record(
+                verified.user_id,
+                Actions::Unlocked,
+                TargetTypes::User,
+                verified.user_id,
+                "Login unlocked from the unlock email",
+            )
+            .await?;
+
+        Ok(true)
+    }
+
+    /// Shared tail of every first login step: ask for the second factor when
+    /// enabled, otherwise open the session.
+    async fn complete_login(
+        &self,
+        user_id: Uuid,
+        email_verified: bool,
+        remember: bool,
+        expire: i64,
+        client: ClientInfo,
+    ) -> DbResult<LoginResponse> {
+        if self.mfa_service.is_enabled(user_id).await? {
+            let challenge = self.mfa_service.create_challenge(user_id, remember).await?;
+            return Ok(LoginResponse::MfaRequired(challenge));
+        }
+
+        let session = self.open_session(user_id, remember, client).await?;
+        let limited = self.is_limited(user_id, email_verified).await?;
+
+        Ok(LoginResponse::Authenticated(
+            self.sign_session(session, expire, limited)?,
+        ))
+    }
+
+    /// Create the session of a finished login, telling the user when it comes
+    /// from a device they never used before.
+    async fn open_session(
+        &self,
+        user_id: Uuid,
+        remember: bool,
+        client: ClientInfo,
+    ) -> DbResult<IssuedSession> {
+        let known = self
+            .session_service
+            .is_known_device(user_id, &client)
+            .await?;
+        let session = self
+            .session_service
+            .create_session(user_id, remember, client.clone())
+            .await?;
+        self.activity_log_service
+            .record(
+                user_id,
+                Actions::LoggedIn,
+                TargetTypes::User,
+                user_id,
+                "Logged in",
+            )
+            .await?;
+        if known {
+            return Ok(session);
+        }
+
+        let content = format!(
+            "Your account was just signed in from {} ({}). If this wasn't you, sign out that session and change your password.",
+            client.user_agent.as_deref().unwrap_or("an unknown device"),
+            client.ip.as_deref().unwrap_or("unknown address"),
+        );
+        self.notification_service
+            .notify(
+                user_id,
+                NotificationTypes::NewDevice,
+                "New device signed in",
+                &content,
+            )
+            .await?;
+
+        if let Some(user) = self.user_service.find_user_by_id(user_id).await? {
+            let mail = MailMessage {
+                to: user.email,
+                subject: "New sign-in to your account".to_string(),
+                body: content,
+            };
+            if let Err(e) = self.mailer.send(mail).await {
+                log::error!("Send new device email error: {:#?}", e);
+            }
+        }
+
+        Ok(session)
     }
 
-    pub async fn login(&self, payload: LoginPayload) -> Result<AuthResponse, DbErr> {
+    /// Login, or link a new identity, through an OpenID Connect provider.
+    pub async fn login_oidc(
+        &self,
+        payload: OidcCallbackPayload,
+        client: ClientInfo,
+    ) -> DbResult<LoginResponse> {
+        let identity = self.oidc_service.exchange(payload).await?;
+        let user_id = self.resolve_oidc_user(&identity).await?;
+        self.user_service.ensure_active(user_id).await?;
         let user = self
             .user_service
-            .verify_password_by_email(&payload.email, &payload.password)
+            .find_user_by_id(user_id)
+            .await?
+            .ok_or(DbErr::Custom("Cannot find user".to_string()))?;
+
+        self.complete_login(
+            user_id,
+            user.email_verified_at.is_some(),
+            identity.remember,
+            Self::access_expire(),
+            client,
+        )
+        .await
+    }
+
+    /// Find the account an external identity belongs to. Unknown identities are
+    /// linked to the user who started the flow, to the account with the same
+    /// verified email, or get a new account.
+    async fn resolve_oidc_user(&self, identity: &VerifiedIdentity) -> DbResult<Uuid> {
+        if let Some(linked) = self
+            .oidc_service
+            .find_identity(&identity.provider, &identity.subject)
+            .await?
+        {
+            if identity.link_user_id.is_some_and(|id| id != linked.user_id) {
+                return Err(DbErr::Custom(
+                    "This login is already linked to another account".to_string(),
+                ));
+            }
+            self.oidc_service
+                .touch_identity(linked.identity_id, identity.email.clone())
+                .await?;
+            return Ok(linked.user_id);
+        }
+
+        let user_id = match identity.link_user_id {
+            Some(user_id) => user_id,
+            None => {
+                let email = identity.email.as_deref().ok_or(DbErr::Custom(
+                    "The login provider did not share an email address".to_string(),
+                ))?;
+
+                match self.user_service.find_user_by_email(email).await? {
+                    Some(_) if !identity.email_verified => {
+                        return Err(DbErr::Custom(
+                            "Email already in use, log in and link this provider instead"
+                                .to_string(),
+                        ))
+                    }
+                    // Whoever registered an unverified account may not own the
+                    // address, linking would hand them the provider's login.
+                    Some(user) if user.email_verified_at.is_none() => {
+                        return Err(DbErr::Custom(
+                            "Email already in use by an unverified account, reset its password \
+                             and verify the email before logging in with this provider"
+                                .to_string(),
+                        ))
+                    }
+                    Some(user) => user.user_id,
+                    None => {
+                        let user = self
+                            .user_service
+                            .create_external_user(
+                                email,
+                                identity.name.clone(),
+                                identity.picture.clone(),
+                                identity.email_verified,
+                            )
+                            .await?;
+                        if !identity.email_verified {
+                            if let Err(e) = self.send_verify_email(user.user_id, email).await {
+                                log::error!("Send verify email error: {:#?}", e);
+                            }
+                        }
+                        user.user_id
+                    }
+                }
+            }
+        };
+
+        self.oidc_service.link_identity(user_id, identity).await?;
+        Ok(user_id)
+    }
+
+    /// Second login step for accounts with two-factor authentication.
+    pub async fn login_mfa(
+        &self,
+        payload: LoginMfaPayload,
+        client: ClientInfo,
+    ) -> DbResult<AuthResponse> {
+        let passed = self
+            .mfa_service
+            .pass_challenge(&payload.mfa_token, &payload.code)
             .await?;
+        // The account may have been suspended since the password step.
+        self.user_service.ensure_active(passed.user_id).await?;
 
-        let expire: i64 = payload.expire.unwrap_or_else(|| {
-            var("JWT_EXIPRE")
-                .ok()
-                .and_then(|v| v.parse::<i64>().ok())
-                .unwrap_or(1000 * 60 * 10)
-        });
+        let user = self
+            .user_service
+            .find_user_by_id(passed.user_id)
+            .await?
+            .ok_or(DbErr::Custom("Cannot find user".to_string()))?;
+        let session = self
+            .open_session(passed.user_id, passed.remember, client)
+            .await?;
+        let limited = self
+            .is_limited(user.user_id, user.email_verified_at.is_some())
+            .await?;
 
-        let token = Jwt::sign(TokenPayload {
-            user_id: user.user_id,
-            expire: expire.clone(),
-        })
-        .unwrap();
+        self.sign_session(session, Self::access_expire(), limited)
+    }
+
+    pub async fn disable_mfa(&self, user_id: Uuid, code: &str) -> DbResult<()> {
+        if self.mfa_service.is_required(user_id).await? {
+            return Err(DbErr::Custom(
+                "Two-factor authentication is required by one of your teams".to_string(),
+            ));
+        }
 
-        Ok(AuthResponse { token, expire })
+        self.mfa_service.disable_totp(user_id, code).await
     }
 
-    pub async fn register(&self, payload: CreateUserDto) -> DbResult<AuthResponse> {
+    pub async fn register(
+        &self,
+        payload: CreateUserDto,
+        client: ClientInfo,
+    ) -> DbResult<AuthResponse> {
+        // Check the invitation first, a bad one should not leave an account behind.
+        let invite_token = payload.invite_token.clone();
+        if let Some(token) = &invite_token {
+            let invitation = self.team_invitation_service.preview(token).await?;
+            if invitation.email != payload.email.trim().to_lowercase() {
+                return Err(DbErr::Custom(
+                    "This invitation was sent to another email address".to_string(),
+                ));
+            }
+        }
+
+        let user = self.user_service.create_user(payload).await?;
+
+        // The invite link proved the address, so no verification mail is needed.
+        let mut email_verified = false;
+        if let Some(token) = &invite_token {
+            match self
+                .team_invitation_service
+                .accept(token, user.user_id)
+                .await
+            {
+                Ok(_) => email_verified = true,
+                Err(e) => log::error!("Accept invitation on register error: {:#?}", e),
+            }
+        }
+
+        // The account exists at this point, a failed mail can be resent later.
+        if !email_verified {
+            if let Err(e) = self.send_verify_email(user.user_id, &user.email).await {
+                log::error!("Send verify email error: {:#?}", e);
+            }
+        }
+
+        let session = self
+            .session_service
+            .create_session(user.user_id, false, client)
+            .await?;
+
+        let limited = self.is_limited(user.user_id, email_verified).await?;
+
+        self.sign_session(session, Self::access_expire(), limited)
+    }
+
+    pub async fn refresh_token(&self, refresh_token: &str) -> DbResult<AuthResponse> {
+        let session = self.session_service.rotate_session(refresh_token).await?;
+        self.user_service.ensure_active(session.user_id).await?;
         let user = self
             .user_service
-            .create_user(payload)
+            .find_user_by_id(session.user_id)
+            .await?
+            .ok_or(DbErr::Custom("Cannot find user".to_string()))?;
+        let limited = self
+            .is_limited(user.user_id, user.email_verified_at.is_some())
+            .await?;
+
+        self.sign_session(session, Self::access_expire(), limited)
+    }
+
+    async fn send_verify_email(&self, user_id: Uuid, email: &str) -> DbResult<()> {
+        let code = self
+            .verification_code_service
+            .issue_code(user_id, email, CodePurposes::EmailVerify)
+            .await?;
+
+        let mut body = format!("Your email verification code is {}.", code);
+        if let Some(verify_link) = &self.config.verify_link {
+            let link = Url::parse_with_params(verify_link, &[("email", email), ("code", &code)])
+                .map_err(|e| DbErr::Custom(format!("Invalid verify link: {}", e)))?;
+            body.push_str(&format!("\n\nOr open this link to verify: {}", link));
+        }
+        body.push_str("\n\nThe code expires in 15 minutes.");
+
+        self.mailer
+            .send(MailMessage {
+                to: email.to_string(),
+                subject: "Verify your email".to_string(),
+                body,
+            })
+            .await
+    }
+
+    pub async fn resend_verify_email(&self, user_id: Uuid) -> DbResult<bool> {
+        let user = self.get_user_info(user_id).await?;
+
+        if user.email_verified_at.is_some() {
+            return Err(DbErr::Custom("Email is already verified".to_string()));
+        }
+
+        self.send_verify_email(user.user_id, &user.email).await?;
+        Ok(true)
+    }
+
+    pub async fn verify_email(&self, email: &str, code: &str) -> DbResult<bool> {
+        let verified = self
+            .verification_code_service
+            .verify_code(email, CodePurposes::EmailVerify, code, true)
+            .await?;
+
+        self.user_service
+            .mark_email_verified(verified.user_id, &verified.email)
+            .await?;
+
+        Ok(true)
+    }
+
+    pub async fn logout(&self, info: &TokenInfo) -> DbResult<()> {
+        self.revocation_store
+            .revoke_token(&info.jti, info.expires_at)
+            .await?;
+
+        if let Some(session_id) = info.session_id {
+            self.session_service.revoke_session(session_id).await?;
+        }
+
+        match info.actor_id {
+            Some(actor_id) => {
+                self.activity_log_service
+                    .record_impersonated(
+                        actor_id,
+                        info.user_id,
+                        Actions::Impersonated,
+                        TargetTypes::User,
+                        info.user_id,
+                        "Ended impersonation",
+                    )
+                    .await
+            }
+            None => {
+                self.activity_log_service
+                    .record(
+                        info.user_id,
+                        Actions::LoggedOut,
+                        TargetTypes::User,
+                        info.user_id,
+                        "Logged out",
+                    )
+                    .await
+            }
+        }
+    }
+
+    /// Revoke every access token and session the user currently holds.
+    pub async fn revoke_user_tokens(&self, user_id: Uuid) -> DbResult<()> {
+        // `iat` only has second precision, so tokens of the current second stay
+        // valid; otherwise a login right after a password reset would be revoked.
+        let now = get_current_timestamp();
+        self.revocation_store
+            .revoke_user(user_id, now - now % 1000 - 1)
+            .await?;
+        self.session_service.revoke_user_sessions(user_id).await
+    }
+
+    pub async fn deactivate_user(&self, user_id: Uuid) -> DbResult<()> {
+        self.user_service
+            .update_status_by_id(user_id, Status::Inactive)
+            .await?;
+        self.revoke_user_tokens(user_id).await
+    }
+
+    /// Let a platform admin act as a user. The token carries both IDs, has no
+    /// session to refresh and stops working once it expires or is logged out.
+    pub async fn impersonate(
+        &self,
+        admin_id: Uuid,
+        user_id: Uuid,
+        payload: ImpersonatePayload,
+    ) -> DbResult<ImpersonationResponse> {
+        if admin_id == user_id {
+            return Err(DbErr::Custom("You cannot impersonate yourself".to_string()));
+        }
+        if self
+            .user_service
+            .find_platform_role(user_id)
             .await?
-            .try_into_model()
-            .unwrap();
+            .can_administer()
+        {
+            return Err(DbErr::Custom(
+                "Platform admins cannot be impersonated".to_string(),
+            ));
+        }
+        self.user_service.ensure_active(user_id).await?;
 
-        // TODO: Send email verify code
+        let expire = self.impersonation_expire();
+        let token = self
+            .jwt
+            .sign(TokenPayload {
+                user_id,
+                session_id: None,
+                limited: false,
+                actor_id: Some(admin_id),
+                expire,
+            })
+            .map_err(|e| DbErr::Custom(format!("Sign token error: {}", e)))?;
 
-        let expire = std::env::var("JWT_EXIPRE")
-            .map(|v| v.parse::<i64>().unwrap_or(1000 * 60 * 10))
-            .unwrap_or(1000 * 60 * 10);
+        let description = match payload.reason.filter(|reason| !reason.trim().is_empty()) {
+            Some(reason) => format!("Started impersonation: {}", reason),
+            None => "Started impersonation".to_string(),
+        };
+        self.activity_log_service
+            .record_impersonated(
+                admin_id,
+                user_id,
+                Actions::Impersonated,
+                TargetTypes::User,
+                user_id,
+                &description,
+            )
+            .await?;
 
-        let token = Jwt::sign(TokenPayload {
-            user_id: user.user_id,
+        Ok(ImpersonationResponse {
+            token,
             expire,
+            user_id,
         })
-        .unwrap();
+    }
 
-        Ok(AuthResponse { token, expire })
+    /// Audit one request made with an impersonation token.
+    pub async fn record_impersonated_request(
+        &self,
+        info: &TokenInfo,
+        method: &str,
+        path: &str,
+    ) -> DbResult<()> {
+        let Some(actor_id) = info.actor_id else {
+            return Ok(());
+        };
+
+        self.activity_log_service
+            .record_impersonated(
+                actor_id,
+                info.user_id,
+                Actions::Requested,
+                TargetTypes::User,
+                info.user_id,
+                &format!("{} {}", method, path),
+            )
+            .await
     }
 
-    pub async fn logout(&self) {
-        // TODO: Remove redis data
+    fn refuse_impersonation(actor_id: Option<Uuid>) -> DbResult<()> {
+        match actor_id {
+            Some(_) => Err(DbErr::Custom(REFUSED_WHILE_IMPERSONATING.to_string())),
+            None => Ok(()),
+        }
     }
 
-    pub async fn forget_send_email(&self, email: &str) -> DbResult<bool> {
-        let exists = self.user_service.check_email_exist(email).await?;
+    /// Suspend an account on behalf of a platform admin, signing it out everywhere.
+    pub async fn suspend_user(
+        &self,
+        admin_id: Uuid,
+        user_id: Uuid,
+        payload: SuspendUserPayload,
+    ) -> DbResult<()> {
+        if admin_id == user_id {
+            return Err(DbErr::Custom("You cannot suspend yourself".to_string()));
+        }
+
+        let until = payload
+            .until
+            .as_deref()
+            .map(|until| {
+                sqlx::types::chrono::NaiveDateTime::parse_from_str(until, "%Y-%m-%d %H:%M:%S")
+                    .map_err(|_| {
+                        DbErr::Custom("until: must be formatted as YYYY-MM-DD HH:MM:SS".to_string())
+                    })
+            })
+            .transpose()?;
+
+        let user = self
+            .user_service
+            .suspend_user(user_id, payload.reason, until)
+            .await?;
+        self.revoke_user_tokens(user_id).await?;
 
-        if !exists {
+        let until = until.map_or("further notice".to_string(), |until| {
+            until.format("%Y-%m-%d %H:%M:%S").to_string()
+        });
+        let description = match user.suspended_reason {
+            Some(reason) => format!("Suspended until {}: {}", until, reason),
+            None => format!("Suspended until {}", until),
+        };
+        self.activity_log_service
+            .record(
+                admin_id,
+                Actions::Suspended,
+                TargetTypes::User,
+                user_id,
+                &description,
+            )
+            .await
+    }
+
+    /// Lift a suspension or deactivation on behalf of a platform admin.
+    pub async fn reactivate_user(&self, admin_id: Uuid, user_id: Uuid) -> DbResult<()> {
+        let previous = self
+            .user_service
+            .find_user_by_id(user_id)
+            .await?
+            .ok_or(DbErr::Custom("Cannot find user".to_string()))?
+            .status;
+        if previous == Status::Active {
+            return Err(DbErr::Custom("User is already active".to_string()));
+        }
+
+        self.user_service.reactivate_user(user_id).await?;
+        self.activity_log_service
+            .record(
+                admin_id,
+                Actions::Reactivated,
+                TargetTypes::User,
+                user_id,
+                &format!("Reactivated {} account", previous.to_value()),
+            )
+            .await
+    }
+
+    /// Make a user pick a new password: the current one stops working, every
+    /// session is signed out and a reset code is mailed.
+    pub async fn force_password_reset(&self, admin_id: Uuid, user_id: Uuid) -> DbResult<()> {
+        let user = self.user_service.scramble_password(user_id).await?;
+        self.revoke_user_tokens(user_id).await?;
+
+        let code = self
+            .verification_code_service
+            .issue_code(user.user_id, &user.email, CodePurposes::PasswordReset)
+            .await?;
+        self.mailer
+            .send(MailMessage {
+                to: user.email,
+                subject: "Your password has been reset".to_string(),
+                body: format!(
+                    "An administrator has reset your password, you have been signed out everywhere.\n\nUse the code {} to choose a new password. It expires in 15 minutes, after that you can request a new one from the forgot password page.",
+                    code
+                ),
+            })
+            .await?;
+
+        self.activity_log_service
+            .record(
+                admin_id,
+                Actions::Updated,
+                TargetTypes::User,
+                user_id,
+                "Forced password reset",
+            )
+            .await
+    }
+
+    /// Grant or take away platform roles, super admins only.
+    pub async fn update_platform_role(
+        &self,
+        admin_id: Uuid,
+        user_id: Uuid,
+        role: PlatformRoles,
+    ) -> DbResult<()> {
+        if !self
+            .user_service
+            .find_platform_role(admin_id)
+            .await?
+            .can_manage_admins()
+        {
             return Err(DbErr::Custom(
-                "Cannot find user with provided email".to_string(),
+                "Only super admins can change platform roles".to_string(),
+            ));
+        }
+        if admin_id == user_id {
+            return Err(DbErr::Custom(
+                "You cannot change your own platform role".to_string(),
             ));
         }
 
-        // TODO: Send verify mail and save code
-        Ok(exists)
+        self.user_service
+            .update_platform_role_by_id(user_id, role.clone())
+            .await?;
+        self.activity_log_service
+            .record(
+                admin_id,
+                Actions::Updated,
+                TargetTypes::User,
+                user_id,
+                &format!("Changed platform role to {}", role.to_value()),
+            )
+            .await
     }
 
-    pub async fn forget_verify_code(&self, _email: &str, _code: &str) -> DbResult<bool> {
-        // TODO: Check the email and code can match in redix saved data
+    /// Change the password of the logged-in user, signing out every other session.
+    pub async fn change_password(
+        &self,
+        user_id: Uuid,
+        actor_id: Option<Uuid>,
+        session_id: Option<Uuid>,
+        payload: ChangePasswordPayload,
+    ) -> DbResult<()> {
+        Self::refuse_impersonation(actor_id)?;
+        self.user_service
+            .change_password(user_id, &payload.current_password, &payload.new_password)
+            .await?;
 
-        Ok(true)
+        match session_id {
+            Some(session_id) => {
+                self.session_service
+                    .revoke_other_sessions(user_id, session_id)
+                    .await?;
+            }
+            None => self.revoke_user_tokens(user_id).await?,
+        }
+
+        self.activity_log_service
+            .record(
+                user_id,
+                Actions::Updated,
+                TargetTypes::User,
+                user_id,
+                "Changed password",
+            )
+            .await
     }
 
-    pub async fn forget(&self, payload: ForgetPasswordPayload) -> DbResult<bool> {
+    pub async fn deactivate_account(
+        &self,
+        user_id: Uuid,
+        actor_id: Option<Uuid>,
+        payload: ConfirmPasswordPayload,
+    ) -> DbResult<()> {
+        Self::refuse_impersonation(actor_id)?;
+        self.user_service
+            .check_password_by_id(user_id, &payload.password)
+            .await?;
+
+        self.deactivate_user(user_id).await?;
+        self.activity_log_service
+            .record(
+                user_id,
+                Actions::Updated,
+                TargetTypes::User,
+                user_id,
+                "Deactivated account",
+            )
+            .await
+    }
+
+    /// Delete the account of the logged-in user. Teams can't be left without
+    /// an Owner, so sole Owners have to hand over or delete those teams first.
+    pub async fn delete_account(
+        &self,
+        user_id: Uuid,
+        actor_id: Option<Uuid>,
+        payload: ConfirmPasswordPayload,
+    ) -> DbResult<()> {
+        Self::refuse_impersonation(actor_id)?;
         self.user_service
-            .update_password_by_email(&payload.email, &payload.password)
+            .check_password_by_id(user_id, &payload.password)
+            .await?;
+
+        let txn = self.db.begin().await?;
+        let sole_owned = self
+            .team_user_service
+            .find_sole_owned_teams(&txn, user_id)
+            .await?;
+        if !sole_owned.is_empty() {
+            let names = sole_owned
+                .iter()
+                .map(|team| format!("`{}`", team.team_name))
+                .collect::<Vec<_>>()
+                .join(", ");
+            return Err(DbErr::Custom(format!(
+                "You are the only Owner of {}, transfer ownership or delete the team first",
+                names
+            )));
+        }
+
+        self.team_user_service
+            .left_all_relation_by_user(&txn, user_id)
+            .await?;
+        self.api_key_service
+            .delete_keys_by_user(&txn, user_id)
             .await?;
+        self.oidc_service
+            .unlink_identities_by_user(&txn, user_id)
+            .await?;
+        self.activity_log_service
+            .record_with(
+                &txn,
+                user_id,
+                Actions::Removed,
+                TargetTypes::User,
+                user_id,
+                "Deleted account",
+            )
+            .await?;
+        self.user_service.delete_user(&txn, user_id).await?;
+        txn.commit().await?;
+
+        // The revocation store may live outside the database. Until it caught
+        // up the tokens are refused anyway, their user no longer exists.
+        self.revoke_user_tokens(user_id).await
+    }
+
+    pub async fn forget_send_email(&self, email: &str) -> DbResult<bool> {
+        // Answer the same way for unknown emails so the endpoint can't be used
+        // to find out who has an account.
+        let Some(user) = self.user_service.find_user_by_email(email).await? else {
+            log::info!("Password reset requested for unknown email: {}", email);
+            return Ok(true);
+        };
+
+        // Throttled or failed sends are only logged, an error here would
+        // tell the caller the account exists.
+        if let Err(e) = self.send_reset_code(&user).await {
+            log::warn!("Password reset code not sent to {}: {}", user.email, e);
+        }
 
         Ok(true)
     }
 
-    pub async fn get_user_id_by_token(&self, token: &str) -> DbResult<Uuid> {
-        let info = Jwt::extract_info(token).unwrap();
-        Ok(info.user_id)
+    async fn send_reset_code(&self, user: &users::Model) -> DbResult<()> {
+        let code = self
+            .verification_code_service
+            .issue_code(user.user_id, &user.email, CodePurposes::PasswordReset)
+            .await?;
+
+        self.mailer
+            .send(MailMessage {
+                to: user.email.clone(),
+                subject: "Reset your password".to_string(),
+                body: format!(
+                    "Your password reset code is {}.\n\nIt expires in 15 minutes. If you did not ask to reset your password, you can ignore this email.",
+                    code
+                ),
+            })
+            .await
     }
 
-    pub async fn get_user_info_by_token(&self, token: &str) -> DbResult<PartialUser> {
-        let info = Jwt::extract_info(token).unwrap();
-        let user = self.user_service.find_user_by_id(info.user_id).await?;
+    pub async fn forget_verify_code(&self, email: &str, code: &str) -> DbResult<bool> {
+        self.verification_code_service
+            .verify_code(email, CodePurposes::PasswordReset, code, false)
+            .await?;
 
-        Ok(user.unwrap())
+        Ok(true)
     }
 
-    pub async fn update_user_by_token(&self, token: &str, payload: UpdateUserDto) -> DbResult<()> {
-        let info = Jwt::extract_info(token).unwrap();
+    pub async fn forget(&self, payload: ForgetPasswordPayload) -> DbResult<bool> {
+        if payload.password.trim().is_empty() {
+            return Err(DbErr::Custom("New password cannot be empty".to_string()));
+        }
+
+        let verified = self
+            .verification_code_service
+            .verify_code(
+                &payload.email,
+                CodePurposes::PasswordReset,
+                &payload.code,
+                true,
+            )
+            .await?;
+
+        let user = self
+            .user_service
+            .update_password_by_email(&verified.email, &payload.password)
+            .await?;
+        self.revoke_user_tokens(user.user_id).await?;
+        self.activity_log_service
+            .record(
+                user.user_id,
+                Actions::Updated,
+                TargetTypes::User,
+                user.user_id,
+                "Reset password with an emailed code",
+            )
+            .await?;
+
+        Ok(true)
+    }
+
+    pub async fn get_user_info(&self, user_id: Uuid) -> DbResult<PartialUser> {
         self.user_service
-            .update_user_by_id(info.user_id, payload)
+            .find_user_by_id(user_id)
+            .await?
+            .ok_or(DbErr::Custom("Cannot find user".to_string()))
+    }
+
+    pub async fn update_user_info(&self, user_id: Uuid, payload: UpdateUserDto) -> DbResult<()> {
+        let email_updated = payload.email.is_some();
+        let user = self
+            .user_service
+            .update_user_by_id(user_id, payload)
             .await?;
 
+        if email_updated && user.email_verified_at.is_none() {
+            self.send_verify_email(user.user_id, &user.email).await?;
+        }
+
         Ok(())
     }
 
     pub async fn verify_token(&self, token: &str) -> bool {
-        Jwt::verify(token).is_ok()
+        self.validate_token(token).await.is_some()
     }
 
-    pub async fn reset_token_by_jwt(&self, token: &str) -> AuthResponse {
-        let info = Jwt::extract_info(token).unwrap();
-        let token = Jwt::sign(TokenPayload {
-            user_id: info.user_id,
-            expire: info.expire.clone(),
-        })
-        .unwrap();
+    /// Verify the signature, expiry and revocation of a token.
+    pub async fn validate_token(&self, token: &str) -> Option<TokenInfo> {
+        let info = self.jwt.verify(token).ok()?;
 
-        AuthResponse {
-            token,
-            expire: info.expire,
+        match self.revocation_store.is_revoked(&info).await {
+            Ok(false) => {}
+            Ok(true) => return None,
+            Err(e) => {
+                log::error!("Check token revocation error: {:#?}", e);
+                return None;
+            }
+        }
+
+        // An impersonation ends as soon as the admin loses the role.
+        if let Some(actor_id) = info.actor_id {
+            if let Err(e) = self.user_service.ensure_active_admin(actor_id).await {
+                log::info!("Refused impersonation by {}: {}", actor_id, e);
+                return None;
+            }
+        }
+
+        // Deactivating or suspending an account, or revoking the session, cuts
+        // its access tokens off right away.
+        let active = match info.session_id {
+            Some(session_id) => {
+                self.user_service
+                    .ensure_active_session(info.user_id, session_id)
+                    .await
+            }
+            None => self.user_service.ensure_active(info.user_id).await,
+        };
+        if let Err(e) = active {
+            log::info!("Refused token of user {}: {}", info.user_id, e);
+            return None;
         }
+
+        Some(info)
+    }
+
+    /// `current` marks the session making the request.
+    pub async fn list_sessions(
+        &self,
+        user_id: Uuid,
+        current: Option<Uuid>,
+    ) -> DbResult<Vec<SessionInfo>> {
+        self.session_service
+            .find_sessions_by_user(user_id, current)
+            .await
+    }
+
+    pub async fn revoke_session(
+        &self,
+        user_id: Uuid,
+        actor_id: Option<Uuid>,
+        session_id: Uuid,
+    ) -> DbResult<()> {
+        Self::refuse_impersonation(actor_id)?;
+        self.session_service
+            .revoke_user_session(user_id, session_id)
+            .await
+    }
+
+    /// Sign out everywhere except the session making the request.
+    pub async fn revoke_other_sessions(
+        &self,
+        user_id: Uuid,
+        actor_id: Option<Uuid>,
+        current: Option<Uuid>,
+    ) -> DbResult<u64> {
+        Self::refuse_impersonation(actor_id)?;
+        let current = current.ok_or(DbErr::Custom(
+            "This token does not belong to a session".to_string(),
+        ))?;
+        self.session_service
+            .revoke_other_sessions(user_id, current)
+            .await
     }
 }
@@ -0,0 +1,175 @@
+use std::{sync::Arc, time::Duration};
+
+use crate::_entities::login_attempts;
+use crate::_entities::prelude::LoginAttempts;
+use crate::utils::DbResult;
+use sea_orm::sea_query::OnConflict;
+use sea_orm::sqlx::types::chrono;
+use sea_orm::*;
+
+/// The only answer a failed password login gets, whether the account exists or not.
+pub const LOGIN_FAILED: &str = "Incorrect email or password";
+const TOO_MANY_ATTEMPTS: &str = "Too many failed login attempts, please try again later";
+
+/// How long a lockout lasts.
+pub const LOCK_DURATION: Duration = Duration::from_secs(15 * 60);
+/// Counters start over after this long without a failure.
+const RESET_WINDOW: Duration = Duration::from_secs(60 * 60);
+/// Upper bound of the delay between two attempts before the lockout.
+const MAX_DELAY: Duration = Duration::from_secs(60);
+
+struct Limits {
+    /// Failures allowed before every further attempt has to wait.
+    free_attempts: i32,
+    /// Failures that lock the key for `LOCK_DURATION`.
+    lock_after: i32,
+}
+
+const ACCOUNT_LIMITS: Limits = Limits {
+    free_attempts: 3,
+    lock_after: 10,
+};
+/// Looser, many users may share one address.
+const IP_LIMITS: Limits = Limits {
+    free_attempts: 10,
+    lock_after: 50,
+};
+
+/// Which counters the failure just pushed into a lockout.
+#[derive(Debug, Default)]
+pub struct Lockout {
+    pub account: bool,
+    pub ip: bool,
+}
+
+/// Failed password logins counted per account and per client address.
+pub struct LoginAttemptService {
+    pub db: Arc<DatabaseConnection>,
+}
+
+impl LoginAttemptService {
+    pub fn new(db: Arc<DatabaseConnection>) -> Self {
+        Self { db }
+    }
+
+    /// Refuse the attempt while the account or address is locked or still
+    /// waiting out its delay.
+    pub async fn check(&self, email: &str, ip: Option<&str>) -> DbResult<()> {
+        let now = chrono::Utc::now().naive_utc();
+        let keys = Self::keys(email, ip);
+
+        let counters = LoginAttempts::find()
+            .filter(login_attempts::Column::AttemptKey.is_in(keys.iter().map(|(key, _)| key)))
+            .all(self.db.as_ref())
+            .await?;
+
+        for counter in counters {
+            let limits = keys
+                .iter()
+                .find(|(key, _)| *key == counter.attempt_key)
+                .map(|(_, limits)| *limits)
+                .unwrap_or(&ACCOUNT_LIMITS);
+            if Self::blocked_until(&counter, limits).is_some_and(|until| until > now) {
+                return Err(DbErr::Custom(TOO_MANY_ATTEMPTS.to_string()));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub async fn record_failure(&self, email: &str, ip: Option<&str>) -> DbResult<Lockout> {
+        let now = chrono::Utc::now().naive_utc();
+        let mut lockout = Lockout::default();
+
+        LoginAttempts::delete_many()
+            .filter(login_attempts::Column::LastFailedAt.lt(now - RESET_WINDOW))
+            .filter(
+                Condition::any()
+                    .add(login_attempts::Column::LockedUntil.is_null())
+                    .add(login_attempts::Column::LockedUntil.lt(now)),
+            )
+            .exec(self.db.as_ref())
+            .await?;
+
+        for (key, limits) in Self::keys(email, ip) {
+            let previous = LoginAttempts::find_by_id(key.clone())
+                .one(self.db.as_ref())
+                .await?;
+            // A lockout that ran out starts the count over.
+            let failures = match previous {
+                Some(counter) if counter.locked_until.is_none_or(|until| until > now) => {
+                    counter.failures + 1
+                }
+                _ => 1,
+            };
+            let locked = failures >= limits.lock_after;
+
+            LoginAttempts::insert(login_attempts::ActiveModel {
+                attempt_key: Set(key.clone()),
+                failures: Set(failures),
+                locked_until: Set(locked.then(|| now + LOCK_DURATION)),
+                last_failed_at: Set(now),
+            })
+            .on_conflict(
+                OnConflict::column(login_attempts::Column::AttemptKey)
+                    .update_columns([
+                        login_attempts::Column::Failures,
+                        login_attempts::Column::LockedUntil,
+                        login_attempts::Column::LastFailedAt,
+                    ])
+                    .to_owned(),
+            )
+            .exec(self.db.as_ref())
+            .await?;
+
+            if locked {
+                if key.starts_with("ip:") {
+                    lockout.ip = true;
+                } else {
+                    lockout.account = true;
+                }
+            }
+        }
+
+        Ok(lockout)
+    }
+
+    /// Clear the account counter after a successful login or an unlock. The
+    /// address counter is left alone, an attacker may own an account too.
+    pub async fn reset_account(&self, email: &str) -> DbResult<()> {
+        LoginAttempts::delete_by_id(Self::account_key(email))
+            .exec(self.db.as_ref())
+            .await?;
+
+        Ok(())
+    }
+
+    fn blocked_until(
+        counter: &login_attempts::Model,
+        limits: &Limits,
+    ) -> Option<chrono::NaiveDateTime> {
+        if counter.locked_until.is_some() {
+            return counter.locked_until;
+        }
+        if counter.failures < limits.free_attempts {
+            return None;
+        }
+
+        // 1s, 2s, 4s, ... up to `MAX_DELAY`.
+        let exponent = (counter.failures - limits.free_attempts).min(16) as u32;
+        let delay = Duration::from_secs(2u64.pow(exponent)).min(MAX_DELAY);
+        Some(counter.last_failed_at + delay)
+    }
+
+    fn keys(email: &str, ip: Option<&str>) -> Vec<(String, &'static Limits)> {
+        let mut keys = vec![(Self::account_key(email), &ACCOUNT_LIMITS)];
+        if let Some(ip) = ip {
+            keys.push((format!("ip:{}", ip), &IP_LIMITS));
+        }
+        keys
+    }
+
+    fn account_key(email: &str) -> String {
+        format!("account:{}", email.trim().to_lowercase())
+    }
+}
@@ -0,0 +1,443 @@
+use std::{sync::Arc, time::Duration};
+
+use crate::_entities::prelude::{MfaChallenges, RecoveryCodes, TeamUsers, Teams, UserTotp};
+use crate::_entities::sea_orm_active_enums::{Actions, TargetTypes, TeamUserStatus};
+use crate::_entities::{mfa_challenges, recovery_codes, team_users, teams, user_totp};
+use crate::common::activity_log_service::ActivityLogService;
+use crate::utils::encrypt::{generator_random_token, hash_token};
+use crate::utils::DbResult;
+use sea_orm::prelude::Uuid;
+use sea_orm::sea_query::Expr;
+use sea_orm::sqlx::types::chrono;
+use sea_orm::*;
+use serde::{Deserialize, Serialize};
+use totp_rs::{Algorithm, Secret, TOTP};
+
+const TOTP_DIGITS: usize = 6;
+const TOTP_STEP: u64 = 30;
+/// Accept codes one step before and after the current one for clock drift.
+const TOTP_SKEW: u64 = 1;
+const RECOVERY_CODE_COUNT: usize = 10;
+/// How long the second login step may take.
+const CHALLENGE_EXPIRE: Duration = Duration::from_secs(5 * 60);
+/// Wrong codes allowed before a challenge is burned.
+const CHALLENGE_MAX_ATTEMPTS: i32 = 5;
+/// Wrong codes in a row allowed before two-factor checks lock.
+const CODE_MAX_ATTEMPTS: i32 = 5;
+const CODE_LOCK_DURATION: Duration = Duration::from_secs(15 * 60);
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TotpSetup {
+    pub secret: String,
+    /// `otpauth://` URI to render as a QR code for authenticator apps.
+    #[serde(rename = "otpauthUrl")]
+    pub otpauth_url: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MfaChallenge {
+    #[serde(rename = "mfaRequired")]
+    pub mfa_required: bool,
+    #[serde(rename = "mfaToken")]
+    pub mfa_token: String,
+    pub expire: i64,
+}
+
+#[derive(Debug)]
+pub struct PassedChallenge {
+    pub user_id: Uuid,
+    pub remember: bool,
+}
+
+pub struct MfaService {
+    pub db: Arc<DatabaseConnection>,
+    issuer: String,
+    pub activity_log_service: Arc<ActivityLogService>,
+}
+
+impl MfaService {
+    pub fn new(
+        db: Arc<DatabaseConnection>,
+        issuer: String,
+        activity_log_service: Arc<ActivityLogService>,
+    ) -> Self {
+        Self {
+            db,
+            issuer,
+            activity_log_service,
+        }
+    }
+
+    pub async fn is_enabled(&self, user_id: Uuid) -> DbResult<bool> {
+        Ok(UserTotp::find_by_id(user_id)
+            .filter(user_totp::Column::EnabledAt.is_not_null())
+            .one(self.db.as_ref())
+            .await?
+            .is_some())
+    }
+
+    /// Whether any team the user is in requires two-factor authentication.
+    pub async fn is_required(&self, user_id: Uuid) -> DbResult<bool> {
+        Ok(TeamUsers::find()
+            .join(
+                JoinType::InnerJoin,
+                team_users::Entity::belongs_to(teams::Entity)
+                    .from(team_users::Column::TeamId)
+                    .to(teams::Column::TeamId)
+                    .into(),
+            )
+            .filter(team_users::Column::UserId.eq(user_id))
+            .filter(team_users::Column::Status.eq(TeamUserStatus::Joined))
+            .filter(teams::Column::RequireMfa.eq(true))
+            .one(self.db.as_ref())
+            .await?
+            .is_some())
+    }
+
+    pub async fn set_team_required(
+        &self,
+        team_id: Uuid,
+        actor_id: Uuid,
+        required: bool,
+    ) -> DbResult<()> {
+        let before = Teams::find_by_id(team_id)
+            .one(self.db.as_ref())
+            .await?
+            .ok_or(DbErr::Custom("Cannot find team!".to_string()))?;
+
+        let mut team = before.clone().into_active_model();
+        team.require_mfa = Set(required);
+        let team = team.update(self.db.as_ref()).await?;
+
+        let Some(changes) = ActivityLogService::changes(Some(&before), Some(&team)) else {
+            return Ok(());
+        };
+        let description = match required {
+            true => "Required two-factor authentication",
+            false => "Stopped requiring two-factor authentication",
+        };
+        self.activity_log_service
+            .record_changes(
+                actor_id,
+                Actions::Updated,
+                TargetTypes::Team,
+                team_id,
+                description,
+                Some(changes),
+            )
+            .await
+    }
+
+    /// Start enrollment with a fresh secret. Nothing changes for login until
+    /// the secret is confirmed with `confirm_totp`.
+    pub async fn setup_totp(&self, user_id: Uuid, email: &str) -> DbResult<TotpSetup> {
+        if self.is_enabled(user_id).await? {
+            return Err(DbErr::Custom(
+                "Two-factor authentication is already enabled".to_string(),
+            ));
+        }
+
+        let secret = Secret::generate_secret().to_encoded().to_string();
+        let totp = Self::build_totp(&secret, Some(self.issuer.clone()), email.to_string())?;
+
+        // A fresh secret must not start the wrong code count over.
+        let previous = UserTotp::find_by_id(user_id).one(self.db.as_ref()).await?;
+        UserTotp::delete_by_id(user_id)
+            .exec(self.db.as_ref())
+            .await?;
+        user_totp::ActiveModel {
+            user_id: Set(user_id),
+            secret: Set(secret.clone()),
+            last_used_step: Set(0),
+            failed_attempts: Set(previous.as_ref().map_or(0, |totp| totp.failed_attempts)),
+            locked_until: Set(previous.and_then(|totp| totp.locked_until)),
+            enabled_at: Set(None),
+            ..Default::default()
+        }
+        .insert(self.db.as_ref())
+        .await?;
+
+        Ok(TotpSetup {
+            secret,
+            otpauth_url: totp.get_url(),
+        })
+    }
+
+    /// Enable TOTP after the first valid code and hand out recovery codes.
+    pub async fn confirm_totp(&self, user_id: Uuid, code: &str) -> DbResult<Vec<String>> {
+        let totp = UserTotp::find_by_id(user_id)
+            .filter(user_totp::Column::EnabledAt.is_null())
+            .one(self.db.as_ref())
+            .await?
+            .ok_or(DbErr::Custom(
+                "Start two-factor setup before confirming it".to_string(),
+            ))?;
+
+        Self::ensure_not_locked(&totp)?;
+        let valid = self.check_totp(&totp, code).await?;
+        self.record_code_result(&totp, valid).await?;
+
+        let mut model = totp.into_active_model();
+        model.enabled_at = Set(Some(chrono::Utc::now().naive_utc()));
+        model.update(self.db.as_ref()).await?;
+        self.record(user_id, "Enabled two-factor authentication")
+            .await?;
+
+        self.generate_recovery_codes(user_id).await
+    }
+
+    pub async fn disable_totp(&self, user_id: Uuid, code: &str) -> DbResult<()> {
+        self.verify_code(user_id, code).await?;
+
+        UserTotp::delete_by_id(user_id)
+            .exec(self.db.as_ref())
+            .await?;
+        RecoveryCodes::delete_many()
+            .filter(recovery_codes::Column::UserId.eq(user_id))
+            .exec(self.db.as_ref())
+            .await?;
+
+        self.record(user_id, "Disabled two-factor authentication")
+            .await
+    }
+
+    pub async fn regenerate_recovery_codes(
+        &self,
+        user_id: Uuid,
+        code: &str,
+    ) -> DbResult<Vec<String>> {
+        self.verify_code(user_id, code).await?;
+
+        self.record(user_id, "Regenerated recovery codes").await?;
+        self.generate_recovery_codes(user_id).await
+    }
+
+    async fn record(&self, user_id: Uuid, description: &str) -> DbResult<()> {
+        self.activity_log_service
+            .record(
+                user_id,
+                Actions::Updated,
+                TargetTypes::User,
+                user_id,
+                description,
+            )
+            .await
+    }
+
+    /// Accepts either a current TOTP code or an unused recovery code. Locked
+    /// for a while after too many wrong codes in a row, counted across login
+    /// challenges and account settings so a new login does not start over.
+    async fn verify_code(&self, user_id: Uuid, code: &str) -> DbResult<()> {
+        let totp = self.find_enabled_totp(user_id).await?;
+        Self::ensure_not_locked(&totp)?;
+        let valid = self.check_code(&totp, code).await?;
+        self.record_code_result(&totp, valid).await
+    }
+
+    async fn find_enabled_totp(&self, user_id: Uuid) -> DbResult<user_totp::Model> {
+        UserTotp::find_by_id(user_id)
+            .filter(user_totp::Column::EnabledAt.is_not_null())
+            .one(self.db.as_ref())
+            .await?
+            .ok_or(DbErr::Custom(
+                "Two-factor authentication is not enabled".to_string(),
+            ))
+    }
+
+    async fn check_code(&self, totp: &user_totp::Model, code: &str) -> DbResult<bool> {
+        if self.check_totp(totp, code).await? {
+            return Ok(true);
+        }
+
+        self.use_recovery_code(totp.user_id, code).await
+    }
+
+    fn ensure_not_locked(totp: &user_totp::Model) -> DbResult<()> {
+        match totp.locked_until {
+            Some(until) if until > chrono::Utc::now().naive_utc() => Err(DbErr::Custom(
+                "Too many invalid authentication codes, please try again later".to_string(),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Count a wrong code, locking once `CODE_MAX_ATTEMPTS` is reached, or
+    /// start the count over after a valid one. `Err` for a wrong code.
+    async fn record_code_result(&self, totp: &user_totp::Model, valid: bool) -> DbResult<()> {
+        let (failures, locked_until) = if valid {
+            (0, None)
+        } else if totp.failed_attempts + 1 >= CODE_MAX_ATTEMPTS {
+            (0, Some(chrono::Utc::now().naive_utc() + CODE_LOCK_DURATION))
+        } else {
+            (totp.failed_attempts + 1, None)
+        };
+
+        UserTotp::update_many()
+            .col_expr(user_totp::Column::FailedAttempts, Expr::value(failures))
+            .col_expr(user_totp::Column::LockedUntil, Expr::value(locked_until))
+            .filter(user_totp::Column::UserId.eq(totp.user_id))
+            .exec(self.db.as_ref())
+            .await?;
+
+        match valid {
+            true => Ok(()),
+            false => Err(DbErr::Custom("Invalid authentication code".to_string())),
+        }
+    }
+
+    pub async fn create_challenge(&self, user_id: Uuid, remember: bool) -> DbResult<MfaChallenge> {
+        let token = generator_random_token(32);
+        let now = chrono::Utc::now().naive_utc();
+
+        mfa_challenges::ActiveModel {
+            user_id: Set(user_id),
+            token_hash: Set(hash_token(&token)),
+            remember: Set(remember),
+            expires_at: Set(now + CHALLENGE_EXPIRE),
+            ..Default::default()
+        }
+        .insert(self.db.as_ref())
+        .await?;
+
+        Ok(MfaChallenge {
+            mfa_required: true,
+            mfa_token: token,
+            expire: CHALLENGE_EXPIRE.as_millis() as i64,
+        })
+    }
+
+    /// Check the second factor of a pending login and consume the challenge.
+    pub async fn pass_challenge(&self, mfa_token: &str, code: &str) -> DbResult<PassedChallenge> {
+        let challenge = MfaChallenges::find()
+            .filter(mfa_challenges::Column::TokenHash.eq(hash_token(mfa_token)))
+            .filter(mfa_challenges::Column::UsedAt.is_null())
+            .filter(mfa_challenges::Column::ExpiresAt.gt(chrono::Utc::now().naive_utc()))
+            .filter(mfa_challenges::Column::Attempts.lt(CHALLENGE_MAX_ATTEMPTS))
+            .one(self.db.as_ref())
+            .await?
+            .ok_or(DbErr::Custom(
+                "Login session has expired, please login again".to_string(),
+            ))?;
+
+        if let Err(e) = self.verify_code(challenge.user_id, code).await {
+            MfaChallenges::update_many()
+                .col_expr(
+                    mfa_challenges::Column::Attempts,
+                    Expr::col(mfa_challenges::Column::Attempts).add(1),
+                )
+                .filter(mfa_challenges::Column::ChallengeId.eq(challenge.challenge_id))
+                .exec(self.db.as_ref())
+                .await?;
+            return Err(e);
+        }
+
+        let res = MfaChallenges::update_many()
+            .col_expr(
+                mfa_challenges::Column::UsedAt,
+                Expr::value(chrono::Utc::now().naive_utc()),
+            )
+            .filter(mfa_challenges::Column::ChallengeId.eq(challenge.challenge_id))
+            .filter(mfa_challenges::Column::UsedAt.is_null())
+            .exec(self.db.as_ref())
+            .await?;
+
+        if res.rows_affected == 0 {
+            return Err(DbErr::Custom(
+                "Login session has expired, please login again".to_string(),
+            ));
+        }
+
+        Ok(PassedChallenge {
+            user_id: challenge.user_id,
+            remember: challenge.remember,
+        })
+    }
+
+    fn build_totp(secret: &str, issuer: Option<String>, account_name: String) -> DbResult<TOTP> {
+        let secret = Secret::Encoded(secret.to_string())
+            .to_bytes()
+            .map_err(|_| DbErr::Custom("Invalid two-factor secret".to_string()))?;
+
+        TOTP::new(
+            Algorithm::SHA1,
+            TOTP_DIGITS,
+            TOTP_SKEW as u8,
+            TOTP_STEP,
+            secret,
+            issuer,
+            account_name,
+        )
+        .map_err(|e| DbErr::Custom(format!("Build two-factor secret error: {:?}", e)))
+    }
+
+    /// Check a TOTP code and remember its time step so it cannot be replayed.
+    async fn check_totp(&self, model: &user_totp::Model, code: &str) -> DbResult<bool> {
+        // Issuer and account name only matter for the provisioning URI.
+        let totp = Self::build_totp(&model.secret, None, String::new())?;
+        let now = chrono::Utc::now().timestamp() as u64;
+        let code = code.trim();
+
+        let step = (0..=TOTP_SKEW * 2)
+            .map(|offset| (now / TOTP_STEP + offset).saturating_sub(TOTP_SKEW))
+            .find(|step| totp.generate(step * TOTP_STEP) == code);
+
+        let Some(step) = step else {
+            return Ok(false);
+        };
+
+        let res = UserTotp::update_many()
+            .col_expr(user_totp::Column::LastUsedStep, Expr::value(step as i64))
+            .filter(user_totp::Column::UserId.eq(model.user_id))
+            .filter(user_totp::Column::LastUsedStep.lt(step as i64))
+            .exec(self.db.as_ref())
+            .await?;
+
+        Ok(res.rows_affected > 0)
+    }
+
+    async fn use_recovery_code(&self, user_id: Uuid, code: &str) -> DbResult<bool> {
+        let res = RecoveryCodes::update_many()
+            .col_expr(
+                recovery_codes::Column::UsedAt,
+                Expr::value(chrono::Utc::now().naive_utc()),
+            )
+            .filter(recovery_codes::Column::UserId.eq(user_id))
+            .filter(recovery_codes::Column::CodeHash.eq(hash_token(&Self::normalize_code(code))))
+            .filter(recovery_codes::Column::UsedAt.is_null())
+            .exec(self.db.as_ref())
+            .await?;
+
+        Ok(res.rows_affected > 0)
+    }
+
+    async fn generate_recovery_codes(&self, user_id: Uuid) -> DbResult<Vec<String>> {
+        RecoveryCodes::delete_many()
+            .filter(recovery_codes::Column::UserId.eq(user_id))
+            .exec(self.db.as_ref())
+            .await?;
+
+        let codes: Vec<String> = (0..RECOVERY_CODE_COUNT)
+            .map(|_| {
+                let raw = generator_random_token(5);
+                format!("{}-{}", &raw[..5], &raw[5..])
+            })
+            .collect();
+
+        RecoveryCodes::insert_many(codes.iter().map(|code| recovery_codes::ActiveModel {
+            user_id: Set(user_id),
+            code_hash: Set(hash_token(&Self::normalize_code(code))),
+            ..Default::default()
+        }))
+        .exec_without_returning(self.db.as_ref())
+        .await?;
+
+        Ok(codes)
+    }
+
+    fn normalize_code(code: &str) -> String {
+        code.chars()
+            .filter(|c| c.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_lowercase()
+    }
+}
@@ -1,4 +1,17 @@
+pub mod admin_service;
+pub mod activity_log_service;
+pub mod api_key_service;
 pub mod auth_service;
+pub mod login_attempt_service;
+pub mod mfa_service;
+pub mod notification_service;
+pub mod oidc_service;
+pub mod permission_service;
+pub mod session_service;
 pub mod user_service;
+pub mod verification_code_service;
+pub mod team_invitation_service;
+pub mod team_join_request_service;
+pub mod team_ownership_service;
 pub mod team_service;
 pub mod team_user_service;
@@ -0,0 +1,109 @@
+use std::sync::Arc;
+
+use crate::_entities::notifications;
+use crate::_entities::prelude::Notifications;
+use crate::_entities::sea_orm_active_enums::NotificationTypes;
+use crate::utils::DbResult;
+use sea_orm::prelude::Uuid;
+use sea_orm::sea_query::Expr;
+use sea_orm::sqlx::types::chrono;
+use sea_orm::*;
+use serde::Serialize;
+
+#[derive(Debug, Serialize)]
+pub struct NotificationInfo {
+    #[serde(rename = "notificationId")]
+    pub notification_id: Uuid,
+    #[serde(rename = "type")]
+    pub notification_type: String,
+    pub title: String,
+    pub content: String,
+    pub read: bool,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+}
+
+impl From<notifications::Model> for NotificationInfo {
+    fn from(notification: notifications::Model) -> Self {
+        Self {
+            notification_id: notification.notification_id,
+            notification_type: notification.notification_type.to_value(),
+            title: notification.title,
+            content: notification.content,
+            read: notification.read_at.is_some(),
+            created_at: notification
+                .created_at
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string(),
+        }
+    }
+}
+
+/// In-app messages shown to a single user.
+pub struct NotificationService {
+    pub db: Arc<DatabaseConnection>,
+}
+
+impl NotificationService {
+    pub fn new(db: Arc<DatabaseConnection>) -> Self {
+        Self { db }
+    }
+
+    pub async fn notify(
+        &self,
+        user_id: Uuid,
+        notification_type: NotificationTypes,
+        title: &str,
+        content: &str,
+    ) -> DbResult<()> {
+        notifications::ActiveModel {
+            user_id: Set(user_id),
+            notification_type: Set(notification_type),
+            title: Set(title.to_string()),
+            content: Set(content.to_string()),
+            ..Default::default()
+        }
+        .insert(self.db.as_ref())
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn find_notifications_by_user(
+        &self,
+        user_id: Uuid,
+        unread_only: bool,
+    ) -> DbResult<Vec<NotificationInfo>> {
+        let mut query = Notifications::find().filter(notifications::Column::UserId.eq(user_id));
+        if unread_only {
+            query = query.filter(notifications::Column::ReadAt.is_null());
+        }
+
+        let notifications = query
+            .order_by_desc(notifications::Column::CreatedAt)
+            .all(self.db.as_ref())
+            .await?;
+
+        Ok(notifications
+            .into_iter()
+            .map(NotificationInfo::from)
+            .collect())
+    }
+
+    /// Mark one notification, or all of them without `notification_id`, as read.
+    pub async fn mark_read(&self, user_id: Uuid, notification_id: Option<Uuid>) -> DbResult<u64> {
+        let mut query = Notifications::update_many()
+            .col_expr(
+                notifications::Column::ReadAt,
+                Expr::value(chrono::Utc::now().naive_utc()),
+            )
+            .filter(notifications::Column::UserId.eq(user_id))
+            .filter(notifications::Column::ReadAt.is_null());
+        if let Some(notification_id) = notification_id {
+            query = query.filter(notifications::Column::NotificationId.eq(notification_id));
+        }
+
+        let res = query.exec(self.db.as_ref()).await?;
+        Ok(res.rows_affected)
+    }
+}
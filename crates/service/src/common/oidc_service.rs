@@ -0,0 +1,479 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use crate::_entities::prelude::{OidcStates, UserIdentities};
+use crate::_entities::{oidc_states, user_identities};
+use crate::config::OidcProviderConfig;
+use crate::utils::encrypt::{generator_random_token, hash_token};
+use crate::utils::DbResult;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use jsonwebtoken::{decode, decode_header, jwk::JwkSet, Algorithm, DecodingKey, Validation};
+use sea_orm::prelude::Uuid;
+use sea_orm::sea_query::Expr;
+use sea_orm::sqlx::types::chrono;
+use sea_orm::*;
+use serde::{Deserialize, Deserializer, Serialize};
+use sha2::{Digest, Sha256};
+use url::Url;
+
+/// How long a started login may take before the callback, in seconds.
+const STATE_EXPIRE: u64 = 60 * 10;
+/// ID tokens signed with anything else, HMAC in particular, are refused.
+const ID_TOKEN_ALGORITHMS: [Algorithm; 7] = [
+    Algorithm::RS256,
+    Algorithm::RS384,
+    Algorithm::RS512,
+    Algorithm::PS256,
+    Algorithm::ES256,
+    Algorithm::ES384,
+    Algorithm::EdDSA,
+];
+
+#[derive(Debug, Deserialize)]
+struct Discovery {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    jwks_uri: String,
+}
+
+struct ProviderMetadata {
+    discovery: Discovery,
+    jwks: JwkSet,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct IdTokenClaims {
+    sub: String,
+    nonce: Option<String>,
+    email: Option<String>,
+    /// Some providers send `"true"` instead of `true`.
+    #[serde(default, deserialize_with = "deserialize_flag")]
+    email_verified: bool,
+    name: Option<String>,
+    picture: Option<String>,
+}
+
+fn deserialize_flag<'de, D>(deserializer: D) -> Result<bool, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Flag {
+        Bool(bool),
+        String(String),
+    }
+
+    Ok(match Flag::deserialize(deserializer)? {
+        Flag::Bool(flag) => flag,
+        Flag::String(flag) => flag == "true",
+    })
+}
+
+#[derive(Debug, Serialize)]
+pub struct OidcProviderInfo {
+    pub name: String,
+    #[serde(rename = "displayName")]
+    pub display_name: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OidcAuthorization {
+    #[serde(rename = "authorizationUrl")]
+    pub authorization_url: String,
+    pub state: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OidcAuthorizePayload {
+    pub provider: String,
+    #[serde(default)]
+    pub remember: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OidcCallbackPayload {
+    pub code: String,
+    pub state: String,
+}
+
+/// Identity asserted by a validated ID token.
+#[derive(Debug)]
+pub struct VerifiedIdentity {
+    pub provider: String,
+    pub subject: String,
+    pub email: Option<String>,
+    pub email_verified: bool,
+    pub name: Option<String>,
+    pub picture: Option<String>,
+    /// The login was started by this user to link a new identity.
+    pub link_user_id: Option<Uuid>,
+    pub remember: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct IdentityInfo {
+    #[serde(rename = "identityId")]
+    pub identity_id: Uuid,
+    pub provider: String,
+    pub email: Option<String>,
+    #[serde(rename = "lastLoginAt")]
+    pub last_login_at: Option<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+}
+
+impl From<user_identities::Model> for IdentityInfo {
+    fn from(identity: user_identities::Model) -> Self {
+        let format = |date: chrono::NaiveDateTime| date.format("%Y-%m-%d %H:%M:%S").to_string();
+        Self {
+            identity_id: identity.identity_id,
+            provider: identity.provider,
+            email: identity.email,
+            last_login_at: identity.last_login_at.map(format),
+            created_at: format(identity.created_at),
+        }
+    }
+}
+
+pub struct OidcService {
+    pub db: Arc<DatabaseConnection>,
+    providers: Vec<OidcProviderConfig>,
+    /// Discovery documents and key sets, fetched on first use.
+    metadata: Mutex<HashMap<String, Arc<ProviderMetadata>>>,
+    client: reqwest::Client,
+}
+
+impl OidcService {
+    pub fn new(db: Arc<DatabaseConnection>, providers: Vec<OidcProviderConfig>) -> Self {
+        Self {
+            db,
+            providers,
+            metadata: Mutex::new(HashMap::new()),
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(10))
+                .build()
+                .unwrap(),
+        }
+    }
+
+    pub fn providers(&self) -> Vec<OidcProviderInfo> {
+        self.providers
+            .iter()
+            .map(|provider| OidcProviderInfo {
+                name: provider.name.clone(),
+                display_name: provider
+                    .display_name
+                    .clone()
+                    .unwrap_or_else(|| provider.name.clone()),
+            })
+            .collect()
+    }
+
+    /// Start a login, or link an identity to `user_id` when given.
+    pub async fn authorize(
+        &self,
+        payload: OidcAuthorizePayload,
+        user_id: Option<Uuid>,
+    ) -> DbResult<OidcAuthorization> {
+        let provider = self.provider(&payload.provider)?;
+        let metadata = self.metadata(provider, false).await?;
+
+        let state = generator_random_token(32);
+        let nonce = generator_random_token(16);
+        let code_verifier = generator_random_token(32);
+        let code_challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes()));
+
+        let now = chrono::Utc::now().naive_utc();
+        OidcStates::delete_many()
+            .filter(oidc_states::Column::ExpiresAt.lt(now))
+            .exec(self.db.as_ref())
+            .await?;
+
+        oidc_states::ActiveModel {
+            state_hash: Set(hash_token(&state)),
+            provider: Set(provider.name.clone()),
+            code_verifier: Set(code_verifier),
+            nonce: Set(nonce.clone()),
+            user_id: Set(user_id),
+            remember: Set(payload.remember),
+            expires_at: Set(now + Duration::from_secs(STATE_EXPIRE)),
+            ..Default::default()
+        }
+        .insert(self.db.as_ref())
+        .await?;
+
+        let authorization_url = Url::parse_with_params(
+            &metadata.discovery.authorization_endpoint,
+            [
+                ("response_type", "code"),
+                ("client_id", &provider.client_id),
+                ("redirect_uri", &provider.redirect_uri),
+                ("scope", &provider.scopes.join(" ")),
+                ("state", &state),
+                ("nonce", &nonce),
+                ("code_challenge", &code_challenge),
+                ("code_challenge_method", "S256"),
+            ],
+        )
+        .map_err(|e| DbErr::Custom(format!("Invalid authorization endpoint: {}", e)))?;
+
+        Ok(OidcAuthorization {
+            authorization_url: authorization_url.to_string(),
+            state,
+        })
+    }
+
+    /// Finish a login: redeem the code and validate the returned ID token.
+    pub async fn exchange(&self, payload: OidcCallbackPayload) -> DbResult<VerifiedIdentity> {
+        let state = self.consume_state(&payload.state).await?;
+        let provider = self.provider(&state.provider)?;
+        let metadata = self.metadata(provider, false).await?;
+
+        let mut request = self.client.post(&metadata.discovery.token_endpoint).form(&[
+            ("grant_type", "authorization_code"),
+            ("code", &payload.code),
+            ("redirect_uri", &provider.redirect_uri),
+            ("client_id", &provider.client_id),
+            ("code_verifier", &state.code_verifier),
+        ]);
+        if let Some(secret) = &provider.client_secret {
+            request = request.basic_auth(&provider.client_id, Some(secret));
+        }
+
+        let response = request.send().await.map_err(Self::request_error)?;
+        if !response.status().is_success() {
+            log::warn!(
+                "OIDC token request to `{}` failed: {}",
+                provider.name,
+                response.text().await.unwrap_or_default()
+            );
+            return Err(DbErr::Custom(
+                "The login provider refused the authorization code".to_string(),
+            ));
+        }
+        let tokens: TokenResponse = response.json().await.map_err(Self::request_error)?;
+
+        let claims = self.validate_id_token(provider, &tokens.id_token).await?;
+        if claims.nonce.as_deref() != Some(state.nonce.as_str()) {
+            return Err(DbErr::Custom("Invalid ID token nonce".to_string()));
+        }
+
+        Ok(VerifiedIdentity {
+            provider: provider.name.clone(),
+            subject: claims.sub,
+            email: claims.email,
+            email_verified: claims.email_verified,
+            name: claims.name,
+            picture: claims.picture,
+            link_user_id: state.user_id,
+            remember: state.remember,
+        })
+    }
+
+    pub async fn find_identity(
+        &self,
+        provider: &str,
+        subject: &str,
+    ) -> DbResult<Option<user_identities::Model>> {
+        UserIdentities::find()
+            .filter(user_identities::Column::Provider.eq(provider))
+            .filter(user_identities::Column::Subject.eq(subject))
+            .one(self.db.as_ref())
+            .await
+    }
+
+    pub async fn find_identities_by_user(&self, user_id: Uuid) -> DbResult<Vec<IdentityInfo>> {
+        let identities = UserIdentities::find()
+            .filter(user_identities::Column::UserId.eq(user_id))
+            .order_by_asc(user_identities::Column::CreatedAt)
+            .all(self.db.as_ref())
+            .await?;
+
+        Ok(identities.into_iter().map(IdentityInfo::from).collect())
+    }
+
+    pub async fn link_identity(
+        &self,
+        user_id: Uuid,
+        identity: &VerifiedIdentity,
+    ) -> DbResult<user_identities::Model> {
+        user_identities::ActiveModel {
+            user_id: Set(user_id),
+            provider: Set(identity.provider.clone()),
+            subject: Set(identity.subject.clone()),
+            email: Set(identity.email.clone()),
+            last_login_at: Set(Some(chrono::Utc::now().naive_utc())),
+            ..Default::default()
+        }
+        .insert(self.db.as_ref())
+        .await
+    }
+
+    pub async fn touch_identity(&self, identity_id: Uuid, email: Option<String>) -> DbResult<()> {
+        UserIdentities::update_many()
+            .col_expr(
+                user_identities::Column::LastLoginAt,
+                Expr::value(chrono::Utc::now().naive_utc()),
+            )
+            .col_expr(user_identities::Column::Email, Expr::value(email))
+            .filter(user_identities::Column::IdentityId.eq(identity_id))
+            .exec(self.db.as_ref())
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn unlink_identity(&self, user_id: Uuid, identity_id: Uuid) -> DbResult<()> {
+        let res = UserIdentities::delete_many()
+            .filter(user_identities::Column::IdentityId.eq(identity_id))
+            .filter(user_identities::Column::UserId.eq(user_id))
+            .exec(self.db.as_ref())
+            .await?;
+
+        if res.rows_affected == 0 {
+            return Err(DbErr::Custom("Cannot find this login".to_string()));
+        }
+
+        Ok(())
+    }
+
+    pub async fn unlink_identities_by_user<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        user_id: Uuid,
+    ) -> DbResult<()> {
+        UserIdentities::delete_many()
+            .filter(user_identities::Column::UserId.eq(user_id))
+            .exec(db)
+            .await?;
+
+        Ok(())
+    }
+
+    fn provider(&self, name: &str) -> DbResult<&OidcProviderConfig> {
+        self.providers
+            .iter()
+            .find(|provider| provider.name == name)
+            .ok_or(DbErr::Custom(format!("Unknown login provider `{}`", name)))
+    }
+
+    async fn consume_state(&self, state: &str) -> DbResult<oidc_states::Model> {
+        let invalid = || DbErr::Custom("Invalid or expired login state".to_string());
+        let state = OidcStates::find_by_id(hash_token(state))
+            .one(self.db.as_ref())
+            .await?
+            .ok_or_else(invalid)?;
+
+        // Only the callback whose delete went through may use the state.
+        let res = OidcStates::delete_by_id(state.state_hash.clone())
+            .exec(self.db.as_ref())
+            .await?;
+        if res.rows_affected == 0 || state.expires_at <= chrono::Utc::now().naive_utc() {
+            return Err(invalid());
+        }
+
+        Ok(state)
+    }
+
+    async fn metadata(
+        &self,
+        provider: &OidcProviderConfig,
+        refresh: bool,
+    ) -> DbResult<Arc<ProviderMetadata>> {
+        if !refresh {
+            if let Some(metadata) = self.metadata.lock().unwrap().get(&provider.name) {
+                return Ok(metadata.clone());
+            }
+        }
+
+        let discovery_url = format!(
+            "{}/.well-known/openid-configuration",
+            provider.issuer.trim_end_matches('/')
+        );
+        let discovery: Discovery = self.get_json(&discovery_url).await?;
+        if discovery.issuer.trim_end_matches('/') != provider.issuer.trim_end_matches('/') {
+            return Err(DbErr::Custom(format!(
+                "Login provider `{}` reports issuer `{}`",
+                provider.name, discovery.issuer
+            )));
+        }
+        let jwks: JwkSet = self.get_json(&discovery.jwks_uri).await?;
+
+        let metadata = Arc::new(ProviderMetadata { discovery, jwks });
+        self.metadata
+            .lock()
+            .unwrap()
+            .insert(provider.name.clone(), metadata.clone());
+
+        Ok(metadata)
+    }
+
+    async fn validate_id_token(
+        &self,
+        provider: &OidcProviderConfig,
+        id_token: &str,
+    ) -> DbResult<IdTokenClaims> {
+        let invalid = |reason: &str| DbErr::Custom(format!("Invalid ID token: {}", reason));
+        let header = decode_header(id_token).map_err(|e| invalid(&e.to_string()))?;
+        if !ID_TOKEN_ALGORITHMS.contains(&header.alg) {
+            return Err(invalid("unsupported signing algorithm"));
+        }
+
+        // Providers rotate their keys, an unknown `kid` warrants one refetch.
+        let mut metadata = self.metadata(provider, false).await?;
+        let mut refreshed = false;
+        let key = loop {
+            let jwk = match &header.kid {
+                Some(kid) => metadata.jwks.find(kid),
+                None if metadata.jwks.keys.len() == 1 => metadata.jwks.keys.first(),
+                None => None,
+            };
+            match jwk {
+                Some(jwk) => {
+                    break DecodingKey::from_jwk(jwk).map_err(|e| invalid(&e.to_string()))?
+                }
+                None if !refreshed => {
+                    metadata = self.metadata(provider, true).await?;
+                    refreshed = true;
+                }
+                None => return Err(invalid("unknown signing key")),
+            }
+        };
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_issuer(&[&metadata.discovery.issuer]);
+        validation.set_audience(&[&provider.client_id]);
+        validation.set_required_spec_claims(&["sub", "iss", "aud", "exp"]);
+
+        decode::<IdTokenClaims>(id_token, &key, &validation)
+            .map(|data| data.claims)
+            .map_err(|e| invalid(&e.to_string()))
+    }
+
+    async fn get_json<T: serde::de::DeserializeOwned>(&self, url: &str) -> DbResult<T> {
+        self.client
+            .get(url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(Self::request_error)?
+            .json()
+            .await
+            .map_err(Self::request_error)
+    }
+
+    fn request_error(e: reqwest::Error) -> DbErr {
+        log::error!("OIDC provider request error: {:#?}", e);
+        DbErr::Custom("Cannot reach the login provider".to_string())
+    }
+}
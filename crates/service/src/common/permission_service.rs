@@ -0,0 +1,412 @@
+use std::sync::Arc;
+
+use crate::_entities::prelude::{TeamRoles, TeamUsers};
+use crate::_entities::sea_orm_active_enums::{Actions, TargetTypes, TeamUserRoles};
+use crate::_entities::{team_roles, team_users};
+use crate::common::{activity_log_service::ActivityLogService, team_user_service::TeamUserService};
+use crate::permission::Permission;
+use crate::utils::DbResult;
+use sea_orm::prelude::{Json, Uuid};
+use sea_orm::sea_query::Expr;
+use sea_orm::sqlx::types::chrono;
+use sea_orm::*;
+use serde::{Deserialize, Serialize};
+
+const MAX_ROLE_NAME_LENGTH: usize = 50;
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TeamRoleDto {
+    pub name: String,
+    pub description: Option<String>,
+    pub permissions: Vec<Permission>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AssignCustomRoleDto {
+    /// `None` puts the member back on the permissions of their built-in role.
+    #[serde(rename = "roleId")]
+    pub role_id: Option<Uuid>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TeamRoleInfo {
+    #[serde(rename = "roleId")]
+    pub role_id: Uuid,
+    #[serde(rename = "teamId")]
+    pub team_id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub permissions: Vec<Permission>,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: String,
+}
+
+impl TeamRoleInfo {
+    fn new(role: team_roles::Model) -> Self {
+        let format = |date: chrono::NaiveDateTime| date.format("%Y-%m-%d %H:%M:%S").to_string();
+        Self {
+            role_id: role.role_id,
+            team_id: role.team_id,
+            permissions: parse_permissions(&role.permissions),
+            name: role.name,
+            description: role.description,
+            created_at: format(role.created_at),
+            updated_at: format(role.updated_at),
+        }
+    }
+}
+
+/// Names that no longer match a [`Permission`] are dropped rather than failing
+/// the whole role.
+fn parse_permissions(value: &Json) -> Vec<Permission> {
+    value
+        .as_array()
+        .map(|names| {
+            names
+                .iter()
+                .filter_map(|name| name.as_str()?.parse().ok())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Decides what a member may do in a team, from their built-in role or the
+/// custom role the team gave them, and manages those custom roles.
+pub struct PermissionService {
+    pub db: Arc<DatabaseConnection>,
+    pub team_user_service: Arc<TeamUserService>,
+    pub activity_log_service: Arc<ActivityLogService>,
+}
+
+impl PermissionService {
+    pub fn new(
+        db: Arc<DatabaseConnection>,
+        team_user_service: Arc<TeamUserService>,
+        activity_log_service: Arc<ActivityLogService>,
+    ) -> Self {
+        Self {
+            db,
+            team_user_service,
+            activity_log_service,
+        }
+    }
+
+    /// Whether the user holds `permission` in the team. Fails when the user
+    /// is not a member at all.
+    pub async fn authorize(
+        &self,
+        user_id: Uuid,
+        team_id: Uuid,
+        permission: Permission,
+    ) -> DbResult<bool> {
+        Ok(self
+            .find_permissions(user_id, team_id)
+            .await?
+            .contains(&permission))
+    }
+
+    pub async fn find_permissions(
+        &self,
+        user_id: Uuid,
+        team_id: Uuid,
+    ) -> DbResult<Vec<Permission>> {
+        let member = self
+            .team_user_service
+            .find_joined_member(team_id, user_id)
+            .await?;
+        self.permissions_of(&member).await
+    }
+
+    pub async fn find_roles(&self, team_id: Uuid) -> DbResult<Vec<TeamRoleInfo>> {
+        Ok(TeamRoles::find()
+            .filter(team_roles::Column::TeamId.eq(team_id))
+            .order_by_asc(team_roles::Column::Name)
+            .all(self.db.as_ref())
+            .await?
+            .into_iter()
+            .map(TeamRoleInfo::new)
+            .collect())
+    }
+
+    pub async fn create_role(
+        &self,
+        team_id: Uuid,
+        actor_id: Uuid,
+        payload: TeamRoleDto,
+    ) -> DbResult<TeamRoleInfo> {
+        let (name, permissions) = self.validate(team_id, actor_id, None, &payload).await?;
+
+        let role = team_roles::ActiveModel {
+            team_id: Set(team_id),
+            name: Set(name),
+            description: Set(payload.description),
+            permissions: Set(permissions_to_json(&permissions)),
+            ..Default::default()
+        }
+        .insert(self.db.as_ref())
+        .await?;
+
+        self.activity_log_service
+            .record_changes(
+                actor_id,
+                Actions::Created,
+                TargetTypes::Role,
+                team_id,
+                &format!(
+                    "Created custom role `{}` with {}",
+                    role.name,
+                    describe(&permissions)
+                ),
+                ActivityLogService::changes(None, Some(&role)),
+            )
+            .await?;
+
+        Ok(TeamRoleInfo::new(role))
+    }
+
+    pub async fn update_role(
+        &self,
+        team_id: Uuid,
+        role_id: Uuid,
+        actor_id: Uuid,
+        payload: TeamRoleDto,
+    ) -> DbResult<TeamRoleInfo> {
+        let before = self.find_role(team_id, role_id).await?;
+        // Editing a role could otherwise take away more than the editor holds.
+        self.ensure_holds(team_id, actor_id, &parse_permissions(&before.permissions))
+            .await?;
+        let (name, permissions) = self
+            .validate(team_id, actor_id, Some(role_id), &payload)
+            .await?;
+
+        let mut role = before.clone().into_active_model();
+        role.name = Set(name);
+        role.description = Set(payload.description);
+        role.permissions = Set(permissions_to_json(&permissions));
+        role.updated_at = Set(chrono::Utc::now().naive_utc());
+        let role = role.update(self.db.as_ref()).await?;
+
+        self.activity_log_service
+            .record_changes(
+                actor_id,
+                Actions::Updated,
+                TargetTypes::Role,
+                team_id,
+                &format!(
+                    "Updated custom role `{}` to {}",
+                    role.name,
+                    describe(&permissions)
+                ),
+                ActivityLogService::changes(Some(&before), Some(&role)),
+            )
+            .await?;
+
+        Ok(TeamRoleInfo::new(role))
+    }
+
+    /// Members holding the role fall back to their built-in role.
+    pub async fn delete_role(&self, team_id: Uuid, role_id: Uuid, actor_id: Uuid) -> DbResult<()> {
+        let role = self.find_role(team_id, role_id).await?;
+        self.ensure_holds(team_id, actor_id, &parse_permissions(&role.permissions))
+            .await?;
+
+        let txn = self.db.begin().await?;
+        TeamUsers::update_many()
+            .col_expr(
+                team_users::Column::CustomRoleId,
+                Expr::value(Option::<Uuid>::None),
+            )
+            .filter(team_users::Column::TeamId.eq(team_id))
+            .filter(team_users::Column::CustomRoleId.eq(role_id))
+            .exec(&txn)
+            .await?;
+        TeamRoles::delete_by_id(role_id).exec(&txn).await?;
+        txn.commit().await?;
+
+        self.activity_log_service
+            .record_changes(
+                actor_id,
+                Actions::Removed,
+                TargetTypes::Role,
+                team_id,
+                &format!("Deleted custom role `{}`", role.name),
+                ActivityLogService::changes(Some(&role), None),
+            )
+            .await
+    }
+
+    pub async fn assign_role(
+        &self,
+        team_id: Uuid,
+        actor_id: Uuid,
+        member_id: Uuid,
+        payload: AssignCustomRoleDto,
+    ) -> DbResult<()> {
+        if actor_id == member_id {
+            return Err(DbErr::Custom("Cannot change your own role".to_string()));
+        }
+
+        let actor = self
+            .team_user_service
+            .find_joined_member(team_id, actor_id)
+            .await?;
+        let member = self
+            .team_user_service
+            .find_joined_member(team_id, member_id)
+            .await?;
+        if member.role == TeamUserRoles::Owner {
+            return Err(DbErr::Custom(
+                "Owners always hold every permission".to_string(),
+            ));
+        }
+        if !actor.role.can_manage_member(&member.role) {
+            return Err(DbErr::Custom(format!(
+                "{} cannot change the role of {}",
+                actor.role.to_value(),
+                member.role.to_value()
+            )));
+        }
+
+        let role_name = match payload.role_id {
+            Some(role_id) => {
+                let role = self.find_role(team_id, role_id).await?;
+                self.ensure_holds(team_id, actor_id, &parse_permissions(&role.permissions))
+                    .await?;
+                Some(role.name)
+            }
+            None => None,
+        };
+
+        let mut model = member.clone().into_active_model();
+        model.custom_role_id = Set(payload.role_id);
+        let updated = model.update(self.db.as_ref()).await?;
+
+        self.activity_log_service
+            .record_changes(
+                actor_id,
+                Actions::Updated,
+                TargetTypes::Role,
+                team_id,
+                &match role_name {
+                    Some(name) => format!("Gave user {} the custom role `{}`", member_id, name),
+                    None => format!("Removed the custom role of user {}", member_id),
+                },
+                ActivityLogService::changes(Some(&member), Some(&updated)),
+            )
+            .await
+    }
+
+    pub async fn permissions_of(&self, member: &team_users::Model) -> DbResult<Vec<Permission>> {
+        // Custom roles never apply to Owners, who keep every permission.
+        match member.custom_role_id {
+            Some(role_id) if member.role != TeamUserRoles::Owner => {
+                let role = TeamRoles::find_by_id(role_id).one(self.db.as_ref()).await?;
+                let mut permissions = role
+                    .map(|role| parse_permissions(&role.permissions))
+                    .unwrap_or_default();
+                // Every member sees the team, whatever the role grants.
+                if !permissions.contains(&Permission::TeamView) {
+                    permissions.push(Permission::TeamView);
+                }
+                Ok(permissions)
+            }
+            _ => Ok(member.role.permissions().to_vec()),
+        }
+    }
+
+    /// Checks a role payload, returning its trimmed name and deduplicated
+    /// permissions.
+    async fn validate(
+        &self,
+        team_id: Uuid,
+        actor_id: Uuid,
+        role_id: Option<Uuid>,
+        payload: &TeamRoleDto,
+    ) -> DbResult<(String, Vec<Permission>)> {
+        let name = payload.name.trim().to_string();
+        if name.is_empty() {
+            return Err(DbErr::Custom("name: cannot be empty".to_string()));
+        }
+        if name.chars().count() > MAX_ROLE_NAME_LENGTH {
+            return Err(DbErr::Custom(format!(
+                "name: must be at most {} characters long",
+                MAX_ROLE_NAME_LENGTH
+            )));
+        }
+        if let Some(permission) = payload.permissions.iter().find(|p| p.is_owner_only()) {
+            return Err(DbErr::Custom(format!(
+                "`{}` is kept to Owners and cannot be part of a custom role",
+                permission
+            )));
+        }
+
+        let mut permissions = Vec::new();
+        for permission in &payload.permissions {
+            if !permissions.contains(permission) {
+                permissions.push(*permission);
+            }
+        }
+        self.ensure_holds(team_id, actor_id, &permissions).await?;
+
+        let mut duplicate = TeamRoles::find()
+            .filter(team_roles::Column::TeamId.eq(team_id))
+            .filter(team_roles::Column::Name.eq(&name));
+        if let Some(role_id) = role_id {
+            duplicate = duplicate.filter(team_roles::Column::RoleId.ne(role_id));
+        }
+        if duplicate.count(self.db.as_ref()).await? > 0 {
+            return Err(DbErr::Custom(
+                "A role with this name already exists".to_string(),
+            ));
+        }
+
+        Ok((name, permissions))
+    }
+
+    /// Nobody hands out or takes away permissions they don't hold themselves.
+    async fn ensure_holds(
+        &self,
+        team_id: Uuid,
+        actor_id: Uuid,
+        permissions: &[Permission],
+    ) -> DbResult<()> {
+        let held = self.find_permissions(actor_id, team_id).await?;
+        match permissions.iter().find(|p| !held.contains(p)) {
+            Some(permission) => Err(DbErr::Custom(format!(
+                "You cannot grant `{}` without holding it",
+                permission
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    async fn find_role(&self, team_id: Uuid, role_id: Uuid) -> DbResult<team_roles::Model> {
+        TeamRoles::find_by_id(role_id)
+            .filter(team_roles::Column::TeamId.eq(team_id))
+            .one(self.db.as_ref())
+            .await?
+            .ok_or(DbErr::Custom("Cannot find role".to_string()))
+    }
+}
+
+fn permissions_to_json(permissions: &[Permission]) -> Json {
+    Json::Array(
+        permissions
+            .iter()
+            .map(|permission| Json::String(permission.to_string()))
+            .collect(),
+    )
+}
+
+fn describe(permissions: &[Permission]) -> String {
+    if permissions.is_empty() {
+        return "no permissions".to_string();
+    }
+    permissions
+        .iter()
+        .map(|permission| format!("`{}`", permission))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
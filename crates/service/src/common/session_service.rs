@@ -0,0 +1,337 @@
+use std::{env::var, sync::Arc, time::Duration};
+
+use crate::_entities::prelude::{RotatedRefreshTokens, Sessions};
+use crate::_entities::{rotated_refresh_tokens, sessions};
+use crate::utils::encrypt::{generator_random_token, hash_token};
+use crate::utils::DbResult;
+use sea_orm::prelude::Uuid;
+use sea_orm::sea_query::Expr;
+use sea_orm::sqlx::types::chrono;
+use sea_orm::*;
+use serde::{Deserialize, Serialize};
+
+/// Refresh token lifetime without `remember`, in milliseconds.
+const DEFAULT_REFRESH_EXPIRE: i64 = 1000 * 60 * 60 * 24;
+/// Refresh token lifetime with `remember`, in milliseconds.
+const DEFAULT_REMEMBER_EXPIRE: i64 = 1000 * 60 * 60 * 24 * 30;
+/// `last_seen_at` is only written when older than this.
+const LAST_SEEN_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Where a login came from, shown in the session list.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ClientInfo {
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct IssuedSession {
+    pub session_id: Uuid,
+    pub user_id: Uuid,
+    /// Opaque `<session_id>.<secret>` token, only ever returned once.
+    pub refresh_token: String,
+    pub refresh_expire: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SessionInfo {
+    #[serde(rename = "sessionId")]
+    pub session_id: Uuid,
+    #[serde(rename = "userAgent")]
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    /// The session the listing request was made with.
+    pub current: bool,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    #[serde(rename = "lastSeenAt")]
+    pub last_seen_at: String,
+    #[serde(rename = "expiresAt")]
+    pub expires_at: String,
+}
+
+impl SessionInfo {
+    fn new(session: sessions::Model, current: Option<Uuid>) -> Self {
+        let format = |date: chrono::NaiveDateTime| date.format("%Y-%m-%d %H:%M:%S").to_string();
+        Self {
+            session_id: session.session_id,
+            user_agent: session.user_agent,
+            ip: session.ip,
+            current: current == Some(session.session_id),
+            created_at: format(session.created_at),
+            last_seen_at: format(session.last_seen_at),
+            expires_at: format(session.expires_at),
+        }
+    }
+}
+
+pub struct SessionService {
+    pub db: Arc<DatabaseConnection>,
+}
+
+impl SessionService {
+    pub fn new(db: Arc<DatabaseConnection>) -> Self {
+        Self { db }
+    }
+
+    pub fn refresh_expire(remember: bool) -> i64 {
+        let (key, default) = if remember {
+            ("JWT_REMEMBER_EXPIRE", DEFAULT_REMEMBER_EXPIRE)
+        } else {
+            ("JWT_REFRESH_EXPIRE", DEFAULT_REFRESH_EXPIRE)
+        };
+
+        var(key)
+            .ok()
+            .and_then(|v| v.parse::<i64>().ok())
+            .unwrap_or(default)
+    }
+
+    pub async fn create_session(
+        &self,
+        user_id: Uuid,
+        remember: bool,
+        client: ClientInfo,
+    ) -> DbResult<IssuedSession> {
+        let secret = generator_random_token(32);
+        let refresh_expire = Self::refresh_expire(remember);
+
+        let session = sessions::ActiveModel {
+            user_id: Set(user_id),
+            refresh_token_hash: Set(hash_token(&secret)),
+            remember: Set(remember),
+            expires_at: Set(Self::expires_at(refresh_expire)),
+            user_agent: Set(client.user_agent),
+            ip: Set(client.ip),
+            ..Default::default()
+        }
+        .insert(self.db.as_ref())
+        .await?;
+
+        Ok(IssuedSession {
+            session_id: session.session_id,
+            user_id,
+            refresh_token: format!("{}.{}", session.session_id.simple(), secret),
+            refresh_expire,
+        })
+    }
+
+    /// Exchange a refresh token for a new one.
+    ///
+    /// A session only ever has one valid refresh token. Presenting a token the
+    /// session already exchanged means it was replayed, so the whole session is
+    /// revoked and every token derived from it stops working. Secrets the
+    /// session never issued are only rejected, they prove nothing.
+    pub async fn rotate_session(&self, refresh_token: &str) -> DbResult<IssuedSession> {
+        let (session_id, secret) = Self::parse_refresh_token(refresh_token)?;
+
+        let session = Sessions::find_by_id(session_id)
+            .one(self.db.as_ref())
+            .await?
+            .ok_or(DbErr::Custom("Invalid refresh token".to_string()))?;
+
+        if session.revoked_at.is_some() {
+            return Err(DbErr::Custom("Session has been revoked".to_string()));
+        }
+
+        if session.expires_at <= chrono::Utc::now().naive_utc() {
+            return Err(DbErr::Custom("Refresh token has expired".to_string()));
+        }
+
+        let secret_hash = hash_token(&secret);
+        if secret_hash != session.refresh_token_hash {
+            let rotated = RotatedRefreshTokens::find_by_id(secret_hash)
+                .filter(rotated_refresh_tokens::Column::SessionId.eq(session_id))
+                .one(self.db.as_ref())
+                .await?;
+            return match rotated {
+                Some(_) => self.revoke_replayed_session(session_id).await,
+                None => Err(DbErr::Custom("Invalid refresh token".to_string())),
+            };
+        }
+
+        let new_secret = generator_random_token(32);
+        let refresh_expire = Self::refresh_expire(session.remember);
+
+        // Compare-and-swap on the current hash so two concurrent refreshes with
+        // the same token cannot both succeed.
+        let txn = self.db.begin().await?;
+        let res = Sessions::update_many()
+            .col_expr(
+                sessions::Column::RefreshTokenHash,
+                Expr::value(hash_token(&new_secret)),
+            )
+            .col_expr(
+                sessions::Column::ExpiresAt,
+                Expr::value(Self::expires_at(refresh_expire)),
+            )
+            .col_expr(
+                sessions::Column::UpdatedAt,
+                Expr::value(chrono::Utc::now().naive_utc()),
+            )
+            .col_expr(
+                sessions::Column::LastSeenAt,
+                Expr::value(chrono::Utc::now().naive_utc()),
+            )
+            .filter(sessions::Column::SessionId.eq(session_id))
+            .filter(sessions::Column::RefreshTokenHash.eq(secret_hash.clone()))
+            .filter(sessions::Column::RevokedAt.is_null())
+            .exec(&txn)
+            .await?;
+
+        if res.rows_affected == 0 {
+            txn.rollback().await?;
+            return self.revoke_replayed_session(session_id).await;
+        }
+
+        rotated_refresh_tokens::ActiveModel {
+            token_hash: Set(secret_hash),
+            session_id: Set(session_id),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await?;
+        txn.commit().await?;
+
+        Ok(IssuedSession {
+            session_id,
+            user_id: session.user_id,
+            refresh_token: format!("{}.{}", session_id.simple(), new_secret),
+            refresh_expire,
+        })
+    }
+
+    async fn revoke_replayed_session(&self, session_id: Uuid) -> DbResult<IssuedSession> {
+        log::warn!(
+            "Refresh token reuse detected for session {}, revoking session",
+            session_id
+        );
+        self.revoke_session(session_id).await?;
+        Err(DbErr::Custom(
+            "Refresh token has already been used, please login again".to_string(),
+        ))
+    }
+
+    /// Whether the user logged in with this user agent before. Accounts without
+    /// any earlier session count as known, there is nothing to compare with.
+    pub async fn is_known_device(&self, user_id: Uuid, client: &ClientInfo) -> DbResult<bool> {
+        let sessions = Sessions::find()
+            .filter(sessions::Column::UserId.eq(user_id))
+            .all(self.db.as_ref())
+            .await?;
+
+        Ok(sessions.is_empty()
+            || sessions
+                .iter()
+                .any(|session| session.user_agent == client.user_agent))
+    }
+
+    /// Active sessions of the user, most recently used first.
+    pub async fn find_sessions_by_user(
+        &self,
+        user_id: Uuid,
+        current: Option<Uuid>,
+    ) -> DbResult<Vec<SessionInfo>> {
+        let sessions = Sessions::find()
+            .filter(sessions::Column::UserId.eq(user_id))
+            .filter(sessions::Column::RevokedAt.is_null())
+            .filter(sessions::Column::ExpiresAt.gt(chrono::Utc::now().naive_utc()))
+            .order_by_desc(sessions::Column::LastSeenAt)
+            .all(self.db.as_ref())
+            .await?;
+
+        Ok(sessions
+            .into_iter()
+            .map(|session| SessionInfo::new(session, current))
+            .collect())
+    }
+
+    /// Record a session as seen. Revocation is checked along with the account
+    /// status, see `UserService::ensure_active_session`.
+    pub async fn touch_session(&self, session_id: Uuid) -> DbResult<()> {
+        let now = chrono::Utc::now().naive_utc();
+        Sessions::update_many()
+            .col_expr(sessions::Column::LastSeenAt, Expr::value(now))
+            .filter(sessions::Column::SessionId.eq(session_id))
+            .filter(sessions::Column::LastSeenAt.lt(now - LAST_SEEN_INTERVAL))
+            .exec(self.db.as_ref())
+            .await?;
+
+        Ok(())
+    }
+
+    /// Revoke one session of the user, refusing sessions of anyone else.
+    pub async fn revoke_user_session(&self, user_id: Uuid, session_id: Uuid) -> DbResult<()> {
+        let res = Sessions::update_many()
+            .col_expr(
+                sessions::Column::RevokedAt,
+                Expr::value(chrono::Utc::now().naive_utc()),
+            )
+            .filter(sessions::Column::SessionId.eq(session_id))
+            .filter(sessions::Column::UserId.eq(user_id))
+            .filter(sessions::Column::RevokedAt.is_null())
+            .exec(self.db.as_ref())
+            .await?;
+
+        if res.rows_affected == 0 {
+            return Err(DbErr::Custom("Cannot find this session".to_string()));
+        }
+
+        Ok(())
+    }
+
+    /// Revoke every session of the user except `keep`.
+    pub async fn revoke_other_sessions(&self, user_id: Uuid, keep: Uuid) -> DbResult<u64> {
+        let res = Sessions::update_many()
+            .col_expr(
+                sessions::Column::RevokedAt,
+                Expr::value(chrono::Utc::now().naive_utc()),
+            )
+            .filter(sessions::Column::UserId.eq(user_id))
+            .filter(sessions::Column::SessionId.ne(keep))
+            .filter(sessions::Column::RevokedAt.is_null())
+            .exec(self.db.as_ref())
+            .await?;
+
+        Ok(res.rows_affected)
+    }
+
+    pub async fn revoke_session(&self, session_id: Uuid) -> DbResult<()> {
+        Sessions::update_many()
+            .col_expr(
+                sessions::Column::RevokedAt,
+                Expr::value(chrono::Utc::now().naive_utc()),
+            )
+            .filter(sessions::Column::SessionId.eq(session_id))
+            .filter(sessions::Column::RevokedAt.is_null())
+            .exec(self.db.as_ref())
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn revoke_user_sessions(&self, user_id: Uuid) -> DbResult<()> {
+        Sessions::update_many()
+            .col_expr(
+                sessions::Column::RevokedAt,
+                Expr::value(chrono::Utc::now().naive_utc()),
+            )
+            .filter(sessions::Column::UserId.eq(user_id))
+            .filter(sessions::Column::RevokedAt.is_null())
+            .exec(self.db.as_ref())
+            .await?;
+
+        Ok(())
+    }
+
+    fn parse_refresh_token(refresh_token: &str) -> DbResult<(Uuid, String)> {
+        refresh_token
+            .split_once('.')
+            .and_then(|(id, secret)| Some((Uuid::parse_str(id).ok()?, secret.to_string())))
+            .ok_or(DbErr::Custom("Invalid refresh token".to_string()))
+    }
+
+    fn expires_at(expire: i64) -> chrono::NaiveDateTime {
+        chrono::Utc::now().naive_utc() + Duration::from_millis(expire.max(0) as u64)
+    }
+}
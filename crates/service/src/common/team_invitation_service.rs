@@ -0,0 +1,467 @@
+use std::{sync::Arc, time::Duration};
+
+use crate::_entities::prelude::{TeamInvitations, Teams};
+use crate::_entities::sea_orm_active_enums::{
+    Actions, InvitationStatus, NotificationTypes, TargetTypes, TeamUserRoles,
+};
+use crate::_entities::team_invitations;
+use crate::common::{
+    activity_log_service::ActivityLogService,
+    notification_service::NotificationService,
+    team_user_service::{JoinTeamDto, TeamUserService},
+    user_service::UserService,
+};
+use crate::mailer::{MailMessage, Mailer};
+use crate::utils::encrypt::{generator_random_token, hash_token};
+use crate::utils::DbResult;
+use sea_orm::prelude::Uuid;
+use sea_orm::sea_query::Expr;
+use sea_orm::sqlx::types::chrono;
+use sea_orm::*;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+/// How long an invite link stays valid, resending starts it over.
+const INVITATION_EXPIRE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+/// Minimum time between two mails of the same invite.
+const RESEND_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct InviteMemberPayload {
+    pub email: String,
+    pub role: TeamUserRoles,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct InvitationTokenPayload {
+    pub token: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct InvitationInfo {
+    #[serde(rename = "invitationId")]
+    pub invitation_id: Uuid,
+    #[serde(rename = "teamId")]
+    pub team_id: Uuid,
+    #[serde(rename = "teamName")]
+    pub team_name: String,
+    pub email: String,
+    pub role: TeamUserRoles,
+    pub status: String,
+    #[serde(rename = "invitedBy")]
+    pub invited_by: Uuid,
+    #[serde(rename = "expiresAt")]
+    pub expires_at: String,
+    #[serde(rename = "sentAt")]
+    pub sent_at: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+}
+
+impl InvitationInfo {
+    fn new(invitation: team_invitations::Model, team_name: String) -> Self {
+        let format = |date: chrono::NaiveDateTime| date.format("%Y-%m-%d %H:%M:%S").to_string();
+        Self {
+            invitation_id: invitation.invitation_id,
+            team_id: invitation.team_id,
+            team_name,
+            email: invitation.email,
+            role: invitation.role,
+            status: invitation.status.to_value(),
+            invited_by: invitation.invited_by,
+            expires_at: format(invitation.expires_at),
+            sent_at: format(invitation.sent_at),
+            created_at: format(invitation.created_at),
+        }
+    }
+}
+
+/// Invites to join a team, sent to an email address that may not have an
+/// account yet. Only a hash of the token in the link is stored.
+pub struct TeamInvitationService {
+    pub db: Arc<DatabaseConnection>,
+    /// Page the invite link points to, `token` is appended as query parameter.
+    pub invite_link: Option<String>,
+    pub mailer: Arc<dyn Mailer>,
+    pub user_service: Arc<UserService>,
+    pub team_user_service: Arc<TeamUserService>,
+    pub notification_service: Arc<NotificationService>,
+    pub activity_log_service: Arc<ActivityLogService>,
+}
+
+impl TeamInvitationService {
+    pub fn new(
+        db: Arc<DatabaseConnection>,
+        invite_link: Option<String>,
+        mailer: Arc<dyn Mailer>,
+        user_service: Arc<UserService>,
+        team_user_service: Arc<TeamUserService>,
+        notification_service: Arc<NotificationService>,
+        activity_log_service: Arc<ActivityLogService>,
+    ) -> Self {
+        Self {
+            db,
+            invite_link,
+            mailer,
+            user_service,
+            team_user_service,
+            notification_service,
+            activity_log_service,
+        }
+    }
+
+    pub async fn invite(
+        &self,
+        team_id: Uuid,
+        inviter_id: Uuid,
+        payload: InviteMemberPayload,
+    ) -> DbResult<InvitationInfo> {
+        let email = payload.email.trim().to_lowercase();
+        if email.is_empty() || !email.contains('@') {
+            return Err(DbErr::Custom("email: must be a valid address".to_string()));
+        }
+        if payload.role == TeamUserRoles::Owner {
+            return Err(DbErr::Custom(
+                "Nobody can be invited as Owner, transfer the ownership instead".to_string(),
+            ));
+        }
+        self.ensure_can_invite_as(team_id, inviter_id, &payload.role)
+            .await?;
+
+        if let Some(user) = self.user_service.find_user_by_email(&email).await? {
+            if self
+                .team_user_service
+                .check_user_in_team(user.user_id, team_id)
+                .await?
+            {
+                return Err(DbErr::Custom(
+                    "This user is already a member of the team".to_string(),
+                ));
+            }
+        }
+
+        let now = chrono::Utc::now().naive_utc();
+        let pending = TeamInvitations::find()
+            .filter(team_invitations::Column::TeamId.eq(team_id))
+            .filter(team_invitations::Column::Email.eq(&email))
+            .filter(team_invitations::Column::Status.eq(InvitationStatus::Pending))
+            .all(self.db.as_ref())
+            .await?;
+        if pending.iter().any(|invitation| invitation.expires_at > now) {
+            return Err(DbErr::Custom(
+                "This address already has a pending invitation, resend it instead".to_string(),
+            ));
+        }
+        // Expired invites are replaced by the new one.
+        TeamInvitations::update_many()
+            .col_expr(
+                team_invitations::Column::Status,
+                Expr::value(InvitationStatus::Revoked),
+            )
+            .filter(
+                team_invitations::Column::InvitationId
+                    .is_in(pending.iter().map(|invitation| invitation.invitation_id)),
+            )
+            .exec(self.db.as_ref())
+            .await?;
+
+        let token = generator_random_token(32);
+        let invitation = team_invitations::ActiveModel {
+            team_id: Set(team_id),
+            email: Set(email),
+            role: Set(payload.role),
+            token_hash: Set(hash_token(&token)),
+            invited_by: Set(inviter_id),
+            expires_at: Set(now + INVITATION_EXPIRE),
+            sent_at: Set(now),
+            created_at: Set(now),
+            ..Default::default()
+        }
+        .insert(self.db.as_ref())
+        .await?;
+
+        let team_name = self.team_name(team_id).await?;
+        self.send_invitation(&invitation, &team_name, &token)
+            .await?;
+        self.activity_log_service
+            .record_changes(
+                inviter_id,
+                Actions::Invited,
+                TargetTypes::Team,
+                team_id,
+                &format!(
+                    "Invited {} as {}",
+                    invitation.email,
+                    invitation.role.to_value()
+                ),
+                ActivityLogService::changes(None, Some(&invitation)),
+            )
+            .await?;
+
+        Ok(InvitationInfo::new(invitation, team_name))
+    }
+
+    pub async fn find_pending_by_team(&self, team_id: Uuid) -> DbResult<Vec<InvitationInfo>> {
+        let team_name = self.team_name(team_id).await?;
+        let invitations = TeamInvitations::find()
+            .filter(team_invitations::Column::TeamId.eq(team_id))
+            .filter(team_invitations::Column::Status.eq(InvitationStatus::Pending))
+            .order_by_desc(team_invitations::Column::CreatedAt)
+            .all(self.db.as_ref())
+            .await?;
+
+        Ok(invitations
+            .into_iter()
+            .map(|invitation| InvitationInfo::new(invitation, team_name.clone()))
+            .collect())
+    }
+
+    /// Mail the invite again with a fresh token and expiry, the old link stops working.
+    pub async fn resend(
+        &self,
+        team_id: Uuid,
+        invitation_id: Uuid,
+        actor_id: Uuid,
+    ) -> DbResult<InvitationInfo> {
+        let invitation = self.find_pending(team_id, invitation_id).await?;
+        self.ensure_can_invite_as(team_id, actor_id, &invitation.role)
+            .await?;
+        let now = chrono::Utc::now().naive_utc();
+        if invitation.sent_at + RESEND_INTERVAL > now {
+            return Err(DbErr::Custom(
+                "Please wait a minute before resending this invitation".to_string(),
+            ));
+        }
+
+        let token = generator_random_token(32);
+        let before = invitation.clone();
+        let mut invitation = invitation.into_active_model();
+        invitation.token_hash = Set(hash_token(&token));
+        invitation.expires_at = Set(now + INVITATION_EXPIRE);
+        invitation.sent_at = Set(now);
+        let invitation = invitation.update(self.db.as_ref()).await?;
+
+        let team_name = self.team_name(team_id).await?;
+        self.send_invitation(&invitation, &team_name, &token)
+            .await?;
+        self.activity_log_service
+            .record_changes(
+                actor_id,
+                Actions::Updated,
+                TargetTypes::Team,
+                team_id,
+                &format!("Resent the invitation of {}", invitation.email),
+                ActivityLogService::changes(Some(&before), Some(&invitation)),
+            )
+            .await?;
+
+        Ok(InvitationInfo::new(invitation, team_name))
+    }
+
+    pub async fn revoke(&self, team_id: Uuid, invitation_id: Uuid, actor_id: Uuid) -> DbResult<()> {
+        let before = self.find_pending(team_id, invitation_id).await?;
+        let invitation = self
+            .respond(before.clone(), InvitationStatus::Revoked)
+            .await?;
+
+        self.activity_log_service
+            .record_changes(
+                actor_id,
+                Actions::Removed,
+                TargetTypes::Team,
+                team_id,
+                &format!("Revoked the invitation of {}", invitation.email),
+                ActivityLogService::changes(Some(&before), Some(&invitation)),
+            )
+            .await
+    }
+
+    /// What the invite link leads to, for the page showing accept and decline.
+    pub async fn preview(&self, token: &str) -> DbResult<InvitationInfo> {
+        let invitation = self.find_valid(token).await?;
+        let team_name = self.team_name(invitation.team_id).await?;
+        Ok(InvitationInfo::new(invitation, team_name))
+    }
+
+    /// Join the team as the user the invite was sent to. Holding the token
+    /// proves access to the address, so it also counts as verifying it.
+    pub async fn accept(&self, token: &str, user_id: Uuid) -> DbResult<InvitationInfo> {
+        let invitation = self.find_valid(token).await?;
+        let user = self
+            .user_service
+            .find_user_by_id(user_id)
+            .await?
+            .ok_or(DbErr::Custom("Cannot find user".to_string()))?;
+        if user.email.to_lowercase() != invitation.email {
+            return Err(DbErr::Custom(
+                "This invitation was sent to another email address".to_string(),
+            ));
+        }
+        // The inviter may have been demoted or left since sending it.
+        self.ensure_can_invite_as(invitation.team_id, invitation.invited_by, &invitation.role)
+            .await
+            .map_err(|e| {
+                log::info!("Refused invitation {}: {}", invitation.invitation_id, e);
+                DbErr::Custom(
+                    "This invitation is no longer valid, ask the team for a new one".to_string(),
+                )
+            })?;
+
+        self.team_user_service
+            .join_team(JoinTeamDto {
+                team_id: invitation.team_id,
+                user_id,
+                role: invitation.role.clone(),
+            })
+            .await?;
+        if user.email_verified_at.is_none() {
+            self.user_service
+                .mark_email_verified(user_id, &user.email)
+                .await?;
+        }
+
+        let team_name = self.team_name(invitation.team_id).await?;
+        let invitation = self.respond(invitation, InvitationStatus::Accepted).await?;
+
+        Ok(InvitationInfo::new(invitation, team_name))
+    }
+
+    /// Turn the invite down, works without an account.
+    pub async fn decline(&self, token: &str) -> DbResult<()> {
+        let invitation = self.find_valid(token).await?;
+        self.respond(invitation, InvitationStatus::Declined).await?;
+        Ok(())
+    }
+
+    /// An invitation hands out no role its sender could not assign directly.
+    async fn ensure_can_invite_as(
+        &self,
+        team_id: Uuid,
+        inviter_id: Uuid,
+        role: &TeamUserRoles,
+    ) -> DbResult<()> {
+        let inviter = self
+            .team_user_service
+            .find_joined_member(team_id, inviter_id)
+            .await?;
+        if !inviter.role.can_assign_role(role) {
+            return Err(DbErr::Custom(format!(
+                "{} cannot invite members as {}",
+                inviter.role.to_value(),
+                role.to_value()
+            )));
+        }
+
+        Ok(())
+    }
+
+    async fn respond(
+        &self,
+        invitation: team_invitations::Model,
+        status: InvitationStatus,
+    ) -> DbResult<team_invitations::Model> {
+        let mut invitation = invitation.into_active_model();
+        invitation.status = Set(status);
+        invitation.responded_at = Set(Some(chrono::Utc::now().naive_utc()));
+        invitation.update(self.db.as_ref()).await
+    }
+
+    async fn find_pending(
+        &self,
+        team_id: Uuid,
+        invitation_id: Uuid,
+    ) -> DbResult<team_invitations::Model> {
+        TeamInvitations::find_by_id(invitation_id)
+            .filter(team_invitations::Column::TeamId.eq(team_id))
+            .filter(team_invitations::Column::Status.eq(InvitationStatus::Pending))
+            .one(self.db.as_ref())
+            .await?
+            .ok_or(DbErr::Custom(
+                "Cannot find a pending invitation".to_string(),
+            ))
+    }
+
+    async fn find_valid(&self, token: &str) -> DbResult<team_invitations::Model> {
+        TeamInvitations::find()
+            .filter(team_invitations::Column::TokenHash.eq(hash_token(token)))
+            .filter(team_invitations::Column::Status.eq(InvitationStatus::Pending))
+            .filter(team_invitations::Column::ExpiresAt.gt(chrono::Utc::now().naive_utc()))
+            .one(self.db.as_ref())
+            .await?
+            .ok_or(DbErr::Custom(
+                "Invitation is invalid or has expired".to_string(),
+            ))
+    }
+
+    async fn team_name(&self, team_id: Uuid) -> DbResult<String> {
+        Ok(Teams::find_by_id(team_id)
+            .one(self.db.as_ref())
+            .await?
+            .ok_or(DbErr::Custom("Cannot find team".to_string()))?
+            .team_name)
+    }
+
+    async fn send_invitation(
+        &self,
+        invitation: &team_invitations::Model,
+        team_name: &str,
+        token: &str,
+    ) -> DbResult<()> {
+        let inviter = self
+            .user_service
+            .find_user_by_id(invitation.invited_by)
+            .await?
+            .map(|user| user.display_name.or(user.username).unwrap_or(user.email))
+            .unwrap_or_else(|| "A team member".to_string());
+
+        let mut body = format!(
+            "{} invited you to join {} as {}.",
+            inviter,
+            team_name,
+            invitation.role.to_value()
+        );
+        match &self.invite_link {
+            Some(invite_link) => {
+                let link = Url::parse_with_params(invite_link, &[("token", token)])
+                    .map_err(|e| DbErr::Custom(format!("Invalid invite link: {}", e)))?;
+                body.push_str(&format!(
+                    "\n\nOpen this link to accept or decline: {}",
+                    link
+                ));
+            }
+            None => body.push_str(&format!("\n\nYour invitation token is {}.", token)),
+        }
+        body.push_str("\n\nThe invitation expires in 7 days. If you don't have an account yet, you can create one with this address from the same link.");
+
+        self.mailer
+            .send(MailMessage {
+                to: invitation.email.clone(),
+                subject: format!("Join {} on MMS", team_name),
+                body,
+            })
+            .await?;
+
+        // Members with an account also see it in the app.
+        if let Some(user) = self
+            .user_service
+            .find_user_by_email(&invitation.email)
+            .await?
+        {
+            self.notification_service
+                .notify(
+                    user.user_id,
+                    NotificationTypes::TeamInvitation,
+                    &format!("Invitation to join {}", team_name),
+                    &format!(
+                        "{} invited you to join {} as {}. Check your email to accept.",
+                        inviter,
+                        team_name,
+                        invitation.role.to_value()
+                    ),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,392 @@
+use std::sync::Arc;
+
+use crate::_entities::prelude::{TeamJoinRequests, Teams};
+use crate::_entities::sea_orm_active_enums::{
+    Actions, JoinRequestStatus, NotificationTypes, TargetTypes, TeamUserRoles,
+};
+use crate::_entities::{team_join_requests, teams, users};
+use crate::common::{
+    activity_log_service::ActivityLogService,
+    notification_service::NotificationService,
+    team_user_service::{JoinTeamDto, TeamUserService},
+};
+use crate::utils::DbResult;
+use sea_orm::prelude::Uuid;
+use sea_orm::sea_query::Expr;
+use sea_orm::sqlx::types::chrono;
+use sea_orm::*;
+use serde::{Deserialize, Serialize};
+
+/// Longest message a requester can attach.
+const MAX_MESSAGE_LENGTH: usize = 500;
+
+#[derive(Copy, Clone, Debug, EnumIter)]
+enum Relation {
+    User,
+    Team,
+}
+
+impl RelationTrait for Relation {
+    fn def(&self) -> RelationDef {
+        match self {
+            Self::User => team_join_requests::Entity::belongs_to(users::Entity)
+                .from(team_join_requests::Column::UserId)
+                .to(users::Column::UserId)
+                .into(),
+            Self::Team => team_join_requests::Entity::belongs_to(teams::Entity)
+                .from(team_join_requests::Column::TeamId)
+                .to(teams::Column::TeamId)
+                .into(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CreateJoinRequestDto {
+    #[serde(rename = "teamNamespace")]
+    pub team_namespace: String,
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ApproveJoinRequestDto {
+    pub role: TeamUserRoles,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromQueryResult)]
+pub struct JoinRequestInfo {
+    #[serde(rename = "requestId")]
+    pub request_id: Uuid,
+    #[serde(rename = "teamId")]
+    pub team_id: Uuid,
+    #[serde(rename = "teamName")]
+    pub team_name: String,
+    #[serde(rename = "userId")]
+    pub user_id: Uuid,
+    pub email: String,
+    pub username: Option<String>,
+    #[serde(rename = "displayName")]
+    pub display_name: Option<String>,
+    pub message: Option<String>,
+    pub status: String,
+    pub role: Option<TeamUserRoles>,
+    #[serde(rename = "reviewedAt")]
+    pub reviewed_at: Option<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+}
+
+/// Requests of users to join a team, reviewed by its Owners and Admins.
+pub struct TeamJoinRequestService {
+    pub db: Arc<DatabaseConnection>,
+    pub team_user_service: Arc<TeamUserService>,
+    pub notification_service: Arc<NotificationService>,
+    pub activity_log_service: Arc<ActivityLogService>,
+}
+
+impl TeamJoinRequestService {
+    pub fn new(
+        db: Arc<DatabaseConnection>,
+        team_user_service: Arc<TeamUserService>,
+        notification_service: Arc<NotificationService>,
+        activity_log_service: Arc<ActivityLogService>,
+    ) -> Self {
+        Self {
+            db,
+            team_user_service,
+            notification_service,
+            activity_log_service,
+        }
+    }
+
+    pub async fn create_request(
+        &self,
+        user_id: Uuid,
+        payload: CreateJoinRequestDto,
+    ) -> DbResult<JoinRequestInfo> {
+        let message = payload
+            .message
+            .map(|message| message.trim().to_string())
+            .filter(|message| !message.is_empty());
+        if message
+            .as_ref()
+            .is_some_and(|message| message.chars().count() > MAX_MESSAGE_LENGTH)
+        {
+            return Err(DbErr::Custom(format!(
+                "message: must be at most {} characters long",
+                MAX_MESSAGE_LENGTH
+            )));
+        }
+
+        // Teams that don't take requests answer like unknown ones.
+        let team = Teams::find()
+            .filter(teams::Column::TeamNamespace.eq(payload.team_namespace.trim()))
+            .one(self.db.as_ref())
+            .await?
+            .filter(|team| team.allow_join_requests)
+            .ok_or(DbErr::Custom(
+                "Cannot find a team accepting join requests".to_string(),
+            ))?;
+
+        if self
+            .team_user_service
+            .check_user_in_team(user_id, team.team_id)
+            .await?
+        {
+            return Err(DbErr::Custom(
+                "You are already a member of this team".to_string(),
+            ));
+        }
+        if TeamJoinRequests::find()
+            .filter(team_join_requests::Column::TeamId.eq(team.team_id))
+            .filter(team_join_requests::Column::UserId.eq(user_id))
+            .filter(team_join_requests::Column::Status.eq(JoinRequestStatus::Pending))
+            .count(self.db.as_ref())
+            .await?
+            > 0
+        {
+            return Err(DbErr::Custom(
+                "You already asked to join this team".to_string(),
+            ));
+        }
+
+        let request = team_join_requests::ActiveModel {
+            team_id: Set(team.team_id),
+            user_id: Set(user_id),
+            message: Set(message),
+            ..Default::default()
+        }
+        .insert(self.db.as_ref())
+        .await?;
+        let info = self.find_info(request.request_id).await?;
+
+        let reviewers = self
+            .team_user_service
+            .find_member_ids_by_roles(team.team_id, &[TeamUserRoles::Owner, TeamUserRoles::Admin])
+            .await?;
+        let requester = info
+            .display_name
+            .clone()
+            .or(info.username.clone())
+            .unwrap_or(info.email.clone());
+        for reviewer in reviewers {
+            self.notification_service
+                .notify(
+                    reviewer,
+                    NotificationTypes::JoinRequest,
+                    &format!("{} asks to join {}", requester, team.team_name),
+                    info.message
+                        .as_deref()
+                        .unwrap_or("Review the request in the team settings."),
+                )
+                .await?;
+        }
+
+        Ok(info)
+    }
+
+    /// Pending requests of the team, oldest first.
+    pub async fn find_pending_by_team(&self, team_id: Uuid) -> DbResult<Vec<JoinRequestInfo>> {
+        Self::select_info()
+            .filter(team_join_requests::Column::TeamId.eq(team_id))
+            .filter(team_join_requests::Column::Status.eq(JoinRequestStatus::Pending))
+            .order_by_asc(team_join_requests::Column::CreatedAt)
+            .into_model::<JoinRequestInfo>()
+            .all(self.db.as_ref())
+            .await
+    }
+
+    pub async fn find_requests_by_user(&self, user_id: Uuid) -> DbResult<Vec<JoinRequestInfo>> {
+        Self::select_info()
+            .filter(team_join_requests::Column::UserId.eq(user_id))
+            .order_by_desc(team_join_requests::Column::CreatedAt)
+            .into_model::<JoinRequestInfo>()
+            .all(self.db.as_ref())
+            .await
+    }
+
+    pub async fn approve(
+        &self,
+        team_id: Uuid,
+        request_id: Uuid,
+        reviewer_id: Uuid,
+        payload: ApproveJoinRequestDto,
+    ) -> DbResult<()> {
+        if payload.role == TeamUserRoles::Owner {
+            return Err(DbErr::Custom(
+                "Nobody can join as Owner, transfer the ownership instead".to_string(),
+            ));
+        }
+        let reviewer = self
+            .team_user_service
+            .find_joined_member(team_id, reviewer_id)
+            .await?;
+        if !reviewer.role.can_assign_role(&payload.role) {
+            return Err(DbErr::Custom(format!(
+                "{} cannot let members join as {}",
+                reviewer.role.to_value(),
+                payload.role.to_value()
+            )));
+        }
+
+        let request = self.find_pending(team_id, request_id).await?;
+        let user_id = request.user_id;
+        self.team_user_service
+            .join_team(JoinTeamDto {
+                team_id,
+                user_id,
+                role: payload.role.clone(),
+            })
+            .await?;
+        self.review(
+            request,
+            JoinRequestStatus::Approved,
+            reviewer_id,
+            Some(payload.role.clone()),
+        )
+        .await?;
+
+        let team_name = self.team_name(team_id).await?;
+        self.notification_service
+            .notify(
+                user_id,
+                NotificationTypes::JoinRequestReviewed,
+                &format!("Welcome to {}", team_name),
+                &format!(
+                    "Your request to join {} was approved, you joined as {}.",
+                    team_name,
+                    payload.role.to_value()
+                ),
+            )
+            .await?;
+        self.activity_log_service
+            .record(
+                reviewer_id,
+                Actions::Created,
+                TargetTypes::Team,
+                team_id,
+                &format!(
+                    "Approved the join request of user {} as {}",
+                    user_id,
+                    payload.role.to_value()
+                ),
+            )
+            .await
+    }
+
+    pub async fn reject(&self, team_id: Uuid, request_id: Uuid, reviewer_id: Uuid) -> DbResult<()> {
+        let request = self.find_pending(team_id, request_id).await?;
+        let user_id = request.user_id;
+        self.review(request, JoinRequestStatus::Rejected, reviewer_id, None)
+            .await?;
+
+        let team_name = self.team_name(team_id).await?;
+        self.notification_service
+            .notify(
+                user_id,
+                NotificationTypes::JoinRequestReviewed,
+                &format!("Request to join {} declined", team_name),
+                &format!("Your request to join {} was declined.", team_name),
+            )
+            .await?;
+        self.activity_log_service
+            .record(
+                reviewer_id,
+                Actions::Removed,
+                TargetTypes::Team,
+                team_id,
+                &format!("Rejected the join request of user {}", user_id),
+            )
+            .await
+    }
+
+    /// Take back a pending request of the user's own.
+    pub async fn withdraw(&self, user_id: Uuid, request_id: Uuid) -> DbResult<()> {
+        let request = TeamJoinRequests::find_by_id(request_id)
+            .filter(team_join_requests::Column::UserId.eq(user_id))
+            .filter(team_join_requests::Column::Status.eq(JoinRequestStatus::Pending))
+            .one(self.db.as_ref())
+            .await?
+            .ok_or(DbErr::Custom(
+                "Cannot find a pending join request".to_string(),
+            ))?;
+
+        let mut request = request.into_active_model();
+        request.status = Set(JoinRequestStatus::Withdrawn);
+        request.update(self.db.as_ref()).await?;
+        Ok(())
+    }
+
+    async fn review(
+        &self,
+        request: team_join_requests::Model,
+        status: JoinRequestStatus,
+        reviewer_id: Uuid,
+        role: Option<TeamUserRoles>,
+    ) -> DbResult<()> {
+        let mut request = request.into_active_model();
+        request.status = Set(status);
+        request.role = Set(role);
+        request.reviewed_by = Set(Some(reviewer_id));
+        request.reviewed_at = Set(Some(chrono::Utc::now().naive_utc()));
+        request.update(self.db.as_ref()).await?;
+        Ok(())
+    }
+
+    async fn find_pending(
+        &self,
+        team_id: Uuid,
+        request_id: Uuid,
+    ) -> DbResult<team_join_requests::Model> {
+        TeamJoinRequests::find_by_id(request_id)
+            .filter(team_join_requests::Column::TeamId.eq(team_id))
+            .filter(team_join_requests::Column::Status.eq(JoinRequestStatus::Pending))
+            .one(self.db.as_ref())
+            .await?
+            .ok_or(DbErr::Custom(
+                "Cannot find a pending join request".to_string(),
+            ))
+    }
+
+    async fn find_info(&self, request_id: Uuid) -> DbResult<JoinRequestInfo> {
+        Self::select_info()
+            .filter(team_join_requests::Column::RequestId.eq(request_id))
+            .into_model::<JoinRequestInfo>()
+            .one(self.db.as_ref())
+            .await?
+            .ok_or(DbErr::Custom("Cannot find join request".to_string()))
+    }
+
+    async fn team_name(&self, team_id: Uuid) -> DbResult<String> {
+        Ok(Teams::find_by_id(team_id)
+            .one(self.db.as_ref())
+            .await?
+            .ok_or(DbErr::Custom("Cannot find team".to_string()))?
+            .team_name)
+    }
+
+    fn select_info() -> Select<TeamJoinRequests> {
+        TeamJoinRequests::find()
+            .select_only()
+            .column(team_join_requests::Column::RequestId)
+            .column(team_join_requests::Column::TeamId)
+            .column_as(teams::Column::TeamName, "team_name")
+            .column(team_join_requests::Column::UserId)
+            .column_as(users::Column::Email, "email")
+            .column_as(users::Column::Username, "username")
+            .column_as(users::Column::DisplayName, "display_name")
+            .column(team_join_requests::Column::Message)
+            .column_as(Expr::cust("team_join_requests.status::text"), "status")
+            .column(team_join_requests::Column::Role)
+            .column_as(
+                Expr::cust("to_char(team_join_requests.reviewed_at, 'YYYY-MM-DD HH:mm:ss')"),
+                "reviewed_at",
+            )
+            .column_as(
+                Expr::cust("to_char(team_join_requests.created_at, 'YYYY-MM-DD HH:mm:ss')"),
+                "created_at",
+            )
+            .join(JoinType::InnerJoin, Relation::User.def())
+            .join(JoinType::InnerJoin, Relation::Team.def())
+    }
+}
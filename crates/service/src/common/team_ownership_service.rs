@@ -0,0 +1,370 @@
+use std::{sync::Arc, time::Duration};
+
+use crate::_entities::prelude::{TeamOwnershipTransfers, TeamUsers, Teams};
+use crate::_entities::sea_orm_active_enums::{
+    Actions, NotificationTypes, TargetTypes, TeamUserRoles, TeamUserStatus, TransferStatus,
+};
+use crate::_entities::{team_ownership_transfers, team_users};
+use crate::common::{
+    activity_log_service::ActivityLogService, notification_service::NotificationService,
+};
+use crate::utils::DbResult;
+use sea_orm::prelude::Uuid;
+use sea_orm::sea_query::Expr;
+use sea_orm::sqlx::types::chrono;
+use sea_orm::*;
+use serde::{Deserialize, Serialize};
+
+/// How long a nominee has to confirm before the transfer lapses.
+const TRANSFER_EXPIRE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct NominateOwnerDto {
+    #[serde(rename = "userId")]
+    pub user_id: Uuid,
+    /// Add the nominee as another Owner, only where the team allows it.
+    #[serde(rename = "keepOwnership", default)]
+    pub keep_ownership: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OwnershipTransferInfo {
+    #[serde(rename = "transferId")]
+    pub transfer_id: Uuid,
+    #[serde(rename = "teamId")]
+    pub team_id: Uuid,
+    #[serde(rename = "teamName")]
+    pub team_name: String,
+    #[serde(rename = "fromUserId")]
+    pub from_user_id: Uuid,
+    #[serde(rename = "toUserId")]
+    pub to_user_id: Uuid,
+    #[serde(rename = "keepOwnership")]
+    pub keep_ownership: bool,
+    pub status: String,
+    #[serde(rename = "expiresAt")]
+    pub expires_at: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+}
+
+impl OwnershipTransferInfo {
+    fn new(transfer: team_ownership_transfers::Model, team_name: String) -> Self {
+        let format = |date: chrono::NaiveDateTime| date.format("%Y-%m-%d %H:%M:%S").to_string();
+        Self {
+            transfer_id: transfer.transfer_id,
+            team_id: transfer.team_id,
+            team_name,
+            from_user_id: transfer.from_user_id,
+            to_user_id: transfer.to_user_id,
+            keep_ownership: transfer.keep_ownership,
+            status: transfer.status.to_value(),
+            expires_at: format(transfer.expires_at),
+            created_at: format(transfer.created_at),
+        }
+    }
+}
+
+/// Hands a team over from an Owner to another member. The nominee has to
+/// confirm, then both roles change in a single transaction.
+pub struct TeamOwnershipService {
+    pub db: Arc<DatabaseConnection>,
+    pub notification_service: Arc<NotificationService>,
+    pub activity_log_service: Arc<ActivityLogService>,
+}
+
+impl TeamOwnershipService {
+    pub fn new(
+        db: Arc<DatabaseConnection>,
+        notification_service: Arc<NotificationService>,
+        activity_log_service: Arc<ActivityLogService>,
+    ) -> Self {
+        Self {
+            db,
+            notification_service,
+            activity_log_service,
+        }
+    }
+
+    pub async fn nominate(
+        &self,
+        team_id: Uuid,
+        owner_id: Uuid,
+        payload: NominateOwnerDto,
+    ) -> DbResult<OwnershipTransferInfo> {
+        if payload.user_id == owner_id {
+            return Err(DbErr::Custom(
+                "Cannot transfer the ownership to yourself".to_string(),
+            ));
+        }
+
+        let team = Teams::find_by_id(team_id)
+            .one(self.db.as_ref())
+            .await?
+            .ok_or(DbErr::Custom("Cannot find team".to_string()))?;
+        if payload.keep_ownership && !team.allow_multiple_owners {
+            return Err(DbErr::Custom(
+                "This team allows a single Owner only".to_string(),
+            ));
+        }
+
+        let nominee = find_joined_member(self.db.as_ref(), team_id, payload.user_id).await?;
+        if nominee.role == TeamUserRoles::Owner {
+            return Err(DbErr::Custom("This member is already an Owner".to_string()));
+        }
+
+        // A new nomination replaces whatever was still waiting.
+        TeamOwnershipTransfers::update_many()
+            .col_expr(
+                team_ownership_transfers::Column::Status,
+                Expr::value(TransferStatus::Cancelled),
+            )
+            .filter(team_ownership_transfers::Column::TeamId.eq(team_id))
+            .filter(team_ownership_transfers::Column::Status.eq(TransferStatus::Pending))
+            .exec(self.db.as_ref())
+            .await?;
+
+        let transfer = team_ownership_transfers::ActiveModel {
+            team_id: Set(team_id),
+            from_user_id: Set(owner_id),
+            to_user_id: Set(payload.user_id),
+            keep_ownership: Set(payload.keep_ownership),
+            expires_at: Set(chrono::Utc::now().naive_utc() + TRANSFER_EXPIRE),
+            ..Default::default()
+        }
+        .insert(self.db.as_ref())
+        .await?;
+
+        self.notification_service
+            .notify(
+                payload.user_id,
+                NotificationTypes::OwnershipTransfer,
+                &format!("Become Owner of {}", team.team_name),
+                &format!(
+                    "You were nominated as Owner of {}, confirm to take over the team.",
+                    team.team_name
+                ),
+            )
+            .await?;
+        self.activity_log_service
+            .record(
+                owner_id,
+                Actions::Requested,
+                TargetTypes::Team,
+                team_id,
+                &format!("Nominated user {} as Owner", payload.user_id),
+            )
+            .await?;
+
+        Ok(OwnershipTransferInfo::new(transfer, team.team_name))
+    }
+
+    pub async fn find_pending_by_team(
+        &self,
+        team_id: Uuid,
+    ) -> DbResult<Option<OwnershipTransferInfo>> {
+        let transfer = TeamOwnershipTransfers::find()
+            .filter(team_ownership_transfers::Column::TeamId.eq(team_id))
+            .filter(team_ownership_transfers::Column::Status.eq(TransferStatus::Pending))
+            .filter(team_ownership_transfers::Column::ExpiresAt.gt(chrono::Utc::now().naive_utc()))
+            .one(self.db.as_ref())
+            .await?;
+
+        match transfer {
+            Some(transfer) => {
+                let team_name = self.team_name(team_id).await?;
+                Ok(Some(OwnershipTransferInfo::new(transfer, team_name)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Nominations waiting for the user to confirm.
+    pub async fn find_pending_by_nominee(
+        &self,
+        user_id: Uuid,
+    ) -> DbResult<Vec<OwnershipTransferInfo>> {
+        let transfers = TeamOwnershipTransfers::find()
+            .filter(team_ownership_transfers::Column::ToUserId.eq(user_id))
+            .filter(team_ownership_transfers::Column::Status.eq(TransferStatus::Pending))
+            .filter(team_ownership_transfers::Column::ExpiresAt.gt(chrono::Utc::now().naive_utc()))
+            .all(self.db.as_ref())
+            .await?;
+
+        let mut infos = Vec::with_capacity(transfers.len());
+        for transfer in transfers {
+            let team_name = self.team_name(transfer.team_id).await?;
+            infos.push(OwnershipTransferInfo::new(transfer, team_name));
+        }
+        Ok(infos)
+    }
+
+    pub async fn accept(&self, transfer_id: Uuid, user_id: Uuid) -> DbResult<()> {
+        let transfer = self.find_valid(transfer_id, user_id).await?;
+        let team_id = transfer.team_id;
+        let owner_id = transfer.from_user_id;
+        let keep_ownership = transfer.keep_ownership;
+
+        let txn = self.db.begin().await?;
+        // Roles may have moved since the nomination, check them again.
+        let owner = find_joined_member(&txn, team_id, owner_id).await?;
+        if owner.role != TeamUserRoles::Owner {
+            return Err(DbErr::Custom(
+                "The nominating member is no longer an Owner".to_string(),
+            ));
+        }
+        let nominee = find_joined_member(&txn, team_id, user_id).await?;
+        let previous = nominee.role.clone();
+
+        let mut nominee = nominee.into_active_model();
+        nominee.role = Set(TeamUserRoles::Owner);
+        nominee.custom_role_id = Set(None);
+        nominee.update(&txn).await?;
+        if !keep_ownership {
+            let mut owner = owner.into_active_model();
+            owner.role = Set(previous.clone());
+            owner.update(&txn).await?;
+        }
+
+        let mut transfer = transfer.into_active_model();
+        transfer.status = Set(TransferStatus::Accepted);
+        transfer.responded_at = Set(Some(chrono::Utc::now().naive_utc()));
+        transfer.update(&txn).await?;
+        txn.commit().await?;
+
+        let team_name = self.team_name(team_id).await?;
+        self.notification_service
+            .notify(
+                user_id,
+                NotificationTypes::OwnershipTransfer,
+                &format!("You are now Owner of {}", team_name),
+                &format!("The ownership of {} was handed over to you.", team_name),
+            )
+            .await?;
+        self.notification_service
+            .notify(
+                owner_id,
+                NotificationTypes::OwnershipTransfer,
+                &format!("Ownership of {} transferred", team_name),
+                &if keep_ownership {
+                    format!("{} has a new Owner next to you.", team_name)
+                } else {
+                    format!(
+                        "You handed over {}, your role is now {}.",
+                        team_name,
+                        previous.to_value()
+                    )
+                },
+            )
+            .await?;
+        self.activity_log_service
+            .record(
+                user_id,
+                Actions::Updated,
+                TargetTypes::Role,
+                team_id,
+                &if keep_ownership {
+                    format!("Became Owner next to user {}", owner_id)
+                } else {
+                    format!(
+                        "Took over the ownership from user {}, who is now {}",
+                        owner_id,
+                        previous.to_value()
+                    )
+                },
+            )
+            .await
+    }
+
+    pub async fn decline(&self, transfer_id: Uuid, user_id: Uuid) -> DbResult<()> {
+        let transfer = self.find_valid(transfer_id, user_id).await?;
+        let team_id = transfer.team_id;
+        let owner_id = transfer.from_user_id;
+        self.respond(transfer, TransferStatus::Declined).await?;
+
+        let team_name = self.team_name(team_id).await?;
+        self.notification_service
+            .notify(
+                owner_id,
+                NotificationTypes::OwnershipTransfer,
+                &format!("Ownership of {} declined", team_name),
+                &format!("Your nominee declined to take over {}.", team_name),
+            )
+            .await
+    }
+
+    pub async fn cancel(&self, team_id: Uuid, transfer_id: Uuid, owner_id: Uuid) -> DbResult<()> {
+        let transfer = TeamOwnershipTransfers::find_by_id(transfer_id)
+            .filter(team_ownership_transfers::Column::TeamId.eq(team_id))
+            .filter(team_ownership_transfers::Column::Status.eq(TransferStatus::Pending))
+            .one(self.db.as_ref())
+            .await?
+            .ok_or(DbErr::Custom(
+                "Cannot find a pending ownership transfer".to_string(),
+            ))?;
+        let nominee_id = transfer.to_user_id;
+        self.respond(transfer, TransferStatus::Cancelled).await?;
+
+        self.activity_log_service
+            .record(
+                owner_id,
+                Actions::Removed,
+                TargetTypes::Team,
+                team_id,
+                &format!("Cancelled the nomination of user {} as Owner", nominee_id),
+            )
+            .await
+    }
+
+    async fn respond(
+        &self,
+        transfer: team_ownership_transfers::Model,
+        status: TransferStatus,
+    ) -> DbResult<()> {
+        let mut transfer = transfer.into_active_model();
+        transfer.status = Set(status);
+        transfer.responded_at = Set(Some(chrono::Utc::now().naive_utc()));
+        transfer.update(self.db.as_ref()).await?;
+        Ok(())
+    }
+
+    async fn find_valid(
+        &self,
+        transfer_id: Uuid,
+        user_id: Uuid,
+    ) -> DbResult<team_ownership_transfers::Model> {
+        TeamOwnershipTransfers::find_by_id(transfer_id)
+            .filter(team_ownership_transfers::Column::ToUserId.eq(user_id))
+            .filter(team_ownership_transfers::Column::Status.eq(TransferStatus::Pending))
+            .filter(team_ownership_transfers::Column::ExpiresAt.gt(chrono::Utc::now().naive_utc()))
+            .one(self.db.as_ref())
+            .await?
+            .ok_or(DbErr::Custom(
+                "Ownership transfer is invalid or has expired".to_string(),
+            ))
+    }
+
+    async fn team_name(&self, team_id: Uuid) -> DbResult<String> {
+        Ok(Teams::find_by_id(team_id)
+            .one(self.db.as_ref())
+            .await?
+            .ok_or(DbErr::Custom("Cannot find team".to_string()))?
+            .team_name)
+    }
+}
+
+async fn find_joined_member<C: ConnectionTrait>(
+    db: &C,
+    team_id: Uuid,
+    user_id: Uuid,
+) -> DbResult<team_users::Model> {
+    TeamUsers::find()
+        .filter(team_users::Column::TeamId.eq(team_id))
+        .filter(team_users::Column::UserId.eq(user_id))
+        .filter(team_users::Column::Status.eq(TeamUserStatus::Joined))
+        .one(db)
+        .await?
+        .ok_or(DbErr::Custom(
+            "User is not a member of this team".to_string(),
+        ))
+}
@@ -1,7 +1,9 @@
 use std::sync::Arc;
 
 use crate::_entities::prelude::Teams;
+use crate::_entities::sea_orm_active_enums::{Actions, TargetTypes};
 use crate::_entities::teams;
+use crate::common::activity_log_service::ActivityLogService;
 use crate::utils::encrypt::generator_unique_id;
 use crate::utils::DbResult;
 use sea_orm::entity::prelude::*;
@@ -10,6 +12,9 @@ use sea_orm::DatabaseConnection;
 use sea_orm::*;
 use serde::{Deserialize, Serialize};
 
+/// Longest retention a team can set, ten years.
+const MAX_LOG_RETENTION_DAYS: u32 = 3650;
+
 #[derive(FromQueryResult, DerivePartialModel, Serialize)]
 #[sea_orm(entity = "Teams")]
 pub struct FormatTeam {
@@ -24,6 +29,14 @@ pub struct FormatTeam {
     #[serde(rename = "teamNamespace")]
     pub team_namespace: String,
     pub description: Option<String>,
+    #[serde(rename = "requireMfa")]
+    pub require_mfa: bool,
+    #[serde(rename = "allowJoinRequests")]
+    pub allow_join_requests: bool,
+    #[serde(rename = "allowMultipleOwners")]
+    pub allow_multiple_owners: bool,
+    #[serde(rename = "logRetentionDays")]
+    pub log_retention_days: Option<i32>,
     #[sea_orm(from_expr = "Expr::cust(\"to_char(teams.created_at, 'YYYY-MM-DD HH:mm:ss')\")")]
     #[serde(rename = "createdAt")]
     pub created_at: Option<String>,
@@ -56,11 +69,18 @@ pub struct UpdateTeamDto {
 
 pub struct TeamService {
     pub db: Arc<DatabaseConnection>,
+    pub activity_log_service: Arc<ActivityLogService>,
 }
 
 impl TeamService {
-    pub fn new(db: Arc<DatabaseConnection>) -> TeamService {
-        Self { db }
+    pub fn new(
+        db: Arc<DatabaseConnection>,
+        activity_log_service: Arc<ActivityLogService>,
+    ) -> TeamService {
+        Self {
+            db,
+            activity_log_service,
+        }
     }
 
     pub async fn find_project_all(&self) -> DbResult<Vec<FormatTeam>> {
@@ -77,7 +97,11 @@ impl TeamService {
             .await
     }
 
-    pub async fn create_team(&self, form_data: CreateTeamDto) -> DbResult<teams::ActiveModel> {
+    pub async fn create_team(
+        &self,
+        actor_id: Uuid,
+        form_data: CreateTeamDto,
+    ) -> DbResult<teams::Model> {
         if form_data.team_name.trim().is_empty() {
             return Err(DbErr::Custom("Team name cannot be empty".to_string()));
         }
@@ -86,7 +110,7 @@ impl TeamService {
             return Err(DbErr::Custom("Team namespace cannot be empty".to_string()));
         }
 
-        teams::ActiveModel {
+        let team = teams::ActiveModel {
             team_name: Set(form_data.team_name.clone()),
             team_unique_id: Set(generator_unique_id(&form_data.team_namespace)),
             team_namespace: Set(form_data.team_namespace.clone()),
@@ -94,16 +118,30 @@ impl TeamService {
             description: Set(form_data.description),
             ..Default::default()
         }
-        .save(self.db.as_ref())
-        .await
+        .insert(self.db.as_ref())
+        .await?;
+
+        self.activity_log_service
+            .record_changes(
+                actor_id,
+                Actions::Created,
+                TargetTypes::Team,
+                team.team_id,
+                &format!("Created team `{}`", team.team_name),
+                ActivityLogService::changes(None, Some(&team)),
+            )
+            .await?;
+        Ok(team)
     }
 
-    pub async fn update_team(&self, id: Uuid, form_data: UpdateTeamDto) -> DbResult<()> {
-        let mut team = Teams::find_by_id(id)
-            .one(self.db.as_ref())
-            .await?
-            .ok_or(DbErr::Custom("Cannot find team!".to_string()))?
-            .into_active_model();
+    pub async fn update_team(
+        &self,
+        id: Uuid,
+        actor_id: Uuid,
+        form_data: UpdateTeamDto,
+    ) -> DbResult<()> {
+        let before = self.find_team(id).await?;
+        let mut team = before.clone().into_active_model();
 
         if let Some(team_name) = form_data.team_name {
             if team_name.trim().is_empty() {
@@ -140,12 +178,120 @@ impl TeamService {
             team.team_avatar = Set(Some(team_avatar));
         }
 
-        team.update(self.db.as_ref()).await?;
-        Ok(())
+        self.update_and_record(actor_id, before, team, "Updated team")
+            .await
+    }
+
+    pub async fn set_allow_join_requests(
+        &self,
+        id: Uuid,
+        actor_id: Uuid,
+        allowed: bool,
+    ) -> DbResult<()> {
+        let before = self.find_team(id).await?;
+        let mut team = before.clone().into_active_model();
+
+        team.allow_join_requests = Set(allowed);
+        let description = match allowed {
+            true => "Opened the team to join requests",
+            false => "Closed the team to join requests",
+        };
+        self.update_and_record(actor_id, before, team, description)
+            .await
+    }
+
+    /// Only decides how later transfers go, Owners already in place stay.
+    pub async fn set_allow_multiple_owners(
+        &self,
+        id: Uuid,
+        actor_id: Uuid,
+        allowed: bool,
+    ) -> DbResult<()> {
+        let before = self.find_team(id).await?;
+        let mut team = before.clone().into_active_model();
+
+        team.allow_multiple_owners = Set(allowed);
+        let description = match allowed {
+            true => "Allowed multiple Owners",
+            false => "Disallowed multiple Owners",
+        };
+        self.update_and_record(actor_id, before, team, description)
+            .await
     }
 
-    pub async fn delete_team(&self, id: Uuid) -> DbResult<()> {
+    /// Entries older than `days` leave the team's activity log at the next
+    /// purge, `None` keeps them forever.
+    pub async fn set_log_retention(
+        &self,
+        id: Uuid,
+        actor_id: Uuid,
+        days: Option<u32>,
+    ) -> DbResult<()> {
+        if days.is_some_and(|days| !(1..=MAX_LOG_RETENTION_DAYS).contains(&days)) {
+            return Err(DbErr::Custom(format!(
+                "Log retention is between 1 and {} days",
+                MAX_LOG_RETENTION_DAYS
+            )));
+        }
+
+        let before = self.find_team(id).await?;
+        let mut team = before.clone().into_active_model();
+
+        team.log_retention_days = Set(days.map(|days| days as i32));
+        let description = match days {
+            Some(days) => format!("Set the log retention to {} days", days),
+            None => "Kept the activity log forever".to_string(),
+        };
+        self.update_and_record(actor_id, before, team, &description)
+            .await
+    }
+
+    pub async fn delete_team(&self, id: Uuid, actor_id: Uuid) -> DbResult<()> {
+        let team = self.find_team(id).await?;
         Teams::delete_by_id(id).exec(self.db.as_ref()).await?;
-        Ok(())
+
+        self.activity_log_service
+            .record_changes(
+                actor_id,
+                Actions::Removed,
+                TargetTypes::Team,
+                id,
+                &format!("Deleted team `{}`", team.team_name),
+                ActivityLogService::changes(Some(&team), None),
+            )
+            .await
+    }
+
+    async fn find_team(&self, id: Uuid) -> DbResult<teams::Model> {
+        Teams::find_by_id(id)
+            .one(self.db.as_ref())
+            .await?
+            .ok_or(DbErr::Custom("Cannot find team!".to_string()))
+    }
+
+    /// Save `team` and log what changed against `before`, nothing when the
+    /// update was a no-op.
+    async fn update_and_record(
+        &self,
+        actor_id: Uuid,
+        before: teams::Model,
+        team: teams::ActiveModel,
+        description: &str,
+    ) -> DbResult<()> {
+        let team = team.update(self.db.as_ref()).await?;
+        let Some(changes) = ActivityLogService::changes(Some(&before), Some(&team)) else {
+            return Ok(());
+        };
+
+        self.activity_log_service
+            .record_changes(
+                actor_id,
+                Actions::Updated,
+                TargetTypes::Team,
+                team.team_id,
+                description,
+                Some(changes),
+            )
+            .await
     }
 }
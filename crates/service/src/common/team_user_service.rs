@@ -1,6 +1,7 @@
 use crate::_entities::prelude::TeamUsers;
-use crate::_entities::sea_orm_active_enums::{TeamUserRoles, TeamUserStatus};
+use crate::_entities::sea_orm_active_enums::{Actions, TargetTypes, TeamUserRoles, TeamUserStatus};
 use crate::_entities::{self, team_users, teams, users};
+use crate::common::activity_log_service::ActivityLogService;
 use crate::utils::DbResult;
 use sea_orm::sea_query::Expr;
 use sea_orm::sqlx::types::chrono;
@@ -9,8 +10,8 @@ use sea_orm::{
     RelationTrait,
 };
 use sea_orm::{
-    ActiveModelTrait, ColumnTrait, Condition, DbErr, FromQueryResult, JoinType, QueryFilter,
-    QueryOrder, QuerySelect, Set,
+    ActiveEnum, ActiveModelTrait, ColumnTrait, Condition, ConnectionTrait, DbErr, FromQueryResult,
+    IntoActiveModel, JoinType, PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, Set,
 };
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
@@ -50,6 +51,7 @@ impl Related<_entities::teams::Entity> for team_users::Entity {
 
 pub struct TeamUserService {
     db: Arc<DatabaseConnection>,
+    activity_log_service: Arc<ActivityLogService>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -69,6 +71,11 @@ pub struct JoinTeamDto {
     pub role: TeamUserRoles,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateMemberRoleDto {
+    pub role: TeamUserRoles,
+}
+
 #[derive(Debug, Serialize, Deserialize, FromQueryResult)]
 pub struct TeamOfUser {
     #[serde(rename = "teamId")]
@@ -97,6 +104,25 @@ pub struct UserOfTeam {
     pub email: String,
     pub avatar: Option<String>,
     pub role: TeamUserRoles,
+    #[serde(rename = "customRoleId")]
+    pub custom_role_id: Option<Uuid>,
+    pub status: TeamUserStatus,
+    #[serde(rename = "joinedAt")]
+    pub joined_at: String,
+    #[serde(rename = "leftedAt")]
+    pub lefted_at: Option<String>,
+}
+
+/// Every team a user is or was in, for platform admins.
+#[derive(Debug, Serialize, Deserialize, FromQueryResult)]
+pub struct MembershipOfUser {
+    #[serde(rename = "teamId")]
+    pub team_id: Uuid,
+    #[serde(rename = "teamName")]
+    pub team_name: String,
+    #[serde(rename = "teamNamespace")]
+    pub team_namespace: String,
+    pub role: TeamUserRoles,
     pub status: TeamUserStatus,
     #[serde(rename = "joinedAt")]
     pub joined_at: String,
@@ -110,8 +136,11 @@ pub struct FindRoleVo {
 }
 
 impl TeamUserService {
-    pub fn new(db: Arc<DatabaseConnection>) -> Self {
-        Self { db }
+    pub fn new(db: Arc<DatabaseConnection>, activity_log_service: Arc<ActivityLogService>) -> Self {
+        Self {
+            db,
+            activity_log_service,
+        }
     }
 
     pub async fn get_user_role_by_team(
@@ -144,40 +173,180 @@ impl TeamUserService {
             .one(self.db.as_ref())
             .await?;
 
-        if let Some(_) = existing {
-            return Err(DbErr::Custom("User is already in team".to_string()));
+        if let Some(existing) = existing {
+            if existing.status == TeamUserStatus::Joined {
+                return Err(DbErr::Custom("User is already in team".to_string()));
+            }
+
+            // Someone who left before joins again with the new role.
+            let mut model = existing.clone().into_active_model();
+            model.role = Set(payload.role);
+            model.status = Set(TeamUserStatus::Joined);
+            model.joined_at = Set(chrono::Utc::now().naive_utc());
+            model.lefted_at = Set(None);
+            model.custom_role_id = Set(None);
+            let member = model.update(self.db.as_ref()).await?;
+            return self.record_joined(Some(&existing), &member).await;
         }
 
-        let model = team_users::ActiveModel {
+        let member = team_users::ActiveModel {
             team_id: Set(payload.team_id),
             user_id: Set(payload.user_id),
             role: Set(payload.role),
             status: Set(TeamUserStatus::Joined),
             lefted_at: Set(None),
             ..Default::default()
-        };
+        }
+        .insert(self.db.as_ref())
+        .await?;
 
-        model.insert(self.db.as_ref()).await?;
-        Ok(())
+        self.record_joined(None, &member).await
     }
 
-    pub async fn left_team(&self, payload: LeftTeamDto) -> DbResult<()> {
+    async fn record_joined(
+        &self,
+        before: Option<&team_users::Model>,
+        member: &team_users::Model,
+    ) -> DbResult<()> {
+        self.activity_log_service
+            .record_changes(
+                member.user_id,
+                Actions::Joined,
+                TargetTypes::Team,
+                member.team_id,
+                &format!("Joined as {}", member.role.to_value()),
+                ActivityLogService::changes(before, Some(member)),
+            )
+            .await
+    }
+
+    /// Take the member out of the team, by themselves or by `actor_id`.
+    pub async fn left_team(&self, actor_id: Uuid, payload: LeftTeamDto) -> DbResult<()> {
         let team_user = TeamUsers::find()
             .filter(team_users::Column::UserId.eq(payload.user_id))
             .filter(team_users::Column::TeamId.eq(payload.team_id))
+            .filter(team_users::Column::Status.eq(TeamUserStatus::Joined))
             .one(self.db.as_ref())
             .await?;
 
-        match team_user {
-            Some(model) => {
-                let mut model: team_users::ActiveModel = model.into();
-                model.status = Set(TeamUserStatus::Lefted);
-                model.lefted_at = Set(Some(chrono::Utc::now().naive_utc()));
-                model.update(self.db.as_ref()).await?;
-                Ok(())
-            }
-            None => Ok(()),
+        let Some(before) = team_user else {
+            return Ok(());
+        };
+
+        let mut model = before.clone().into_active_model();
+        model.status = Set(TeamUserStatus::Lefted);
+        model.lefted_at = Set(Some(chrono::Utc::now().naive_utc()));
+        let member = model.update(self.db.as_ref()).await?;
+
+        let changes = ActivityLogService::changes(Some(&before), Some(&member));
+        if actor_id == payload.user_id {
+            self.activity_log_service
+                .record_changes(
+                    actor_id,
+                    Actions::Left,
+                    TargetTypes::Team,
+                    payload.team_id,
+                    "Left the team",
+                    changes,
+                )
+                .await
+        } else {
+            self.activity_log_service
+                .record_changes(
+                    actor_id,
+                    Actions::Removed,
+                    TargetTypes::Team,
+                    payload.team_id,
+                    &format!("Removed user {} from the team", payload.user_id),
+                    changes,
+                )
+                .await
+        }
+    }
+
+    /// Change the role of a member. The actor has to outrank the member and
+    /// the new role, and the last Owner of a team keeps the role.
+    pub async fn update_member_role(
+        &self,
+        team_id: Uuid,
+        actor_id: Uuid,
+        member_id: Uuid,
+        payload: UpdateMemberRoleDto,
+    ) -> DbResult<()> {
+        if actor_id == member_id {
+            return Err(DbErr::Custom("Cannot change your own role".to_string()));
         }
+
+        let actor = self.find_joined_member(team_id, actor_id).await?;
+        let member = self.find_joined_member(team_id, member_id).await?;
+        if !actor.role.can_manage_member(&member.role) {
+            return Err(DbErr::Custom(format!(
+                "{} cannot change the role of {}",
+                actor.role.to_value(),
+                member.role.to_value()
+            )));
+        }
+        if !actor.role.can_assign_role(&payload.role) {
+            return Err(DbErr::Custom(if payload.role == TeamUserRoles::Owner {
+                "Transfer the ownership to make someone Owner".to_string()
+            } else {
+                "You can only assign roles below your own".to_string()
+            }));
+        }
+        if member.role == payload.role {
+            return Ok(());
+        }
+        if member.role == TeamUserRoles::Owner && self.count_owners(team_id).await? <= 1 {
+            return Err(DbErr::Custom(
+                "The last Owner of a team cannot be demoted".to_string(),
+            ));
+        }
+
+        let mut model = member.clone().into_active_model();
+        model.role = Set(payload.role.clone());
+        let updated = model.update(self.db.as_ref()).await?;
+
+        self.activity_log_service
+            .record_changes(
+                actor_id,
+                Actions::Updated,
+                TargetTypes::Role,
+                team_id,
+                &format!(
+                    "Changed the role of user {} from {} to {}",
+                    member_id,
+                    member.role.to_value(),
+                    payload.role.to_value()
+                ),
+                ActivityLogService::changes(Some(&member), Some(&updated)),
+            )
+            .await
+    }
+
+    /// The user's membership while they are still in the team.
+    pub async fn find_joined_member(
+        &self,
+        team_id: Uuid,
+        user_id: Uuid,
+    ) -> DbResult<team_users::Model> {
+        TeamUsers::find()
+            .filter(team_users::Column::TeamId.eq(team_id))
+            .filter(team_users::Column::UserId.eq(user_id))
+            .filter(team_users::Column::Status.eq(TeamUserStatus::Joined))
+            .one(self.db.as_ref())
+            .await?
+            .ok_or(DbErr::Custom(
+                "User is not a member of this team".to_string(),
+            ))
+    }
+
+    async fn count_owners(&self, team_id: Uuid) -> DbResult<u64> {
+        TeamUsers::find()
+            .filter(team_users::Column::TeamId.eq(team_id))
+            .filter(team_users::Column::Role.eq(TeamUserRoles::Owner))
+            .filter(team_users::Column::Status.eq(TeamUserStatus::Joined))
+            .count(self.db.as_ref())
+            .await
     }
 
     pub async fn find_teams_by_user(&self, user_id: Uuid) -> DbResult<Vec<TeamOfUser>> {
@@ -198,7 +367,7 @@ impl TeamUserService {
             .join(JoinType::LeftJoin, Relation::User.def())
             .join(JoinType::LeftJoin, Relation::Team.def())
             .filter(
-                Condition::any()
+                Condition::all()
                     .add(team_users::Column::UserId.eq(user_id))
                     .add(team_users::Column::Status.eq(TeamUserStatus::Joined)),
             )
@@ -207,6 +376,30 @@ impl TeamUserService {
             .await
     }
 
+    pub async fn find_memberships_by_user(&self, user_id: Uuid) -> DbResult<Vec<MembershipOfUser>> {
+        TeamUsers::find()
+            .select_only()
+            .column_as(teams::Column::TeamId, "team_id")
+            .column_as(teams::Column::TeamName, "team_name")
+            .column_as(teams::Column::TeamNamespace, "team_namespace")
+            .column_as(
+                Expr::cust("to_char(team_users.joined_at, 'YYYY-MM-DD HH:mm:ss')"),
+                "joined_at",
+            )
+            .column_as(
+                Expr::cust("to_char(team_users.lefted_at, 'YYYY-MM-DD HH:mm:ss')"),
+                "lefted_at",
+            )
+            .column(team_users::Column::Status)
+            .column(team_users::Column::Role)
+            .join(JoinType::InnerJoin, Relation::Team.def())
+            .filter(team_users::Column::UserId.eq(user_id))
+            .order_by(team_users::Column::JoinedAt, sea_orm::Order::Desc)
+            .into_model::<MembershipOfUser>()
+            .all(self.db.as_ref())
+            .await
+    }
+
     pub async fn find_users_by_team(&self, team_id: Uuid) -> DbResult<Vec<UserOfTeam>> {
         TeamUsers::find()
             .select_only()
@@ -225,6 +418,7 @@ impl TeamUserService {
             )
             .column(team_users::Column::Status)
             .column(team_users::Column::Role)
+            .column(team_users::Column::CustomRoleId)
             .join(JoinType::LeftJoin, Relation::User.def())
             .filter(team_users::Column::TeamId.eq(team_id))
             .order_by(team_users::Column::Status, sea_orm::Order::Desc)
@@ -244,7 +438,60 @@ impl TeamUserService {
         Ok(exists.is_some())
     }
 
-    pub async fn left_all_relation_by_user(&self, user_id: Uuid) -> DbResult<()> {
+    /// Current members of the team holding one of `roles`.
+    pub async fn find_member_ids_by_roles(
+        &self,
+        team_id: Uuid,
+        roles: &[TeamUserRoles],
+    ) -> DbResult<Vec<Uuid>> {
+        Ok(TeamUsers::find()
+            .filter(team_users::Column::TeamId.eq(team_id))
+            .filter(team_users::Column::Status.eq(TeamUserStatus::Joined))
+            .filter(team_users::Column::Role.is_in(roles.iter().cloned()))
+            .all(self.db.as_ref())
+            .await?
+            .into_iter()
+            .map(|member| member.user_id)
+            .collect())
+    }
+
+    /// Teams in which the user is the only Owner still joined. The Owner rows
+    /// stay locked for the rest of the transaction, so nobody leaves meanwhile.
+    pub async fn find_sole_owned_teams<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        user_id: Uuid,
+    ) -> DbResult<Vec<teams::Model>> {
+        let owned = TeamUsers::find()
+            .filter(team_users::Column::UserId.eq(user_id))
+            .filter(team_users::Column::Role.eq(TeamUserRoles::Owner))
+            .filter(team_users::Column::Status.eq(TeamUserStatus::Joined))
+            .find_also_related(teams::Entity)
+            .all(db)
+            .await?;
+
+        let mut sole_owned = Vec::new();
+        for (relation, team) in owned {
+            let owners = TeamUsers::find()
+                .filter(team_users::Column::TeamId.eq(relation.team_id))
+                .filter(team_users::Column::Role.eq(TeamUserRoles::Owner))
+                .filter(team_users::Column::Status.eq(TeamUserStatus::Joined))
+                .lock_exclusive()
+                .all(db)
+                .await?;
+            if owners.iter().all(|owner| owner.user_id == user_id) {
+                sole_owned.extend(team);
+            }
+        }
+
+        Ok(sole_owned)
+    }
+
+    pub async fn left_all_relation_by_user<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        user_id: Uuid,
+    ) -> DbResult<()> {
         TeamUsers::update_many()
             .col_expr(
                 team_users::Column::Status,
@@ -255,7 +502,7 @@ impl TeamUserService {
                 Expr::value(chrono::Utc::now()),
             )
             .filter(team_users::Column::UserId.eq(user_id))
-            .exec(self.db.as_ref())
+            .exec(db)
             .await?;
 
         Ok(())
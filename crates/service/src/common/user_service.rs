@@ -1,10 +1,15 @@
 use crate::_entities::prelude::*;
-use crate::_entities::sea_orm_active_enums::Status;
-use crate::_entities::users::{self};
-use crate::utils::encrypt::{generator_unique_id, PassVerify};
-use crate::utils::DbResult;
+use crate::_entities::sea_orm_active_enums::{Actions, PlatformRoles, Status, TargetTypes};
+use crate::_entities::{sessions, users};
+use crate::common::activity_log_service::ActivityLogService;
+use crate::common::login_attempt_service::LOGIN_FAILED;
+use crate::utils::encrypt::{generator_random_token, generator_unique_id, PassVerify};
+use crate::utils::password_policy::PasswordPolicy;
+use crate::utils::{DbResult, Paginated};
 use sea_orm::entity::prelude::*;
 use sea_orm::prelude::Uuid;
+use sea_orm::sea_query::extension::postgres::PgExpr;
+use sea_orm::sqlx::types::chrono;
 use sea_orm::*;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
@@ -18,6 +23,11 @@ pub struct PartialUser {
     #[serde(rename = "uniqueId")]
     pub unique_id: String,
     pub email: String,
+    #[sea_orm(
+        from_expr = "Expr::cust(\"to_char(users.email_verified_at, 'YYYY-MM-DD HH:mm:ss')\")"
+    )]
+    #[serde(rename = "emailVerifiedAt")]
+    pub email_verified_at: Option<String>,
     pub username: Option<String>,
     #[serde(rename = "displayName")]
     pub display_name: Option<String>,
@@ -25,6 +35,13 @@ pub struct PartialUser {
     #[serde(rename = "defaultTeamId")]
     pub default_team_id: Option<Uuid>,
     pub status: Status,
+    #[serde(rename = "suspendedReason")]
+    pub suspended_reason: Option<String>,
+    #[sea_orm(from_expr = "Expr::cust(\"to_char(users.suspended_until, 'YYYY-MM-DD HH:mm:ss')\")")]
+    #[serde(rename = "suspendedUntil")]
+    pub suspended_until: Option<String>,
+    #[serde(rename = "platformRole")]
+    pub platform_role: PlatformRoles,
     #[sea_orm(from_expr = "Expr::cust(\"to_char(users.created_at, 'YYYY-MM-DD HH:mm:ss')\")")]
     #[serde(rename = "createdAt")]
     pub created_at: Option<String>,
@@ -40,6 +57,27 @@ pub struct VerifyUserModel {
     pub user_id: Uuid,
     pub email: String,
     pub password: String,
+    #[serde(rename = "emailVerifiedAt")]
+    pub email_verified_at: Option<DateTime>,
+}
+
+#[derive(Debug, FromQueryResult, DerivePartialModel, Serialize)]
+#[sea_orm(entity = "Users")]
+pub struct StatusUserModel {
+    pub status: Status,
+    pub suspended_reason: Option<String>,
+    pub suspended_until: Option<DateTime>,
+    pub platform_role: PlatformRoles,
+}
+
+/// Account status along with the state of one of its sessions.
+#[derive(Debug, FromQueryResult)]
+struct SessionStatusModel {
+    status: Status,
+    suspended_reason: Option<String>,
+    suspended_until: Option<DateTime>,
+    platform_role: PlatformRoles,
+    revoked_at: Option<DateTime>,
 }
 
 #[derive(Debug, FromQueryResult, DerivePartialModel, Serialize)]
@@ -59,6 +97,9 @@ pub struct CreateUserDto {
     pub avatar: Option<String>,
     #[serde(rename = "displayName")]
     pub display_name: Option<String>,
+    /// Team invitation the account is created from, accepted right away.
+    #[serde(rename = "inviteToken")]
+    pub invite_token: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -72,20 +113,34 @@ pub struct UpdateUserDto {
     pub default_team_id: Option<Uuid>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct SearchUserQuery {
+    pub keyword: Option<String>,
+    pub status: Option<Status>,
+    #[serde(rename = "platformRole")]
+    pub platform_role: Option<PlatformRoles>,
+    pub page: Option<u64>,
+    #[serde(rename = "pageSize")]
+    pub page_size: Option<u64>,
+}
+
 pub struct UserService {
     pub db: Arc<DatabaseConnection>,
+    pub password_policy: Arc<PasswordPolicy>,
+    pub activity_log_service: Arc<ActivityLogService>,
 }
 
 impl UserService {
-    pub fn new(db: Arc<DatabaseConnection>) -> Self {
-        Self { db }
-    }
-
-    pub async fn find_user_all(&self) -> DbResult<Vec<PartialUser>> {
-        Users::find()
-            .into_partial_model::<PartialUser>()
-            .all(self.db.as_ref())
-            .await
+    pub fn new(
+        db: Arc<DatabaseConnection>,
+        password_policy: Arc<PasswordPolicy>,
+        activity_log_service: Arc<ActivityLogService>,
+    ) -> Self {
+        Self {
+            db,
+            password_policy,
+            activity_log_service,
+        }
     }
 
     pub async fn find_user_by_id(&self, id: Uuid) -> DbResult<Option<PartialUser>> {
@@ -124,29 +179,44 @@ impl UserService {
             .is_some())
     }
 
-    pub async fn find_users_in_page(
-        &self,
-        page: u64,
-        users_per_page: u64,
-    ) -> DbResult<(Vec<PartialUser>, u64)> {
-        let page = if page == 0 { 1 } else { page };
-        let users_per_page = if users_per_page == 0 {
-            10
-        } else {
-            users_per_page
-        };
-
-        let query = Users::find()
-            .order_by_asc(users::Column::CreatedAt)
-            .into_partial_model::<PartialUser>();
-
-        let paginator = query.paginate(self.db.as_ref(), users_per_page);
-        let num_pages = paginator.num_pages().await?;
+    /// Admin listing, filtered by a keyword on email, username or display name.
+    pub async fn search_users(&self, query: SearchUserQuery) -> DbResult<Paginated<PartialUser>> {
+        let page = query.page.unwrap_or(1).max(1);
+        let page_size = query.page_size.unwrap_or(20).clamp(1, 100);
+
+        let mut select = Users::find();
+        if let Some(keyword) = query.keyword.filter(|keyword| !keyword.trim().is_empty()) {
+            let pattern = format!("%{}%", keyword.trim());
+            select = select.filter(
+                Condition::any()
+                    .add(Expr::col(users::Column::Email).ilike(&pattern))
+                    .add(Expr::col(users::Column::Username).ilike(&pattern))
+                    .add(Expr::col(users::Column::DisplayName).ilike(&pattern)),
+            );
+        }
+        if let Some(status) = query.status {
+            select = select.filter(users::Column::Status.eq(status));
+        }
+        if let Some(role) = query.platform_role {
+            select = select.filter(users::Column::PlatformRole.eq(role));
+        }
 
-        paginator.fetch_page(page - 1).await.map(|p| (p, num_pages))
+        let paginator = select
+            .order_by_desc(users::Column::CreatedAt)
+            .into_partial_model::<PartialUser>()
+            .paginate(self.db.as_ref(), page_size);
+        let total = paginator.num_items().await?;
+        let items = paginator.fetch_page(page - 1).await?;
+
+        Ok(Paginated {
+            items,
+            total,
+            page,
+            page_size,
+        })
     }
 
-    pub async fn create_user(&self, form_data: CreateUserDto) -> DbResult<users::ActiveModel> {
+    pub async fn create_user(&self, form_data: CreateUserDto) -> ModelResult {
         if form_data.email.trim().is_empty() {
             return Err(DbErr::Custom("Email cannot be empty".to_string()));
         }
@@ -159,6 +229,12 @@ impl UserService {
             return Err(DbErr::Custom("Password cannot be empty".to_string()));
         }
 
+        self.password_policy.check(
+            &form_data.password,
+            &form_data.email,
+            Some(&form_data.username),
+        )?;
+
         if self.check_email_exist(&form_data.email).await? {
             return Err(DbErr::Custom(
                 "User with this email already exists".to_string(),
@@ -171,17 +247,88 @@ impl UserService {
             ));
         }
 
-        users::ActiveModel {
+        let user = users::ActiveModel {
             email: Set(form_data.email.clone()),
             username: Set(Some(form_data.username)),
             display_name: Set(form_data.display_name),
             avatar: Set(form_data.avatar),
-            password: Set(PassVerify::encrypt_password(&form_data.password)),
+            password: Set(self.password_policy.hash(&form_data.password)),
             unique_id: Set(generator_unique_id(&form_data.email)),
             ..Default::default()
         }
-        .save(self.db.as_ref())
-        .await
+        .insert(self.db.as_ref())
+        .await?;
+
+        self.record_created(&user, "Signed up").await?;
+        Ok(user)
+    }
+
+    /// Create an account for someone signing in through an external provider.
+    /// The random password is never shown, a real one can be set by resetting it.
+    pub async fn create_external_user(
+        &self,
+        email: &str,
+        display_name: Option<String>,
+        avatar: Option<String>,
+        email_verified: bool,
+    ) -> ModelResult {
+        if self.check_email_exist(email).await? {
+            return Err(DbErr::Custom(
+                "User with this email already exists".to_string(),
+            ));
+        }
+
+        let user = users::ActiveModel {
+            email: Set(email.to_string()),
+            display_name: Set(display_name),
+            avatar: Set(avatar),
+            password: Set(self.password_policy.hash(&generator_random_token(32))),
+            unique_id: Set(generator_unique_id(email)),
+            email_verified_at: Set(email_verified.then(|| chrono::Utc::now().naive_utc())),
+            ..Default::default()
+        }
+        .insert(self.db.as_ref())
+        .await?;
+
+        self.record_created(&user, "Signed up through an external login")
+            .await?;
+        Ok(user)
+    }
+
+    async fn record_created(&self, user: &users::Model, description: &str) -> DbResult<()> {
+        self.activity_log_service
+            .record_changes(
+                user.user_id,
+                Actions::Created,
+                TargetTypes::User,
+                user.user_id,
+                description,
+                ActivityLogService::changes(None, Some(user)),
+            )
+            .await
+    }
+
+    /// Log an update the user made to their own account, unless it changed
+    /// nothing.
+    async fn record_updated(
+        &self,
+        before: &users::Model,
+        after: &users::Model,
+        description: &str,
+    ) -> DbResult<()> {
+        let Some(changes) = ActivityLogService::changes(Some(before), Some(after)) else {
+            return Ok(());
+        };
+        self.activity_log_service
+            .record_changes(
+                after.user_id,
+                Actions::Updated,
+                TargetTypes::User,
+                after.user_id,
+                description,
+                Some(changes),
+            )
+            .await
     }
 
     pub async fn update_password_by_email(&self, email: &str, password: &str) -> ModelResult {
@@ -189,21 +336,66 @@ impl UserService {
             return Err(DbErr::Custom("New password cannot be empty".to_string()));
         }
 
-        let mut user = self
+        let user = self
             .find_user_by_email(email)
             .await?
-            .ok_or(DbErr::Custom("Cannot find user by the email!".to_string()))?
-            .into_active_model();
-        user.password = Set(PassVerify::encrypt_password(password));
+            .ok_or(DbErr::Custom("Cannot find user by the email!".to_string()))?;
+        self.set_password(user, password).await
+    }
+
+    /// Confirm a sensitive action of a logged-in user with their password.
+    pub async fn check_password_by_id(&self, id: Uuid, password: &str) -> ModelResult {
+        let user = Users::find_by_id(id)
+            .one(self.db.as_ref())
+            .await?
+            .ok_or(DbErr::Custom("Cannot find user".to_owned()))?;
+
+        let matched = PassVerify::verify_password(password, &user.password).map_err(|e| {
+            log::error!("Stored password hash of {} is malformed: {}", id, e);
+            DbErr::Custom("Cannot check the current password".to_string())
+        })?;
+        if !matched {
+            return Err(DbErr::Custom("Current password is incorrect".to_string()));
+        }
+
+        Ok(user)
+    }
+
+    pub async fn change_password(
+        &self,
+        id: Uuid,
+        current_password: &str,
+        password: &str,
+    ) -> ModelResult {
+        if password.trim().is_empty() {
+            return Err(DbErr::Custom("New password cannot be empty".to_string()));
+        }
+
+        let user = self.check_password_by_id(id, current_password).await?;
+        if current_password == password {
+            return Err(DbErr::Custom(
+                "New password must be different from the current one".to_string(),
+            ));
+        }
+
+        self.set_password(user, password).await
+    }
+
+    async fn set_password(&self, user: users::Model, password: &str) -> ModelResult {
+        self.password_policy
+            .check(password, &user.email, user.username.as_deref())?;
+
+        let mut user = user.into_active_model();
+        user.password = Set(self.password_policy.hash(password));
         user.update(self.db.as_ref()).await
     }
 
     pub async fn update_user_by_id(&self, id: Uuid, form_data: UpdateUserDto) -> ModelResult {
-        let mut user: users::ActiveModel = Users::find_by_id(id)
+        let before = Users::find_by_id(id)
             .one(self.db.as_ref())
             .await?
-            .ok_or(DbErr::Custom("Cannot find user".to_owned()))?
-            .into_active_model();
+            .ok_or(DbErr::Custom("Cannot find user".to_owned()))?;
+        let mut user = before.clone().into_active_model();
 
         if let Some(email) = form_data.email {
             if email.trim().is_empty() {
@@ -214,6 +406,10 @@ impl UserService {
                     return Err(DbErr::Custom("Email already exists".to_string()));
                 }
             }
+            // A new address has to be verified again.
+            if user.email.as_ref() != &email {
+                user.email_verified_at = Set(None);
+            }
             user.email = Set(email.clone());
             user.unique_id = Set(generator_unique_id(&email));
         }
@@ -238,13 +434,244 @@ impl UserService {
         user.avatar = Set(form_data.avatar);
         user.default_team_id = Set(form_data.default_team_id);
 
+        let user = user.update(self.db.as_ref()).await?;
+        self.record_updated(&before, &user, "Updated profile")
+            .await?;
+        Ok(user)
+    }
+
+    /// Change only the team the user lands in, leaving the profile alone.
+    pub async fn set_default_team(&self, id: Uuid, team_id: Option<Uuid>) -> ModelResult {
+        let before = Users::find_by_id(id)
+            .one(self.db.as_ref())
+            .await?
+            .ok_or(DbErr::Custom("Cannot find user".to_owned()))?;
+
+        let mut user = before.clone().into_active_model();
+        user.default_team_id = Set(team_id);
+        let user = user.update(self.db.as_ref()).await?;
+        self.record_updated(&before, &user, "Changed the default team")
+            .await?;
+        Ok(user)
+    }
+
+    pub async fn mark_email_verified(&self, id: Uuid, email: &str) -> ModelResult {
+        let user = Users::find_by_id(id)
+            .one(self.db.as_ref())
+            .await?
+            .ok_or(DbErr::Custom("Cannot find user".to_owned()))?;
+
+        // The code was sent to an address the user has changed away from since.
+        if user.email != email {
+            return Err(DbErr::Custom(
+                "Verification code does not match the current email".to_string(),
+            ));
+        }
+
+        let mut model = user.clone().into_active_model();
+        model.email_verified_at = Set(Some(chrono::Utc::now().naive_utc()));
+        let model = model.update(self.db.as_ref()).await?;
+        self.record_updated(&user, &model, "Verified email").await?;
+        Ok(model)
+    }
+
+    pub async fn update_status_by_id(&self, id: Uuid, status: Status) -> ModelResult {
+        let mut user = Users::find_by_id(id)
+            .one(self.db.as_ref())
+            .await?
+            .ok_or(DbErr::Custom("Cannot find user".to_owned()))?
+            .into_active_model();
+
+        user.status = Set(status);
         user.update(self.db.as_ref()).await
     }
 
+    /// Refuse deactivated and suspended accounts. A suspension that has run out
+    /// is lifted on the way.
+    pub async fn ensure_active(&self, id: Uuid) -> DbResult<()> {
+        let user = self.find_status(id).await?;
+        self.check_status(id, user).await
+    }
+
+    /// `ensure_active` for a platform admin, checked in the same query.
+    pub async fn ensure_active_admin(&self, id: Uuid) -> DbResult<()> {
+        let user = self.find_status(id).await?;
+        if !user.platform_role.can_administer() {
+            return Err(DbErr::Custom(
+                "Only platform admins can do this".to_string(),
+            ));
+        }
+
+        self.check_status(id, user).await
+    }
+
+    /// `ensure_active` for the owner of a session, refusing revoked sessions
+    /// in the same query.
+    pub async fn ensure_active_session(&self, id: Uuid, session_id: Uuid) -> DbResult<()> {
+        let row = Sessions::find_by_id(session_id)
+            .filter(sessions::Column::UserId.eq(id))
+            .join(
+                JoinType::InnerJoin,
+                Sessions::belongs_to(Users)
+                    .from(sessions::Column::UserId)
+                    .to(users::Column::UserId)
+                    .into(),
+            )
+            .select_only()
+            .columns([
+                users::Column::Status,
+                users::Column::SuspendedReason,
+                users::Column::SuspendedUntil,
+                users::Column::PlatformRole,
+            ])
+            .column(sessions::Column::RevokedAt)
+            .into_model::<SessionStatusModel>()
+            .one(self.db.as_ref())
+            .await?
+            .ok_or(DbErr::Custom("Cannot find session".to_owned()))?;
+
+        if row.revoked_at.is_some() {
+            return Err(DbErr::Custom("Session has been revoked".to_string()));
+        }
+
+        let user = StatusUserModel {
+            status: row.status,
+            suspended_reason: row.suspended_reason,
+            suspended_until: row.suspended_until,
+            platform_role: row.platform_role,
+        };
+        self.check_status(id, user).await
+    }
+
+    async fn find_status(&self, id: Uuid) -> DbResult<StatusUserModel> {
+        Users::find_by_id(id)
+            .into_partial_model::<StatusUserModel>()
+            .one(self.db.as_ref())
+            .await?
+            .ok_or(DbErr::Custom("Cannot find user".to_owned()))
+    }
+
+    async fn check_status(&self, id: Uuid, user: StatusUserModel) -> DbResult<()> {
+        match user.status {
+            Status::Active => Ok(()),
+            Status::Inactive => Err(DbErr::Custom(
+                "This account has been deactivated".to_string(),
+            )),
+            Status::Suspended => {
+                let now = chrono::Utc::now().naive_utc();
+                if user.suspended_until.is_some_and(|until| until <= now) {
+                    self.reactivate_user(id).await?;
+                    log::info!("Suspension of user {} has ended", id);
+                    return Ok(());
+                }
+
+                let until = user
+                    .suspended_until
+                    .map_or("further notice".to_string(), |until| {
+                        until.format("%Y-%m-%d %H:%M:%S").to_string()
+                    });
+                Err(DbErr::Custom(match user.suspended_reason {
+                    Some(reason) => {
+                        format!("This account is suspended until {}: {}", until, reason)
+                    }
+                    None => format!("This account is suspended until {}", until),
+                }))
+            }
+        }
+    }
+
+    pub async fn suspend_user(
+        &self,
+        id: Uuid,
+        reason: Option<String>,
+        until: Option<DateTime>,
+    ) -> ModelResult {
+        if until.is_some_and(|until| until <= chrono::Utc::now().naive_utc()) {
+            return Err(DbErr::Custom(
+                "Suspension end must be in the future".to_string(),
+            ));
+        }
+
+        let mut user = Users::find_by_id(id)
+            .one(self.db.as_ref())
+            .await?
+            .ok_or(DbErr::Custom("Cannot find user".to_owned()))?
+            .into_active_model();
+
+        user.status = Set(Status::Suspended);
+        user.suspended_reason = Set(reason.filter(|reason| !reason.trim().is_empty()));
+        user.suspended_until = Set(until);
+        user.update(self.db.as_ref()).await
+    }
+
+    /// Make a suspended or deactivated account usable again.
+    pub async fn reactivate_user(&self, id: Uuid) -> ModelResult {
+        let mut user = Users::find_by_id(id)
+            .one(self.db.as_ref())
+            .await?
+            .ok_or(DbErr::Custom("Cannot find user".to_owned()))?
+            .into_active_model();
+
+        user.status = Set(Status::Active);
+        user.suspended_reason = Set(None);
+        user.suspended_until = Set(None);
+        user.update(self.db.as_ref()).await
+    }
+
+    pub async fn find_platform_role(&self, id: Uuid) -> DbResult<PlatformRoles> {
+        Ok(Users::find_by_id(id)
+            .one(self.db.as_ref())
+            .await?
+            .ok_or(DbErr::Custom("Cannot find user".to_owned()))?
+            .platform_role)
+    }
+
+    pub async fn is_platform_admin(&self, id: Uuid) -> DbResult<bool> {
+        Ok(self.find_platform_role(id).await?.can_administer())
+    }
+
+    pub async fn update_platform_role_by_id(&self, id: Uuid, role: PlatformRoles) -> ModelResult {
+        let mut user = Users::find_by_id(id)
+            .one(self.db.as_ref())
+            .await?
+            .ok_or(DbErr::Custom("Cannot find user".to_owned()))?
+            .into_active_model();
+
+        user.platform_role = Set(role);
+        user.update(self.db.as_ref()).await
+    }
+
+    /// Replace the password with a random one nobody knows, so the old one
+    /// stops working until the user resets it.
+    pub async fn scramble_password(&self, id: Uuid) -> ModelResult {
+        let mut user = Users::find_by_id(id)
+            .one(self.db.as_ref())
+            .await?
+            .ok_or(DbErr::Custom("Cannot find user".to_owned()))?
+            .into_active_model();
+
+        user.password = Set(self.password_policy.hash(&generator_random_token(32)));
+        user.update(self.db.as_ref()).await
+    }
+
+    /// Check the credentials and that the account may sign in.
     pub async fn verify_password_by_email(
         &self,
         email: &str,
         password: &str,
+    ) -> DbResult<VerifyUserModel> {
+        let user = self.verify_credentials_by_email(email, password).await?;
+        self.ensure_active(user.user_id).await?;
+
+        Ok(user)
+    }
+
+    /// Check the credentials only. Every failure is the same `LOGIN_FAILED`
+    /// error, whatever state the account is in.
+    pub async fn verify_credentials_by_email(
+        &self,
+        email: &str,
+        password: &str,
     ) -> DbResult<VerifyUserModel> {
         if password.trim().is_empty() {
             return Err(DbErr::Custom("Password cannot be empty".to_string()));
@@ -256,33 +683,61 @@ impl UserService {
                 users::Column::UserId,
                 users::Column::Email,
                 users::Column::Password,
+                users::Column::EmailVerifiedAt,
             ])
             .filter(users::Column::Email.eq(email))
             .into_model::<VerifyUserModel>()
             .one(self.db.as_ref())
-            .await?
-            .ok_or(DbErr::Custom("Cannot find user by the email.".to_string()))?;
+            .await?;
 
         let start = Instant::now();
         log::info!("Verify Password Start processing...");
 
-        if PassVerify::verify_password(password, &user.password).unwrap() == false {
-            return Err(DbErr::Custom("Incorrect password. You can try again or choose to reset your password.".to_string()));
-        }
+        // Unknown emails still pay for a hash check so timing doesn't tell them apart.
+        let hashed = user
+            .as_ref()
+            .map_or(self.password_policy.dummy_hash(), |user| {
+                user.password.as_str()
+            });
+        let matched = PassVerify::verify_password(password, hashed).map_err(|e| {
+            log::error!("Stored password hash of {} is malformed: {}", email, e);
+            DbErr::Custom(LOGIN_FAILED.to_string())
+        })?;
+        let user = user
+            .filter(|_| matched)
+            .ok_or(DbErr::Custom(LOGIN_FAILED.to_string()))?;
 
         let duration = start.elapsed();
         log::info!("Verify Password  Processing completed in {:?}", duration);
 
+        // The plain password is only at hand here, so this is where hashes made
+        // with older parameters get upgraded.
+        if self.password_policy.needs_rehash(&user.password) {
+            Users::update_many()
+                .col_expr(
+                    users::Column::Password,
+                    Expr::value(self.password_policy.hash(password)),
+                )
+                .filter(users::Column::UserId.eq(user.user_id))
+                .exec(self.db.as_ref())
+                .await?;
+            log::info!("Rehashed password of user {}", user.user_id);
+        }
+
         Ok(user)
     }
 
-    pub async fn delete_user(&self, id: Uuid) -> DbResult<DeleteResult> {
+    pub async fn delete_user<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        id: Uuid,
+    ) -> DbResult<DeleteResult> {
         let user = Users::find_by_id(id)
-            .one(self.db.as_ref())
+            .one(db)
             .await?
             .ok_or(DbErr::Custom("Cannot find user".to_owned()))?
             .into_active_model();
 
-        user.delete(self.db.as_ref()).await
+        user.delete(db).await
     }
 }
@@ -0,0 +1,149 @@
+use std::{sync::Arc, time::Duration};
+
+use crate::_entities::prelude::VerificationCodes;
+use crate::_entities::sea_orm_active_enums::CodePurposes;
+use crate::_entities::verification_codes;
+use crate::utils::encrypt::hash_token;
+use crate::utils::DbResult;
+use rand::{rngs::OsRng, Rng};
+use sea_orm::prelude::Uuid;
+use sea_orm::sea_query::Expr;
+use sea_orm::sqlx::types::chrono;
+use sea_orm::*;
+
+/// How long a code stays valid.
+const CODE_EXPIRE: Duration = Duration::from_secs(15 * 60);
+/// Wrong guesses allowed before a code is burned.
+const MAX_ATTEMPTS: i32 = 5;
+/// Minimum time between two codes for the same email.
+const SEND_INTERVAL: Duration = Duration::from_secs(60);
+/// Codes allowed per email within `SEND_WINDOW`.
+const SEND_LIMIT: u64 = 5;
+const SEND_WINDOW: Duration = Duration::from_secs(60 * 60);
+
+pub struct VerificationCodeService {
+    pub db: Arc<DatabaseConnection>,
+}
+
+impl VerificationCodeService {
+    pub fn new(db: Arc<DatabaseConnection>) -> Self {
+        Self { db }
+    }
+
+    /// Create a new code for `email`, invalidating any code still pending for
+    /// the same purpose. Returns the plain code, which is never stored.
+    pub async fn issue_code(
+        &self,
+        user_id: Uuid,
+        email: &str,
+        purpose: CodePurposes,
+    ) -> DbResult<String> {
+        self.check_throttle(email, purpose.clone()).await?;
+
+        let now = chrono::Utc::now().naive_utc();
+        VerificationCodes::update_many()
+            .col_expr(verification_codes::Column::UsedAt, Expr::value(now))
+            .filter(verification_codes::Column::Email.eq(email))
+            .filter(verification_codes::Column::Purpose.eq(purpose.clone()))
+            .filter(verification_codes::Column::UsedAt.is_null())
+            .exec(self.db.as_ref())
+            .await?;
+
+        let code = format!("{:06}", OsRng.gen_range(0..1_000_000));
+
+        verification_codes::ActiveModel {
+            user_id: Set(user_id),
+            email: Set(email.to_string()),
+            purpose: Set(purpose),
+            code_hash: Set(hash_token(&code)),
+            expires_at: Set(now + CODE_EXPIRE),
+            created_at: Set(now),
+            ..Default::default()
+        }
+        .insert(self.db.as_ref())
+        .await?;
+
+        Ok(code)
+    }
+
+    /// Check `code` against the pending code of `email`. With `consume` the
+    /// code is marked as used and cannot be checked again.
+    pub async fn verify_code(
+        &self,
+        email: &str,
+        purpose: CodePurposes,
+        code: &str,
+        consume: bool,
+    ) -> DbResult<verification_codes::Model> {
+        let now = chrono::Utc::now().naive_utc();
+        let invalid = || DbErr::Custom("Verification code is invalid or has expired".to_string());
+
+        let pending = VerificationCodes::find()
+            .filter(verification_codes::Column::Email.eq(email))
+            .filter(verification_codes::Column::Purpose.eq(purpose))
+            .filter(verification_codes::Column::UsedAt.is_null())
+            .filter(verification_codes::Column::ExpiresAt.gt(now))
+            .filter(verification_codes::Column::Attempts.lt(MAX_ATTEMPTS))
+            .order_by_desc(verification_codes::Column::CreatedAt)
+            .one(self.db.as_ref())
+            .await?
+            .ok_or_else(invalid)?;
+
+        if pending.code_hash != hash_token(code.trim()) {
+            VerificationCodes::update_many()
+                .col_expr(
+                    verification_codes::Column::Attempts,
+                    Expr::col(verification_codes::Column::Attempts).add(1),
+                )
+                .filter(verification_codes::Column::CodeId.eq(pending.code_id))
+                .exec(self.db.as_ref())
+                .await?;
+            return Err(invalid());
+        }
+
+        if consume {
+            // Guarded by `used_at IS NULL` so a code can only be consumed once.
+            let res = VerificationCodes::update_many()
+                .col_expr(verification_codes::Column::UsedAt, Expr::value(now))
+                .filter(verification_codes::Column::CodeId.eq(pending.code_id))
+                .filter(verification_codes::Column::UsedAt.is_null())
+                .exec(self.db.as_ref())
+                .await?;
+
+            if res.rows_affected == 0 {
+                return Err(invalid());
+            }
+        }
+
+        Ok(pending)
+    }
+
+    async fn check_throttle(&self, email: &str, purpose: CodePurposes) -> DbResult<()> {
+        let now = chrono::Utc::now().naive_utc();
+
+        let recent = VerificationCodes::find()
+            .filter(verification_codes::Column::Email.eq(email))
+            .filter(verification_codes::Column::Purpose.eq(purpose))
+            .filter(verification_codes::Column::CreatedAt.gt(now - SEND_WINDOW))
+            .order_by_desc(verification_codes::Column::CreatedAt)
+            .all(self.db.as_ref())
+            .await?;
+
+        if recent.len() as u64 >= SEND_LIMIT {
+            return Err(DbErr::Custom(
+                "Too many codes requested, please try again later".to_string(),
+            ));
+        }
+
+        if let Some(last) = recent.first() {
+            if last.created_at > now - SEND_INTERVAL {
+                return Err(DbErr::Custom(
+                    "A code was sent recently, please wait a minute before requesting another"
+                        .to_string(),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,155 @@
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UnverifiedAccess {
+    /// Unverified users may log in but only reach the routes needed to verify.
+    #[default]
+    Limited,
+    /// Unverified users may log in and use the whole API.
+    Full,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Deserialize)]
+pub struct AuthConfig {
+    #[serde(default)]
+    pub unverified_access: UnverifiedAccess,
+    /// Page the email verification link points to, `email` and `code` are
+    /// appended as query parameters.
+    pub verify_link: Option<String>,
+    /// Page the unlock link in the lockout mail points to, `email` and `code`
+    /// are appended as query parameters.
+    pub unlock_link: Option<String>,
+    /// Page the team invitation link points to, `token` is appended as query
+    /// parameter.
+    pub invite_link: Option<String>,
+    /// Issuer shown in authenticator apps, defaults to `MMS`.
+    pub totp_issuer: Option<String>,
+    /// Lifetime of impersonation tokens in milliseconds, defaults to 30 minutes.
+    pub impersonation_expire: Option<i64>,
+}
+
+/// Argon2id cost, the defaults are the ones recommended by OWASP.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(default)]
+pub struct Argon2Config {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl Default for Argon2Config {
+    fn default() -> Self {
+        Self {
+            memory_kib: 19 * 1024,
+            iterations: 2,
+            parallelism: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize)]
+#[serde(default)]
+pub struct PasswordPolicyConfig {
+    pub min_length: usize,
+    pub max_length: usize,
+    /// File of known breached passwords, one per line, compared case-insensitively.
+    pub denylist: Option<String>,
+    /// Changing these rehashes each password at the next successful login.
+    pub argon2: Argon2Config,
+}
+
+impl Default for PasswordPolicyConfig {
+    fn default() -> Self {
+        Self {
+            min_length: 8,
+            max_length: 128,
+            denylist: None,
+            argon2: Argon2Config::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize)]
+pub enum JwtAlgorithm {
+    HS256,
+    #[default]
+    RS256,
+    EdDSA,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize)]
+pub struct JwtKeyConfig {
+    pub kid: String,
+    /// PEM file of the private key. Keys without one are only used to verify
+    /// tokens signed before they were rotated out.
+    pub private_key: Option<String>,
+    /// PEM file of the public key, required for RS256 and EdDSA.
+    pub public_key: Option<String>,
+    /// Shared secret, HS256 only.
+    pub secret: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize)]
+pub struct JwtConfig {
+    #[serde(default)]
+    pub algorithm: JwtAlgorithm,
+    pub issuer: String,
+    pub audience: String,
+    /// Key used to sign new tokens, defaults to the first key able to sign.
+    pub active_kid: Option<String>,
+    pub keys: Vec<JwtKeyConfig>,
+}
+
+fn default_oidc_scopes() -> Vec<String> {
+    ["openid", "email", "profile"].map(String::from).to_vec()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize)]
+pub struct OidcProviderConfig {
+    /// Identifies the provider in requests and in `user_identities`.
+    pub name: String,
+    pub display_name: Option<String>,
+    /// Issuer URL, `/.well-known/openid-configuration` is fetched below it.
+    pub issuer: String,
+    pub client_id: String,
+    /// Sent with HTTP basic auth, leave out for public clients.
+    pub client_secret: Option<String>,
+    /// Frontend page the provider redirects back to with `code` and `state`.
+    pub redirect_uri: String,
+    #[serde(default = "default_oidc_scopes")]
+    pub scopes: Vec<String>,
+}
+
+/// What the purge job does with entries past their team's log retention.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RetentionAction {
+    /// Write them to gzipped JSON Lines files below `archive_dir`, then delete them.
+    #[default]
+    Archive,
+    Delete,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize)]
+#[serde(default)]
+pub struct AuditConfig {
+    /// Seconds between two purge runs, the first one runs at startup.
+    pub purge_interval: u64,
+    pub retention_action: RetentionAction,
+    pub archive_dir: String,
+    /// Days to keep entries outside of any team, such as account and platform
+    /// admin events. `None` keeps them forever.
+    pub platform_retention_days: Option<u32>,
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self {
+            purge_interval: 60 * 60,
+            retention_action: RetentionAction::Archive,
+            archive_dir: "./data/audit-archive".to_string(),
+            platform_retention_days: None,
+        }
+    }
+}
@@ -1,4 +1,7 @@
-use crate::_entities::sea_orm_active_enums::{Status, TeamUserRoles, TeamUserStatus};
+use crate::_entities::sea_orm_active_enums::{
+    Actions, PlatformRoles, Status, TargetTypes, TeamUserRoles, TeamUserStatus,
+};
+use sea_orm::ActiveEnum;
 use serde::{Deserialize, Serialize};
 
 impl Serialize for Status {
@@ -9,9 +12,53 @@ impl Serialize for Status {
         serializer.serialize_str(match self {
             Status::Inactive => "inactive",
             Status::Active => "active",
+            Status::Suspended => "suspended",
         })
     }
 }
+
+impl<'de> Deserialize<'de> for Status {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        match s.as_str() {
+            "active" => Ok(Status::Active),
+            "inactive" => Ok(Status::Inactive),
+            "suspended" => Ok(Status::Suspended),
+            _ => Err(serde::de::Error::custom("Invalid user status")),
+        }
+    }
+}
+
+impl Serialize for PlatformRoles {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(match self {
+            PlatformRoles::Admin => "admin",
+            PlatformRoles::SuperAdmin => "super_admin",
+            PlatformRoles::User => "user",
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for PlatformRoles {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        match s.as_str() {
+            "admin" => Ok(PlatformRoles::Admin),
+            "super_admin" => Ok(PlatformRoles::SuperAdmin),
+            "user" => Ok(PlatformRoles::User),
+            _ => Err(serde::de::Error::custom("Invalid platform role")),
+        }
+    }
+}
 impl Serialize for TeamUserStatus {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -70,16 +117,74 @@ impl<'de> Deserialize<'de> for TeamUserRoles {
     }
 }
 
+impl Serialize for Actions {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.to_value())
+    }
+}
+
+impl<'de> Deserialize<'de> for Actions {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        Actions::try_from_value(&s).map_err(|_| serde::de::Error::custom("Invalid action"))
+    }
+}
+
+impl Serialize for TargetTypes {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.to_value())
+    }
+}
+
+impl<'de> Deserialize<'de> for TargetTypes {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        TargetTypes::try_from_value(&s).map_err(|_| serde::de::Error::custom("Invalid target type"))
+    }
+}
+
 impl TeamUserRoles {
-    pub fn can_remove_team(&self) -> bool {
-        self.eq(&TeamUserRoles::Owner)
+    /// Position in the team hierarchy, a higher rank outranks a lower one.
+    pub fn rank(&self) -> u8 {
+        match self {
+            TeamUserRoles::Owner => 4,
+            TeamUserRoles::Admin => 3,
+            TeamUserRoles::Manager => 2,
+            TeamUserRoles::Member => 1,
+            TeamUserRoles::Guest => 0,
+        }
+    }
+
+    /// Only roles strictly below one's own can be handed out, Owner goes
+    /// through an ownership transfer.
+    pub fn can_assign_role(&self, role: &TeamUserRoles) -> bool {
+        self.rank() > role.rank()
+    }
+
+    /// Owners manage everyone, other roles only members they outrank.
+    pub fn can_manage_member(&self, member_role: &TeamUserRoles) -> bool {
+        self.eq(&TeamUserRoles::Owner) || self.rank() > member_role.rank()
     }
+}
 
-    pub fn can_update_team(&self) -> bool {
-        [TeamUserRoles::Owner, TeamUserRoles::Admin].contains(&self)
+impl PlatformRoles {
+    pub fn can_administer(&self) -> bool {
+        [PlatformRoles::SuperAdmin, PlatformRoles::Admin].contains(self)
     }
 
-    pub fn can_remove_user_by_team(&self) -> bool {
-        [TeamUserRoles::Owner, TeamUserRoles::Admin].contains(&self)
+    pub fn can_manage_admins(&self) -> bool {
+        self.eq(&PlatformRoles::SuperAdmin)
     }
 }
@@ -1,37 +1,155 @@
 pub mod _entities;
 pub mod common;
+pub mod config;
 pub mod enum_serialize;
+pub mod mailer;
+pub mod permission;
+pub mod store;
 pub mod utils;
 use std::sync::Arc;
 
 use common::{
-    auth_service::AuthService, team_service::TeamService, team_user_service::TeamUserService,
-    user_service::UserService,
+    activity_log_service::ActivityLogService, admin_service::AdminService,
+    api_key_service::ApiKeyService, auth_service::AuthService,
+    login_attempt_service::LoginAttemptService, mfa_service::MfaService,
+    notification_service::NotificationService, oidc_service::OidcService,
+    permission_service::PermissionService, session_service::SessionService,
+    team_invitation_service::TeamInvitationService,
+    team_join_request_service::TeamJoinRequestService,
+    team_ownership_service::TeamOwnershipService, team_service::TeamService,
+    team_user_service::TeamUserService, user_service::UserService,
+    verification_code_service::VerificationCodeService,
 };
+use config::{AuthConfig, OidcProviderConfig};
+use mailer::Mailer;
 pub use sea_orm;
 use sea_orm::DatabaseConnection;
+use store::RevocationStore;
+use utils::{jwt::Jwt, password_policy::PasswordPolicy};
 
 pub struct Service {
     pub db: Arc<DatabaseConnection>,
     pub auth_service: Arc<AuthService>,
+    pub api_key_service: Arc<ApiKeyService>,
+    pub login_attempt_service: Arc<LoginAttemptService>,
+    pub activity_log_service: Arc<ActivityLogService>,
+    pub admin_service: Arc<AdminService>,
     pub user_service: Arc<UserService>,
+    pub session_service: Arc<SessionService>,
+    pub mfa_service: Arc<MfaService>,
+    pub notification_service: Arc<NotificationService>,
+    pub oidc_service: Arc<OidcService>,
+    pub verification_code_service: Arc<VerificationCodeService>,
+    pub mailer: Arc<dyn Mailer>,
     pub team_service: Arc<TeamService>,
     pub team_user_service: Arc<TeamUserService>,
+    pub team_invitation_service: Arc<TeamInvitationService>,
+    pub team_join_request_service: Arc<TeamJoinRequestService>,
+    pub team_ownership_service: Arc<TeamOwnershipService>,
+    pub permission_service: Arc<PermissionService>,
 }
 
 impl Service {
-    pub fn new(db: Arc<DatabaseConnection>) -> Self {
-        let user_service = Arc::new(UserService::new(db.clone()));
-        let team_service = Arc::new(TeamService::new(db.clone()));
-        let auth_service = Arc::new(AuthService::new(db.clone(), user_service.clone()));
-        let team_user_service = Arc::new(TeamUserService::new(db.clone()));
+    pub fn new(
+        db: Arc<DatabaseConnection>,
+        auth_config: AuthConfig,
+        oidc_providers: Vec<OidcProviderConfig>,
+        jwt: Arc<Jwt>,
+        password_policy: Arc<PasswordPolicy>,
+        revocation_store: Arc<dyn RevocationStore>,
+        mailer: Arc<dyn Mailer>,
+    ) -> Self {
+        let activity_log_service = Arc::new(ActivityLogService::new(db.clone()));
+        let admin_service = Arc::new(AdminService::new(db.clone()));
+        let api_key_service =
+            Arc::new(ApiKeyService::new(db.clone(), activity_log_service.clone()));
+        let login_attempt_service = Arc::new(LoginAttemptService::new(db.clone()));
+        let user_service = Arc::new(UserService::new(
+            db.clone(),
+            password_policy,
+            activity_log_service.clone(),
+        ));
+        let session_service = Arc::new(SessionService::new(db.clone()));
+        let mfa_service = Arc::new(MfaService::new(
+            db.clone(),
+            auth_config
+                .totp_issuer
+                .clone()
+                .unwrap_or_else(|| "MMS".to_string()),
+            activity_log_service.clone(),
+        ));
+        let notification_service = Arc::new(NotificationService::new(db.clone()));
+        let oidc_service = Arc::new(OidcService::new(db.clone(), oidc_providers));
+        let verification_code_service = Arc::new(VerificationCodeService::new(db.clone()));
+        let team_service = Arc::new(TeamService::new(db.clone(), activity_log_service.clone()));
+        let team_user_service = Arc::new(TeamUserService::new(
+            db.clone(),
+            activity_log_service.clone(),
+        ));
+        let team_invitation_service = Arc::new(TeamInvitationService::new(
+            db.clone(),
+            auth_config.invite_link.clone(),
+            mailer.clone(),
+            user_service.clone(),
+            team_user_service.clone(),
+            notification_service.clone(),
+            activity_log_service.clone(),
+        ));
+        let team_join_request_service = Arc::new(TeamJoinRequestService::new(
+            db.clone(),
+            team_user_service.clone(),
+            notification_service.clone(),
+            activity_log_service.clone(),
+        ));
+        let team_ownership_service = Arc::new(TeamOwnershipService::new(
+            db.clone(),
+            notification_service.clone(),
+            activity_log_service.clone(),
+        ));
+        let permission_service = Arc::new(PermissionService::new(
+            db.clone(),
+            team_user_service.clone(),
+            activity_log_service.clone(),
+        ));
+        let auth_service = Arc::new(AuthService::new(
+            db.clone(),
+            auth_config,
+            jwt,
+            activity_log_service.clone(),
+            api_key_service.clone(),
+            login_attempt_service.clone(),
+            user_service.clone(),
+            session_service.clone(),
+            team_user_service.clone(),
+            team_invitation_service.clone(),
+            mfa_service.clone(),
+            notification_service.clone(),
+            oidc_service.clone(),
+            verification_code_service.clone(),
+            revocation_store,
+            mailer.clone(),
+        ));
 
         Self {
             db,
             auth_service,
+            api_key_service,
+            login_attempt_service,
+            activity_log_service,
+            admin_service,
             user_service,
+            session_service,
+            mfa_service,
+            notification_service,
+            oidc_service,
+            verification_code_service,
+            mailer,
             team_service,
             team_user_service,
+            team_invitation_service,
+            team_join_request_service,
+            team_ownership_service,
+            permission_service,
         }
     }
 }
@@ -0,0 +1,42 @@
+use std::{fs, path::PathBuf};
+
+use async_trait::async_trait;
+use sea_orm::DbErr;
+
+use super::{MailMessage, Mailer};
+use crate::utils::{jwt::get_current_timestamp, DbResult};
+
+pub struct FileMailer {
+    from: String,
+    dir: PathBuf,
+}
+
+impl FileMailer {
+    pub fn new(from: &str, dir: &str) -> Self {
+        Self {
+            from: from.to_string(),
+            dir: PathBuf::from(dir),
+        }
+    }
+}
+
+#[async_trait]
+impl Mailer for FileMailer {
+    async fn send(&self, message: MailMessage) -> DbResult<()> {
+        fs::create_dir_all(&self.dir)
+            .map_err(|e| DbErr::Custom(format!("Create mail dir error: {}", e)))?;
+
+        let name = format!(
+            "{}-{}.eml",
+            get_current_timestamp(),
+            message.to.replace(['@', '/', '\\'], "_")
+        );
+        let content = format!(
+            "From: {}\r\nTo: {}\r\nSubject: {}\r\nContent-Type: text/plain; charset=utf-8\r\n\r\n{}\r\n",
+            self.from, message.to, message.subject, message.body
+        );
+
+        fs::write(self.dir.join(name), content)
+            .map_err(|e| DbErr::Custom(format!("Write mail file error: {}", e)))
+    }
+}
@@ -0,0 +1,20 @@
+use async_trait::async_trait;
+
+use super::{MailMessage, Mailer};
+use crate::utils::DbResult;
+
+#[derive(Default)]
+pub struct LogMailer;
+
+#[async_trait]
+impl Mailer for LogMailer {
+    async fn send(&self, message: MailMessage) -> DbResult<()> {
+        log::info!(
+            "\nMail to: {}\nSubject: {}\n\n{}",
+            message.to,
+            message.subject,
+            message.body
+        );
+        Ok(())
+    }
+}
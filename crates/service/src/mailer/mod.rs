@@ -0,0 +1,53 @@
+pub mod file;
+pub mod log;
+pub mod smtp;
+
+use async_trait::async_trait;
+use serde::Deserialize;
+
+use crate::utils::DbResult;
+
+pub use file::FileMailer;
+pub use log::LogMailer;
+pub use smtp::SmtpMailer;
+
+#[derive(Debug, Clone)]
+pub struct MailMessage {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpEncryption {
+    Tls,
+    #[default]
+    Starttls,
+    /// Plain text, only meant for local catch-all servers.
+    None,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Deserialize)]
+#[serde(tag = "backend", rename_all = "lowercase")]
+pub enum MailerConfig {
+    /// Only write the message to the application log.
+    #[default]
+    Log,
+    /// Drop every message as an `.eml` file into `dir`.
+    File { from: String, dir: String },
+    Smtp {
+        from: String,
+        host: String,
+        port: Option<u16>,
+        username: Option<String>,
+        password: Option<String>,
+        #[serde(default)]
+        encryption: SmtpEncryption,
+    },
+}
+
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, message: MailMessage) -> DbResult<()>;
+}
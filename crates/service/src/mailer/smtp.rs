@@ -0,0 +1,76 @@
+use async_trait::async_trait;
+use lettre::{
+    message::{header::ContentType, Mailbox},
+    transport::smtp::authentication::Credentials,
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+use sea_orm::DbErr;
+
+use super::{MailMessage, Mailer, SmtpEncryption};
+use crate::utils::DbResult;
+
+pub struct SmtpMailer {
+    from: Mailbox,
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+}
+
+impl SmtpMailer {
+    pub fn new(
+        from: &str,
+        host: &str,
+        port: Option<u16>,
+        credentials: Option<(String, String)>,
+        encryption: SmtpEncryption,
+    ) -> DbResult<Self> {
+        let from = from
+            .parse::<Mailbox>()
+            .map_err(|e| DbErr::Custom(format!("Invalid mail sender: {}", e)))?;
+
+        let mut builder = match encryption {
+            SmtpEncryption::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(host),
+            SmtpEncryption::Starttls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host),
+            SmtpEncryption::None => Ok(AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(
+                host,
+            )),
+        }
+        .map_err(|e| DbErr::Custom(format!("Invalid smtp host: {}", e)))?;
+
+        if let Some(port) = port {
+            builder = builder.port(port);
+        }
+
+        if let Some((username, password)) = credentials {
+            builder = builder.credentials(Credentials::new(username, password));
+        }
+
+        Ok(Self {
+            from,
+            transport: builder.build(),
+        })
+    }
+}
+
+#[async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, message: MailMessage) -> DbResult<()> {
+        let to = message
+            .to
+            .parse::<Mailbox>()
+            .map_err(|e| DbErr::Custom(format!("Invalid mail recipient: {}", e)))?;
+
+        let email = Message::builder()
+            .from(self.from.clone())
+            .to(to)
+            .subject(message.subject)
+            .header(ContentType::TEXT_PLAIN)
+            .body(message.body)
+            .map_err(|e| DbErr::Custom(format!("Build mail error: {}", e)))?;
+
+        self.transport
+            .send(email)
+            .await
+            .map_err(|e| DbErr::Custom(format!("Send mail error: {}", e)))?;
+
+        Ok(())
+    }
+}
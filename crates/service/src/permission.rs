@@ -0,0 +1,146 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::_entities::sea_orm_active_enums::TeamUserRoles;
+
+/// What a team member may do, checked through
+/// [`PermissionService::authorize`](crate::common::permission_service::PermissionService::authorize).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Permission {
+    TeamView,
+    TeamUpdate,
+    TeamDelete,
+    TeamSecurity,
+    TeamTransfer,
+    MemberInvite,
+    MemberReview,
+    MemberUpdateRole,
+    MemberRemove,
+    RoleManage,
+    ApiKeyManage,
+    LogView,
+    CampaignView,
+    CampaignWrite,
+}
+
+impl Permission {
+    pub const ALL: [Permission; 14] = [
+        Permission::TeamView,
+        Permission::TeamUpdate,
+        Permission::TeamDelete,
+        Permission::TeamSecurity,
+        Permission::TeamTransfer,
+        Permission::MemberInvite,
+        Permission::MemberReview,
+        Permission::MemberUpdateRole,
+        Permission::MemberRemove,
+        Permission::RoleManage,
+        Permission::ApiKeyManage,
+        Permission::LogView,
+        Permission::CampaignView,
+        Permission::CampaignWrite,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Permission::TeamView => "team.view",
+            Permission::TeamUpdate => "team.update",
+            Permission::TeamDelete => "team.delete",
+            Permission::TeamSecurity => "team.security",
+            Permission::TeamTransfer => "team.transfer",
+            Permission::MemberInvite => "member.invite",
+            Permission::MemberReview => "member.review",
+            Permission::MemberUpdateRole => "member.update_role",
+            Permission::MemberRemove => "member.remove",
+            Permission::RoleManage => "role.manage",
+            Permission::ApiKeyManage => "api_key.manage",
+            Permission::LogView => "log.view",
+            Permission::CampaignView => "campaign.view",
+            Permission::CampaignWrite => "campaign.write",
+        }
+    }
+
+    /// Kept to Owners, a custom role cannot grant these.
+    pub fn is_owner_only(&self) -> bool {
+        [
+            Permission::TeamDelete,
+            Permission::TeamSecurity,
+            Permission::TeamTransfer,
+        ]
+        .contains(self)
+    }
+
+    /// Message for a member lacking the permission.
+    pub fn denied(&self) -> String {
+        format!("Missing the `{}` permission", self)
+    }
+}
+
+impl fmt::Display for Permission {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Permission {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Permission::ALL
+            .into_iter()
+            .find(|permission| permission.as_str() == s)
+            .ok_or(format!("Unknown permission `{}`", s))
+    }
+}
+
+impl Serialize for Permission {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Permission {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+impl TeamUserRoles {
+    /// Permissions of the built-in roles, a member with a custom role gets
+    /// that role's set instead.
+    pub fn permissions(&self) -> &'static [Permission] {
+        match self {
+            TeamUserRoles::Owner => &Permission::ALL,
+            TeamUserRoles::Admin => &[
+                Permission::TeamView,
+                Permission::TeamUpdate,
+                Permission::MemberInvite,
+                Permission::MemberReview,
+                Permission::MemberUpdateRole,
+                Permission::MemberRemove,
+                Permission::RoleManage,
+                Permission::ApiKeyManage,
+                Permission::LogView,
+                Permission::CampaignView,
+                Permission::CampaignWrite,
+            ],
+            TeamUserRoles::Manager => &[
+                Permission::TeamView,
+                Permission::CampaignView,
+                Permission::CampaignWrite,
+            ],
+            TeamUserRoles::Member | TeamUserRoles::Guest => {
+                &[Permission::TeamView, Permission::CampaignView]
+            }
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use async_trait::async_trait;
+use sea_orm::prelude::Uuid;
+
+use super::RevocationStore;
+use crate::utils::{
+    jwt::{get_current_timestamp, TokenInfo},
+    DbResult,
+};
+
+#[derive(Default)]
+pub struct MemoryRevocationStore {
+    tokens: Mutex<HashMap<String, i64>>,
+    users: Mutex<HashMap<Uuid, i64>>,
+}
+
+#[async_trait]
+impl RevocationStore for MemoryRevocationStore {
+    async fn revoke_token(&self, jti: &str, expires_at: i64) -> DbResult<()> {
+        let now = get_current_timestamp();
+        let mut tokens = self.tokens.lock().unwrap();
+        tokens.retain(|_, expires_at| *expires_at > now);
+        tokens.insert(jti.to_string(), expires_at);
+        Ok(())
+    }
+
+    async fn revoke_user(&self, user_id: Uuid, before: i64) -> DbResult<()> {
+        self.users.lock().unwrap().insert(user_id, before);
+        Ok(())
+    }
+
+    async fn is_revoked(&self, info: &TokenInfo) -> DbResult<bool> {
+        if self.tokens.lock().unwrap().contains_key(&info.jti) {
+            return Ok(true);
+        }
+
+        Ok(self
+            .users
+            .lock()
+            .unwrap()
+            .get(&info.user_id)
+            .is_some_and(|before| info.issued_at <= *before))
+    }
+}
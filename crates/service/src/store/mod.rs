@@ -0,0 +1,34 @@
+pub mod memory;
+pub mod postgres;
+
+use async_trait::async_trait;
+use sea_orm::prelude::Uuid;
+use serde::Deserialize;
+
+use crate::utils::{jwt::TokenInfo, DbResult};
+
+pub use memory::MemoryRevocationStore;
+pub use postgres::PgRevocationStore;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RevocationStoreKind {
+    /// Per-process store, revocations are lost on restart.
+    Memory,
+    #[default]
+    Postgres,
+}
+
+/// Storage for revoked access tokens.
+///
+/// All timestamps are unix milliseconds, the same unit `TokenInfo` uses.
+#[async_trait]
+pub trait RevocationStore: Send + Sync {
+    /// Revoke a single token until it would have expired anyway.
+    async fn revoke_token(&self, jti: &str, expires_at: i64) -> DbResult<()>;
+
+    /// Revoke every token of the user issued at or before `before`.
+    async fn revoke_user(&self, user_id: Uuid, before: i64) -> DbResult<()>;
+
+    async fn is_revoked(&self, info: &TokenInfo) -> DbResult<bool>;
+}
@@ -0,0 +1,100 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use sea_orm::prelude::Uuid;
+use sea_orm::sea_query::{Alias, Expr, OnConflict, Query};
+use sea_orm::sqlx::types::chrono;
+use sea_orm::*;
+
+use super::RevocationStore;
+use crate::_entities::prelude::{RevokedTokens, UserTokenRevocations};
+use crate::_entities::{revoked_tokens, user_token_revocations};
+use crate::utils::{jwt::TokenInfo, DbResult};
+
+pub struct PgRevocationStore {
+    db: Arc<DatabaseConnection>,
+}
+
+impl PgRevocationStore {
+    pub fn new(db: Arc<DatabaseConnection>) -> Self {
+        Self { db }
+    }
+
+    fn to_date_time(timestamp: i64) -> chrono::NaiveDateTime {
+        chrono::DateTime::from_timestamp_millis(timestamp)
+            .unwrap_or_default()
+            .naive_utc()
+    }
+}
+
+#[async_trait]
+impl RevocationStore for PgRevocationStore {
+    async fn revoke_token(&self, jti: &str, expires_at: i64) -> DbResult<()> {
+        RevokedTokens::delete_many()
+            .filter(revoked_tokens::Column::ExpiresAt.lt(chrono::Utc::now().naive_utc()))
+            .exec(self.db.as_ref())
+            .await?;
+
+        RevokedTokens::insert(revoked_tokens::ActiveModel {
+            jti: Set(jti.to_string()),
+            expires_at: Set(Self::to_date_time(expires_at)),
+            ..Default::default()
+        })
+        .on_conflict(
+            OnConflict::column(revoked_tokens::Column::Jti)
+                .do_nothing()
+                .to_owned(),
+        )
+        .exec_without_returning(self.db.as_ref())
+        .await?;
+
+        Ok(())
+    }
+
+    async fn revoke_user(&self, user_id: Uuid, before: i64) -> DbResult<()> {
+        UserTokenRevocations::insert(user_token_revocations::ActiveModel {
+            user_id: Set(user_id),
+            revoked_before: Set(Self::to_date_time(before)),
+        })
+        .on_conflict(
+            OnConflict::column(user_token_revocations::Column::UserId)
+                .update_column(user_token_revocations::Column::RevokedBefore)
+                .to_owned(),
+        )
+        .exec_without_returning(self.db.as_ref())
+        .await?;
+
+        Ok(())
+    }
+
+    /// Runs on every request, so both tables are checked in one query.
+    async fn is_revoked(&self, info: &TokenInfo) -> DbResult<bool> {
+        let token = Query::select()
+            .expr(Expr::val(1))
+            .from(RevokedTokens)
+            .and_where(revoked_tokens::Column::Jti.eq(info.jti.clone()))
+            .to_owned();
+        let user = Query::select()
+            .expr(Expr::val(1))
+            .from(UserTokenRevocations)
+            .and_where(user_token_revocations::Column::UserId.eq(info.user_id))
+            .and_where(
+                user_token_revocations::Column::RevokedBefore
+                    .gte(Self::to_date_time(info.issued_at)),
+            )
+            .to_owned();
+        let query = Query::select()
+            .expr_as(
+                Expr::exists(token).or(Expr::exists(user)),
+                Alias::new("revoked"),
+            )
+            .to_owned();
+
+        let row = self
+            .db
+            .query_one(self.db.get_database_backend().build(&query))
+            .await?
+            .ok_or(DbErr::Custom("Check token revocation error".to_string()))?;
+        row.try_get("", "revoked")
+    }
+}
@@ -1,30 +1,69 @@
 use argon2::{
-    password_hash::{rand_core::OsRng, PasswordHasher, SaltString},
-    Argon2, Error, PasswordHash, PasswordVerifier,
+    password_hash::{
+        rand_core::{OsRng, RngCore},
+        Error as HashError, PasswordHasher, SaltString,
+    },
+    Algorithm, Argon2, Error, Params, PasswordHash, PasswordVerifier, Version,
 };
 use sha2::{Digest, Sha256};
 
-pub struct PassVerify;
+use crate::config::Argon2Config;
+
+/// Hashes with the configured Argon2id parameters. They end up in the PHC
+/// string of every hash, which is how outdated hashes are recognised.
+pub struct PassVerify {
+    params: Params,
+}
 
-// TODO: Hash salt
 impl PassVerify {
-    pub fn encrypt_password(password: &str) -> String {
+    pub fn new(config: &Argon2Config) -> Result<Self, Error> {
+        let params = Params::new(
+            config.memory_kib,
+            config.iterations,
+            config.parallelism,
+            None,
+        )?;
+        Ok(Self { params })
+    }
+
+    pub fn encrypt_password(&self, password: &str) -> String {
         let salt = SaltString::generate(&mut OsRng);
-        let argon2 = Argon2::default();
-        argon2
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, self.params.clone())
             .hash_password(password.as_bytes(), &salt)
             .unwrap()
             .to_string()
     }
 
-    pub fn verify_password(password: &str, hashed: &str) -> Result<bool, Error> {
-        let parsed_hash = PasswordHash::new(&hashed).unwrap();
+    /// Verification reads the parameters from the hash itself.
+    pub fn verify_password(password: &str, hashed: &str) -> Result<bool, HashError> {
+        let parsed_hash = PasswordHash::new(hashed)?;
         let res = Argon2::default().verify_password(password.as_bytes(), &parsed_hash);
         match res {
             Ok(_) => Ok(true),
             _ => Ok(false),
         }
     }
+
+    /// Whether `hashed` was made with other parameters than the configured ones.
+    pub fn needs_rehash(&self, hashed: &str) -> bool {
+        let Ok(parsed_hash) = PasswordHash::new(hashed) else {
+            return true;
+        };
+        if parsed_hash.algorithm != Algorithm::Argon2id.ident()
+            || parsed_hash.version != Some(Version::V0x13.into())
+        {
+            return true;
+        }
+
+        match Params::try_from(&parsed_hash) {
+            Ok(params) => {
+                params.m_cost() != self.params.m_cost()
+                    || params.t_cost() != self.params.t_cost()
+                    || params.p_cost() != self.params.p_cost()
+            }
+            Err(_) => true,
+        }
+    }
 }
 
 pub fn generator_unique_id(email: &str) -> String {
@@ -39,3 +78,17 @@ pub fn generator_unique_id(email: &str) -> String {
     let result = hasher.finalize();
     format!("{:x}", result)
 }
+
+/// Random hex string built from `bytes` bytes of OS randomness, used for opaque tokens.
+pub fn generator_random_token(bytes: usize) -> String {
+    let mut buf = vec![0u8; bytes];
+    OsRng.fill_bytes(&mut buf);
+    buf.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Opaque tokens are only stored as their SHA-256 digest.
+pub fn hash_token(token: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(token.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
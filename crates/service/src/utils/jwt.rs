@@ -1,90 +1,410 @@
-use std::{collections::BTreeMap, env::var};
+use std::{collections::HashMap, fs};
 
-use hmac::Hmac;
-use jwt::{Error, SignWithKey, VerifyWithKey};
-use sea_orm::sqlx::types::chrono;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use ed25519_dalek::{pkcs8::DecodePublicKey, VerifyingKey};
+use jsonwebtoken::{
+    crypto, decode, decode_header, encode,
+    errors::{Error, ErrorKind},
+    jwk::{
+        AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, JwkSet, KeyAlgorithm,
+        OctetKeyPairParameters, OctetKeyPairType, PublicKeyUse, RSAKeyParameters, RSAKeyType,
+    },
+    Algorithm, DecodingKey, EncodingKey, Header, Validation,
+};
+use rsa::{pkcs1::DecodeRsaPublicKey, traits::PublicKeyParts, RsaPublicKey};
+use sea_orm::{prelude::Uuid, sqlx::types::chrono, DbErr};
 use serde::{Deserialize, Serialize};
-use sha2::{digest::KeyInit, Sha256};
+
+use super::{encrypt::generator_random_token, DbResult};
+use crate::config::{JwtAlgorithm, JwtConfig, JwtKeyConfig};
 
 #[derive(Debug, Deserialize, Serialize, Clone, Copy)]
 pub struct TokenPayload {
-    pub user_id: sea_orm::prelude::Uuid,
-    pub expire: i64, // Changed to i64 for timestamp
+    pub user_id: Uuid,
+    pub session_id: Option<Uuid>,
+    /// Only allows the routes an unverified account may use.
+    pub limited: bool,
+    /// Platform admin acting as `user_id`.
+    pub actor_id: Option<Uuid>,
+    /// Lifetime in milliseconds.
+    pub expire: i64,
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone, Copy)]
+/// RFC 8693 `act` claim, who is acting on behalf of `sub`.
+#[derive(Debug, Deserialize, Serialize)]
+struct Actor {
+    sub: Uuid,
+}
+
+/// Registered claims plus the few private ones the API relies on.
+#[derive(Debug, Deserialize, Serialize)]
+struct Claims {
+    sub: Uuid,
+    iss: String,
+    aud: String,
+    iat: i64,
+    nbf: i64,
+    exp: i64,
+    jti: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sid: Option<Uuid>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    limited: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    act: Option<Actor>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct TokenInfo {
-    pub user_id: sea_orm::prelude::Uuid,
-    pub expire: i64,
-    pub sign_time: i64,
+    pub user_id: Uuid,
+    pub session_id: Option<Uuid>,
+    pub jti: String,
+    pub limited: bool,
+    pub actor_id: Option<Uuid>,
+    /// Millisecond timestamps, with the second precision of `iat` and `exp`.
+    pub issued_at: i64,
+    pub expires_at: i64,
 }
 
-impl TokenInfo {
-    pub fn new(user_id: sea_orm::prelude::Uuid, expire: i64) -> Self {
-        let now_timestamp = get_current_timestamp();
+impl From<Claims> for TokenInfo {
+    fn from(claims: Claims) -> Self {
         Self {
-            user_id,
-            expire,
-            sign_time: now_timestamp + expire,
+            user_id: claims.sub,
+            session_id: claims.sid,
+            jti: claims.jti,
+            limited: claims.limited,
+            actor_id: claims.act.map(|actor| actor.sub),
+            issued_at: claims.iat * 1000,
+            expires_at: claims.exp * 1000,
         }
     }
 }
 
-pub struct Jwt;
-
-fn get_current_timestamp() -> i64 {
+pub fn get_current_timestamp() -> i64 {
     chrono::Utc::now().timestamp_millis()
 }
 
+/// Signs access tokens with the active key and verifies them with any
+/// configured key, picked by the `kid` header.
+pub struct Jwt {
+    algorithm: Algorithm,
+    issuer: String,
+    audience: String,
+    signing_kid: String,
+    signing_key: EncodingKey,
+    verify_keys: HashMap<String, DecodingKey>,
+    jwks: JwkSet,
+}
+
 impl Jwt {
-    pub fn get_key() -> hmac::Hmac<Sha256> {
-        let secret_key = var("JWT_SECRET").unwrap_or_else(|_| "default_jwt_secret".to_string());
-        Hmac::new_from_slice(secret_key.as_bytes()).unwrap()
-    }
-
-    pub fn sign(payload: TokenPayload) -> Result<String, Error> {
-        let key = Jwt::get_key();
-        let mut claims = BTreeMap::new();
-        let info = TokenInfo::new(payload.user_id, payload.expire);
-        log::info!("JWT sign info: \n{:#?}", &info);
-        claims.insert(
-            "info",
-            serde_json::to_string(&info).map_err(|_| Error::InvalidSignature)?,
-        );
-        claims.sign_with_key(&key)
-    }
+    pub fn new(config: &JwtConfig) -> DbResult<Self> {
+        let algorithm = match config.algorithm {
+            JwtAlgorithm::HS256 => Algorithm::HS256,
+            JwtAlgorithm::RS256 => Algorithm::RS256,
+            JwtAlgorithm::EdDSA => Algorithm::EdDSA,
+        };
+
+        let mut signing_keys = HashMap::new();
+        let mut verify_keys = HashMap::new();
+        let mut jwks = JwkSet { keys: Vec::new() };
 
-    pub fn verify(token: &str) -> Result<TokenInfo, Error> {
-        let claims = Self::get_claims(token)?;
-        let payload = Self::extract_payload(&claims)?;
-        let now_timestamp = get_current_timestamp();
+        for key in &config.keys {
+            if verify_keys.contains_key(&key.kid) {
+                return Err(Self::config_error(&key.kid, "is configured twice"));
+            }
+
+            let (signing_key, verify_key, jwk) = Self::load_key(key, algorithm)?;
+            if let Some(signing_key) = signing_key {
+                Self::check_key_pair(algorithm, &signing_key, &verify_key)
+                    .map_err(|_| Self::config_error(&key.kid, "does not match its public key"))?;
+                signing_keys.insert(key.kid.clone(), signing_key);
+            }
+            if let Some(jwk) = jwk {
+                jwks.keys.push(jwk);
+            }
+            verify_keys.insert(key.kid.clone(), verify_key);
+        }
+
+        let signing_kid = config
+            .active_kid
+            .clone()
+            .or_else(|| {
+                config
+                    .keys
+                    .iter()
+                    .find(|key| signing_keys.contains_key(&key.kid))
+                    .map(|key| key.kid.clone())
+            })
+            .ok_or(DbErr::Custom(
+                "No JWT key is able to sign tokens".to_string(),
+            ))?;
+        let signing_key = signing_keys
+            .remove(&signing_kid)
+            .ok_or_else(|| Self::config_error(&signing_kid, "has no private key to sign with"))?;
 
         log::info!(
-            "Verify payload: \n {:#?} \n Now timestamp: {}",
-            &payload,
-            &now_timestamp
+            "JWT signing with {:?} key `{}`, {} key(s) accepted",
+            algorithm,
+            &signing_kid,
+            verify_keys.len()
         );
 
-        if payload.sign_time <= now_timestamp {
-            return Err(Error::InvalidSignature);
+        Ok(Self {
+            algorithm,
+            issuer: config.issuer.clone(),
+            audience: config.audience.clone(),
+            signing_kid,
+            signing_key,
+            verify_keys,
+            jwks,
+        })
+    }
+
+    /// Public keys for other services to verify our tokens, empty for HS256.
+    pub fn jwks(&self) -> &JwkSet {
+        &self.jwks
+    }
+
+    pub fn sign(&self, payload: TokenPayload) -> Result<String, Error> {
+        let now = get_current_timestamp();
+        let claims = Claims {
+            sub: payload.user_id,
+            iss: self.issuer.clone(),
+            aud: self.audience.clone(),
+            iat: now / 1000,
+            nbf: now / 1000,
+            exp: (now + payload.expire) / 1000,
+            jti: generator_random_token(16),
+            sid: payload.session_id,
+            limited: payload.limited,
+            act: payload.actor_id.map(|sub| Actor { sub }),
+        };
+        log::info!("JWT sign claims: \n{:#?}", &claims);
+
+        let header = Header {
+            kid: Some(self.signing_kid.clone()),
+            ..Header::new(self.algorithm)
+        };
+        encode(&header, &claims, &self.signing_key)
+    }
+
+    /// Check signature, issuer, audience and the validity window.
+    pub fn verify(&self, token: &str) -> Result<TokenInfo, Error> {
+        let info = self.decode(token, true)?;
+        log::info!("Verify token info: \n {:#?}", &info);
+        Ok(info)
+    }
+
+    /// Like [`Jwt::verify`] but also accepts expired tokens.
+    pub fn extract_info(&self, token: &str) -> Result<TokenInfo, Error> {
+        self.decode(token, false)
+    }
+
+    fn decode(&self, token: &str, validate_exp: bool) -> Result<TokenInfo, Error> {
+        let kid = decode_header(token)?
+            .kid
+            .ok_or(Error::from(ErrorKind::InvalidToken))?;
+        let key = self
+            .verify_keys
+            .get(&kid)
+            .ok_or(Error::from(ErrorKind::InvalidToken))?;
+
+        let mut validation = Validation::new(self.algorithm);
+        validation.set_issuer(&[&self.issuer]);
+        validation.set_audience(&[&self.audience]);
+        validation.set_required_spec_claims(&["sub", "iss", "aud", "exp", "nbf"]);
+        validation.validate_nbf = true;
+        validation.validate_exp = validate_exp;
+
+        Ok(decode::<Claims>(token, key, &validation)?.claims.into())
+    }
+
+    fn load_key(
+        key: &JwtKeyConfig,
+        algorithm: Algorithm,
+    ) -> DbResult<(Option<EncodingKey>, DecodingKey, Option<Jwk>)> {
+        if algorithm == Algorithm::HS256 {
+            let secret = key
+                .secret
+                .as_deref()
+                .filter(|secret| !secret.is_empty())
+                .ok_or_else(|| Self::config_error(&key.kid, "has no secret"))?;
+            return Ok((
+                Some(EncodingKey::from_secret(secret.as_bytes())),
+                DecodingKey::from_secret(secret.as_bytes()),
+                None,
+            ));
         }
 
-        Ok(payload)
+        let public_pem = key
+            .public_key
+            .as_deref()
+            .ok_or_else(|| Self::config_error(&key.kid, "has no public key"))
+            .and_then(|path| Self::read_pem(&key.kid, path))?;
+        let key_params = match algorithm {
+            Algorithm::RS256 => {
+                let public_key = RsaPublicKey::from_public_key_pem(&public_pem)
+                    .or_else(|_| RsaPublicKey::from_pkcs1_pem(&public_pem))
+                    .map_err(|_| Self::config_error(&key.kid, "is not a RSA public key"))?;
+                AlgorithmParameters::RSA(RSAKeyParameters {
+                    key_type: RSAKeyType::RSA,
+                    n: URL_SAFE_NO_PAD.encode(public_key.n().to_bytes_be()),
+                    e: URL_SAFE_NO_PAD.encode(public_key.e().to_bytes_be()),
+                })
+            }
+            _ => {
+                let public_key = VerifyingKey::from_public_key_pem(&public_pem)
+                    .map_err(|_| Self::config_error(&key.kid, "is not an Ed25519 public key"))?;
+                AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                    key_type: OctetKeyPairType::OctetKeyPair,
+                    curve: EllipticCurve::Ed25519,
+                    x: URL_SAFE_NO_PAD.encode(public_key.as_bytes()),
+                })
+            }
+        };
+        let jwk = Jwk {
+            common: CommonParameters {
+                public_key_use: Some(PublicKeyUse::Signature),
+                key_algorithm: Some(match algorithm {
+                    Algorithm::RS256 => KeyAlgorithm::RS256,
+                    _ => KeyAlgorithm::EdDSA,
+                }),
+                key_id: Some(key.kid.clone()),
+                ..Default::default()
+            },
+            algorithm: key_params,
+        };
+        let verify_key = DecodingKey::from_jwk(&jwk)
+            .map_err(|e| Self::config_error(&key.kid, &e.to_string()))?;
+
+        let signing_key = match &key.private_key {
+            Some(path) => {
+                let private_pem = Self::read_pem(&key.kid, path)?;
+                let signing_key = match algorithm {
+                    Algorithm::RS256 => EncodingKey::from_rsa_pem(private_pem.as_bytes()),
+                    _ => EncodingKey::from_ed_pem(private_pem.as_bytes()),
+                }
+                .map_err(|_| Self::config_error(&key.kid, "has an invalid private key"))?;
+                Some(signing_key)
+            }
+            None => None,
+        };
+
+        Ok((signing_key, verify_key, Some(jwk)))
     }
 
-    pub fn extract_info(token: &str) -> Result<TokenInfo, Error> {
-        let claims = Self::get_claims(token)?;
-        let payload = Self::extract_payload(&claims)?;
+    /// Sign and verify a throwaway token so a mismatched key pair fails at startup.
+    fn check_key_pair(
+        algorithm: Algorithm,
+        signing_key: &EncodingKey,
+        verify_key: &DecodingKey,
+    ) -> Result<(), Error> {
+        let signature = crypto::sign(b"key-check", signing_key, algorithm)?;
+        match crypto::verify(&signature, b"key-check", verify_key, algorithm)? {
+            true => Ok(()),
+            false => Err(ErrorKind::InvalidSignature.into()),
+        }
+    }
 
-        Ok(payload)
+    fn read_pem(kid: &str, path: &str) -> DbResult<String> {
+        fs::read_to_string(path)
+            .map_err(|e| Self::config_error(kid, &format!("can not read `{}`: {}", path, e)))
     }
 
-    fn get_claims(token: &str) -> Result<BTreeMap<String, String>, Error> {
-        token.verify_with_key(&Self::get_key())
+    fn config_error(kid: &str, reason: &str) -> DbErr {
+        DbErr::Custom(format!("JWT key `{}` {}", kid, reason))
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(kid: &str, secret: &str) -> JwtKeyConfig {
+        JwtKeyConfig {
+            kid: kid.to_string(),
+            private_key: None,
+            public_key: None,
+            secret: Some(secret.to_string()),
+        }
+    }
+
+    fn jwt(active_kid: &str, keys: Vec<JwtKeyConfig>) -> Jwt {
+        Jwt::new(&JwtConfig {
+            algorithm: JwtAlgorithm::HS256,
+            issuer: "mms".to_string(),
+            audience: "mms-api".to_string(),
+            active_kid: Some(active_kid.to_string()),
+            keys,
+        })
+        .unwrap()
+    }
+
+    fn payload() -> TokenPayload {
+        TokenPayload {
+            user_id: Uuid::from_u128(1),
+            session_id: Some(Uuid::from_u128(2)),
+            limited: true,
+            actor_id: Some(Uuid::from_u128(3)),
+            expire: 60 * 1000,
+        }
+    }
+
+    #[test]
+    fn sign_and_verify_round_trip() {
+        let jwt = jwt("2024", vec![key("2024", "secret-2024")]);
+        let payload = payload();
+
+        let info = jwt.verify(&jwt.sign(payload).unwrap()).unwrap();
+        assert_eq!(info.user_id, payload.user_id);
+        assert_eq!(info.session_id, payload.session_id);
+        assert_eq!(info.actor_id, payload.actor_id);
+        assert!(info.limited);
+        assert_eq!(info.expires_at - info.issued_at, payload.expire);
+    }
+
+    #[test]
+    fn tokens_of_a_rotated_key_keep_working() {
+        let old = jwt("2024", vec![key("2024", "secret-2024")]);
+        let token = old.sign(payload()).unwrap();
+
+        let rotated = jwt(
+            "2025",
+            vec![key("2025", "secret-2025"), key("2024", "secret-2024")],
+        );
+        assert!(rotated.verify(&token).is_ok());
+        assert_eq!(
+            decode_header(rotated.sign(payload()).unwrap())
+                .unwrap()
+                .kid
+                .as_deref(),
+            Some("2025")
+        );
+
+        let dropped = jwt("2025", vec![key("2025", "secret-2025")]);
+        assert!(dropped.verify(&token).is_err());
+    }
+
+    #[test]
+    fn rejects_a_kid_signed_with_another_secret() {
+        let token = jwt("2024", vec![key("2024", "secret-2024")])
+            .sign(payload())
+            .unwrap();
+        let forged = jwt("2024", vec![key("2024", "another-secret")]);
+        assert!(forged.verify(&token).is_err());
+    }
+
+    #[test]
+    fn expired_tokens_only_pass_extract_info() {
+        let jwt = jwt("2024", vec![key("2024", "secret-2024")]);
+        let token = jwt
+            .sign(TokenPayload {
+                expire: -5 * 60 * 1000,
+                ..payload()
+            })
+            .unwrap();
 
-    fn extract_payload(claims: &BTreeMap<String, String>) -> Result<TokenInfo, Error> {
-        let info = claims.get("info").ok_or(Error::InvalidSignature)?;
-        serde_json::from_str(info).map_err(|_| Error::InvalidSignature)
+        assert!(jwt.verify(&token).is_err());
+        assert!(jwt.extract_info(&token).is_ok());
     }
 }
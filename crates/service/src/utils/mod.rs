@@ -1,5 +1,26 @@
 pub mod encrypt;
 pub mod jwt;
+pub mod password_policy;
 
 use sea_orm::DbErr;
+use serde::Serialize;
 pub type DbResult<T> = Result<T, DbErr>;
+
+/// One page of a listing, `page` counts from 1.
+#[derive(Debug, Serialize)]
+pub struct Paginated<T> {
+    pub items: Vec<T>,
+    pub total: u64,
+    pub page: u64,
+    #[serde(rename = "pageSize")]
+    pub page_size: u64,
+}
+
+/// One page of a listing walked with a cursor. Pass `next_cursor` back to
+/// get the following page, `None` once the end is reached.
+#[derive(Debug, Serialize)]
+pub struct CursorPage<T> {
+    pub items: Vec<T>,
+    #[serde(rename = "nextCursor")]
+    pub next_cursor: Option<String>,
+}
@@ -0,0 +1,223 @@
+use std::{collections::HashSet, fs};
+
+use sea_orm::DbErr;
+
+use super::{
+    encrypt::{generator_random_token, PassVerify},
+    DbResult,
+};
+use crate::config::PasswordPolicyConfig;
+
+/// Parts of an email or username shorter than this are too likely to show up
+/// by chance to be refused.
+const MIN_PERSONAL_PART: usize = 3;
+
+/// Decides which passwords are accepted and hashes them.
+pub struct PasswordPolicy {
+    min_length: usize,
+    max_length: usize,
+    denylist: HashSet<String>,
+    hasher: PassVerify,
+    /// Checked against when the email is unknown, the password can never match.
+    dummy_hash: String,
+}
+
+impl PasswordPolicy {
+    pub fn new(config: &PasswordPolicyConfig) -> DbResult<Self> {
+        if config.min_length == 0 || config.min_length > config.max_length {
+            return Err(DbErr::Custom(
+                "Password policy needs 0 < min_length <= max_length".to_string(),
+            ));
+        }
+
+        let denylist = match &config.denylist {
+            Some(path) => fs::read_to_string(path)
+                .map_err(|e| {
+                    DbErr::Custom(format!("Can not read password denylist `{}`: {}", path, e))
+                })?
+                .lines()
+                .map(|line| line.trim().to_lowercase())
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .collect(),
+            None => HashSet::new(),
+        };
+        let hasher = PassVerify::new(&config.argon2)
+            .map_err(|e| DbErr::Custom(format!("Invalid Argon2 parameters: {}", e)))?;
+        let dummy_hash = hasher.encrypt_password(&generator_random_token(16));
+
+        log::info!(
+            "Password policy: {} to {} characters, {} denied passwords",
+            config.min_length,
+            config.max_length,
+            denylist.len()
+        );
+
+        Ok(Self {
+            min_length: config.min_length,
+            max_length: config.max_length,
+            denylist,
+            hasher,
+            dummy_hash,
+        })
+    }
+
+    /// Refuse a password with every reason it breaks the policy, prefixed with
+    /// the field name so clients can show them next to the input.
+    pub fn check(&self, password: &str, email: &str, username: Option<&str>) -> DbResult<()> {
+        let reasons = self.violations(password, email, username);
+        if reasons.is_empty() {
+            return Ok(());
+        }
+
+        Err(DbErr::Custom(format!("password: {}", reasons.join("; "))))
+    }
+
+    fn violations(&self, password: &str, email: &str, username: Option<&str>) -> Vec<String> {
+        let mut reasons = Vec::new();
+        let length = password.chars().count();
+        let lowercase = password.to_lowercase();
+
+        if length < self.min_length {
+            reasons.push(format!(
+                "must be at least {} characters long",
+                self.min_length
+            ));
+        }
+        if length > self.max_length {
+            reasons.push(format!(
+                "must be at most {} characters long",
+                self.max_length
+            ));
+        }
+        if self.denylist.contains(&lowercase) {
+            reasons.push("is a commonly used or breached password".to_string());
+        }
+
+        let email = email.trim().to_lowercase();
+        let local_part = email.split('@').next().unwrap_or_default();
+        if Self::contains_part(&lowercase, &email) || Self::contains_part(&lowercase, local_part) {
+            reasons.push("must not contain your email address".to_string());
+        }
+        if username.is_some_and(|username| {
+            Self::contains_part(&lowercase, &username.trim().to_lowercase())
+        }) {
+            reasons.push("must not contain your username".to_string());
+        }
+
+        reasons
+    }
+
+    pub fn hash(&self, password: &str) -> String {
+        self.hasher.encrypt_password(password)
+    }
+
+    pub fn needs_rehash(&self, hashed: &str) -> bool {
+        self.hasher.needs_rehash(hashed)
+    }
+
+    pub fn dummy_hash(&self) -> &str {
+        &self.dummy_hash
+    }
+
+    fn contains_part(password: &str, part: &str) -> bool {
+        part.chars().count() >= MIN_PERSONAL_PART && password.contains(part)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env::temp_dir;
+
+    use super::*;
+    use crate::config::Argon2Config;
+
+    fn policy(denylist: Option<String>) -> PasswordPolicy {
+        PasswordPolicy::new(&PasswordPolicyConfig {
+            min_length: 8,
+            max_length: 16,
+            denylist,
+            // Cheap parameters, only the checks are under test.
+            argon2: Argon2Config {
+                memory_kib: 64,
+                iterations: 1,
+                parallelism: 1,
+            },
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn accepts_a_good_password() {
+        let policy = policy(None);
+        assert!(policy
+            .check("correct horse", "jane@example.com", Some("jane"))
+            .is_ok());
+    }
+
+    #[test]
+    fn checks_the_length() {
+        let policy = policy(None);
+        assert_eq!(
+            policy.violations("short", "jane@example.com", None),
+            ["must be at least 8 characters long"]
+        );
+        assert_eq!(
+            policy.violations("much too long of a password", "jane@example.com", None),
+            ["must be at most 16 characters long"]
+        );
+        // Characters are counted, not bytes.
+        assert!(policy
+            .violations("ääääääää", "jane@example.com", None)
+            .is_empty());
+    }
+
+    #[test]
+    fn refuses_denied_passwords_case_insensitively() {
+        let path = temp_dir().join(format!("denylist-{}.txt", generator_random_token(8)));
+        fs::write(&path, "# comment\n\nPassword1\n").unwrap();
+        let policy = policy(Some(path.to_string_lossy().to_string()));
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            policy.violations("PASSWORD1", "jane@example.com", None),
+            ["is a commonly used or breached password"]
+        );
+        assert!(policy
+            .violations("# comment", "jane@example.com", None)
+            .is_empty());
+    }
+
+    #[test]
+    fn refuses_personal_parts() {
+        let policy = policy(None);
+        assert_eq!(
+            policy.violations("xxJane@Example.com", "jane@example.com", None),
+            [
+                "must be at most 16 characters long",
+                "must not contain your email address"
+            ]
+        );
+        assert_eq!(
+            policy.violations("my-jane-pass", "jane@example.com", None),
+            ["must not contain your email address"]
+        );
+        assert_eq!(
+            policy.violations("hello-JDoe-99", "jane@example.com", Some("jdoe")),
+            ["must not contain your username"]
+        );
+        // Too short to be refused.
+        assert!(policy
+            .violations("jo-password", "jo@example.com", Some("jo"))
+            .is_empty());
+    }
+
+    #[test]
+    fn reports_every_reason() {
+        let err = policy(None).check("jane", "jane@example.com", Some("jane"));
+        assert_eq!(
+            err.unwrap_err().to_string(),
+            "Custom Error: password: must be at least 8 characters long; \
+             must not contain your email address; must not contain your username"
+        );
+    }
+}